@@ -0,0 +1,60 @@
+//! The throughput of the hot users repo reads, against an isolated test schema
+//! (see `db::testing::TestDb`) of the configured database. <br/>
+//! Run it using `cargo bench --features testing --bench users_repo`.
+//! The `unprepared` variants (not using the connection's statement cache) show
+//! how much the (static SQL) queries benefit from it.
+
+use backend::{
+    db::testing::TestDb,
+    domain::model::{Password, RegisterInput, UserEntry, UserId},
+    repos::UsersRepo,
+    AppUseCase,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+const USER_ENTRY_BY_ID: &str =
+    "SELECT id, email, username, password, salt, pwd_algo, bio, image, version, created_at, updated_at
+     FROM accounts WHERE id = $1 AND deleted_at IS NULL";
+
+fn users_repo_reads(c: &mut Criterion) {
+    //
+    let rt = Runtime::new().expect("Failed to create the runtime.");
+    let db = rt.block_on(TestDb::new());
+    let dbcp = Arc::new(db.dbcp.clone());
+    let repo = UsersRepo::new(dbcp.clone());
+    let reg = RegisterInput {
+        email: "bench@example.com".into(),
+        username: "bench".into(),
+        password: "bench-password".into(),
+    }
+    .validate()
+    .unwrap();
+    let pwd = Password::generate(reg.password()).unwrap();
+    let saved = rt
+        .block_on(repo.save(&reg, &pwd, None))
+        .expect("Failed to save the user.");
+    let id = UserId::from_trusted(saved.id);
+    let email = saved.email.to_string();
+
+    c.bench_function("get_by_id", |b| {
+        b.to_async(&rt)
+            .iter(|| repo.get_by_id(&id, AppUseCase::AnyTokenProtectedOperation))
+    });
+    c.bench_function("get_by_id_unprepared", |b| {
+        b.to_async(&rt).iter(|| {
+            sqlx::query_as::<_, UserEntry>(USER_ENTRY_BY_ID)
+                .bind(id.as_value())
+                .persistent(false)
+                .fetch_one(dbcp.as_ref())
+        })
+    });
+    c.bench_function("get_by_email", |b| {
+        b.to_async(&rt)
+            .iter(|| repo.get_by_email(&email, AppUseCase::UserLogin))
+    });
+}
+
+criterion_group!(benches, users_repo_reads);
+criterion_main!(benches);
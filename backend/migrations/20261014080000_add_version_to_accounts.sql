@@ -0,0 +1,3 @@
+
+-- The version of an account entry, used for optimistic locking on updates.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+
+-- The username identifies a profile, thus it must be unique.
+CREATE UNIQUE INDEX IF NOT EXISTS accounts_username_key ON accounts (username);
@@ -0,0 +1,4 @@
+
+-- A deactivated (soft deleted) account has a non-null `deleted_at`.
+-- Its entry is kept, so its email and username remain taken.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
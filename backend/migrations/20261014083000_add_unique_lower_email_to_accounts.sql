@@ -0,0 +1,7 @@
+
+-- Emails are stored in lowercase and looked up case-insensitively.
+-- Normalize any existing emails and make sure that emails that differ only in case
+-- cannot coexist. Note that normalizing fails if such duplicates already exist,
+-- in which case they must be resolved manually before running this migration.
+UPDATE accounts SET email = lower(email) WHERE email <> lower(email);
+CREATE UNIQUE INDEX IF NOT EXISTS accounts_lower_email_key ON accounts (lower(email));
@@ -0,0 +1,7 @@
+-- Accounts always have `created_at` and `updated_at` timestamps.
+-- Existing entries without them are backfilled with the epoch (1970-01-01 00:00:00 UTC),
+-- meaning "unknown", and an entry that has only `created_at` gets it as `updated_at` too.
+UPDATE accounts SET created_at = to_timestamp(0) WHERE created_at IS NULL;
+UPDATE accounts SET updated_at = created_at WHERE updated_at IS NULL;
+ALTER TABLE accounts ALTER COLUMN created_at SET NOT NULL;
+ALTER TABLE accounts ALTER COLUMN updated_at SET NOT NULL;
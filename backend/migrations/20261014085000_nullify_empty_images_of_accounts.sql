@@ -0,0 +1,2 @@
+-- An empty image is the same as no image, which is stored as NULL.
+UPDATE accounts SET image = NULL WHERE trim(image) = '';
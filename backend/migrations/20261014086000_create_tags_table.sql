@@ -0,0 +1,14 @@
+-- The tags, each one with a normalized (trimmed and lowercase) and unique name.
+CREATE TABLE IF NOT EXISTS tags (
+    id                    BIGSERIAL,
+    name                  VARCHAR(32)     NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT unique_tag_name   UNIQUE(name)
+);
+
+-- Include the tags already used by articles.
+INSERT INTO tags (name)
+    SELECT DISTINCT lower(trim(tag)) FROM tags_articles WHERE trim(tag) <> ''
+    ON CONFLICT DO NOTHING;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS comments (
+    id              BIGSERIAL,
+    body            VARCHAR(4096)               NOT NULL,
+    created_at      TIMESTAMP WITH TIME ZONE    NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMP WITH TIME ZONE    NOT NULL DEFAULT current_timestamp,
+    article_id      BIGINT                      NOT NULL,
+    author_id       BIGINT                      NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_article_id   FOREIGN KEY(article_id)  REFERENCES articles(id) ON DELETE CASCADE,
+    CONSTRAINT fk_author_id    FOREIGN KEY(author_id)   REFERENCES accounts(id)
+);
+
+CREATE INDEX IF NOT EXISTS comments_article_id_idx ON comments (article_id);
@@ -0,0 +1,3 @@
+-- Users cannot follow themselves (see `SELF_FOLLOW_MSG`). This is also checked by the app, this is a defense in depth.
+DELETE FROM followings WHERE user_id = followed_user_id;
+ALTER TABLE followings ADD CONSTRAINT followings_no_self_follow CHECK (user_id <> followed_user_id);
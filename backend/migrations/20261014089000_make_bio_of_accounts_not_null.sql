@@ -0,0 +1,4 @@
+-- The bio is always present (an empty one by default), as `User.bio` is not optional.
+UPDATE accounts SET bio = '' WHERE bio IS NULL;
+ALTER TABLE accounts ALTER COLUMN bio SET DEFAULT '';
+ALTER TABLE accounts ALTER COLUMN bio SET NOT NULL;
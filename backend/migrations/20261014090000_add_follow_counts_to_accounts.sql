@@ -0,0 +1,8 @@
+-- The denormalized counts of followers and followed users, so that profiles
+-- do not need to count the followings on each read.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS follower_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS following_count BIGINT NOT NULL DEFAULT 0;
+
+UPDATE accounts a SET
+    follower_count = (SELECT count(*) FROM followings f WHERE f.followed_user_id = a.id),
+    following_count = (SELECT count(*) FROM followings f WHERE f.user_id = a.id);
@@ -0,0 +1,14 @@
+-- The keys of the (client provided) idempotent registrations, so that
+-- a repeated request gets the originally created user. The expired keys
+-- (see `IDEMPOTENCY_KEY_TTL_HOURS`) are ignored, and eventually deleted.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key             VARCHAR(255)                NOT NULL,
+    user_id         BIGINT                      NOT NULL,
+    created_at      TIMESTAMP WITH TIME ZONE    NOT NULL DEFAULT current_timestamp,
+
+    PRIMARY KEY(key),
+
+    CONSTRAINT fk_user_id   FOREIGN KEY(user_id)    REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_created_at_idx ON idempotency_keys (created_at);
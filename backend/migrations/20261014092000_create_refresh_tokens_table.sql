@@ -0,0 +1,21 @@
+-- The refresh tokens (only their hashes are stored). Each rotation creates a new token
+-- in the same family, and marks the old one as used. Presenting an already used token
+-- means it was stolen (or replayed), thus the whole family gets revoked.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id              BIGSERIAL,
+    user_id         BIGINT                      NOT NULL,
+    family_id       UUID                        NOT NULL,
+    token_hash      VARCHAR(64)                 NOT NULL,
+    created_at      TIMESTAMP WITH TIME ZONE    NOT NULL DEFAULT current_timestamp,
+    expires_at      TIMESTAMP WITH TIME ZONE    NOT NULL,
+    used_at         TIMESTAMP WITH TIME ZONE,
+    revoked_at      TIMESTAMP WITH TIME ZONE,
+
+    PRIMARY KEY(id),
+    UNIQUE(token_hash),
+
+    CONSTRAINT fk_user_id   FOREIGN KEY(user_id)    REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens (family_id);
+CREATE INDEX IF NOT EXISTS refresh_tokens_user_id_idx ON refresh_tokens (user_id);
@@ -0,0 +1,6 @@
+-- The confusable skeleton of the username (see `Username::skeleton`), so that the usernames
+-- that look alike (by case or by confusable characters) cannot be used by different accounts.
+-- The existing accounts are backfilled by `UsersRepo::backfill_username_skeletons`, as it is computed by the app.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS username_skeleton VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS accounts_username_skeleton_key ON accounts (username_skeleton);
@@ -0,0 +1,9 @@
+-- The algorithm (KDF) that each password was hashed with (see `PasswordAlgo`),
+-- so that its verification dispatches to the matching one.
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS pwd_algo VARCHAR(16);
+
+-- The existing passwords are either Argon2 (in PHC string format) or legacy (salted MD5) ones.
+UPDATE accounts SET pwd_algo = CASE WHEN password LIKE '$argon2%' THEN 'argon2id' ELSE 'md5' END;
+
+ALTER TABLE accounts ALTER COLUMN pwd_algo SET NOT NULL;
+ALTER TABLE accounts ALTER COLUMN pwd_algo SET DEFAULT 'argon2id';
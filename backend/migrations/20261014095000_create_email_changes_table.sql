@@ -0,0 +1,15 @@
+-- The pending email changes (only the hashes of their confirmation tokens are stored).
+-- The account's email is changed only once the change is confirmed, thus a user has
+-- at most one pending change (a newer request replaces it).
+CREATE TABLE IF NOT EXISTS email_changes (
+    user_id         BIGINT                      NOT NULL,
+    new_email       VARCHAR(255)                NOT NULL,
+    token_hash      VARCHAR(64)                 NOT NULL,
+    created_at      TIMESTAMP WITH TIME ZONE    NOT NULL DEFAULT current_timestamp,
+    expires_at      TIMESTAMP WITH TIME ZONE    NOT NULL,
+
+    PRIMARY KEY(user_id),
+    UNIQUE(token_hash),
+
+    CONSTRAINT fk_user_id   FOREIGN KEY(user_id)    REFERENCES accounts(id) ON DELETE CASCADE
+);
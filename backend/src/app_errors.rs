@@ -4,9 +4,32 @@
 //!
 //! Different cases are considered such as:
 //! - for a database error with code 23505 (see its [postgres specifics](https://www.postgresql.org/docs/9.3/errcodes-appendix.html))
+//!
+//! The `From<(sqlx::Error, AppUseCase)>` mapping of the representative errors is:
+//!
+//! | `sqlx::Error`                     | `AppUseCase`                         | `AppError`                                  |
+//! |-----------------------------------|--------------------------------------|---------------------------------------------|
+//! | `RowNotFound`                     | `UserLogin`                          | `Unauthorized("wrong credentials")`         |
+//! | `RowNotFound`                     | `GetUserProfile`                     | `NotFound("profile")`                       |
+//! | `RowNotFound`                     | `*Article` (except `ListArticles`)   | `NotFound("article")`                       |
+//! | `RowNotFound`                     | `ListArticles`                       | `NotFound("articles")`                      |
+//! | `Database` 23505 (username)       | `UserRegistration`, `UpdateUser`     | `AlreadyExists("username")`                 |
+//! | `Database` 23505 (skeleton)       | `UserRegistration`, `UpdateUser`     | `Conflict(..)`                              |
+//! | `Database` 23505 (other)          | `UserRegistration`, `UpdateUser`     | `AlreadyExists("email")`                    |
+//! | `Database` 23505                  | `FollowUser`, `FavoriteArticle`      | `Ignorable` (it already exists)             |
+//! | `Database` 23505                  | `CreateArticle`, `UpdateArticle`     | `AlreadyExists("slug")`                     |
+//! | `Database` 23514 (no self-follow) | `FollowUser`                         | `InvalidRequest(..)`                        |
+//! | `Database` 57014 (timed out)      | any                                  | `Unavailable("database")`                   |
+//! | `PoolTimedOut` (and anything else)| any                                  | `InternalErr`                               |
+//!
+//! Changing any of these changes the responded status codes (see `AppError::status_code`).
 
+use std::collections::HashMap;
 use thiserror::Error;
 
+/// The reason of rejecting a self-follow, whether by the app or by the database.
+pub const SELF_FOLLOW_MSG: &str = "users cannot follow themselves";
+
 #[derive(Debug)]
 pub enum AppUseCase {
     UserRegistration,
@@ -15,6 +38,12 @@ pub enum AppUseCase {
     UpdateUser,
     GetUserProfile,
     FollowUser,
+    CreateArticle,
+    UpdateArticle,
+    DeleteArticle,
+    GetArticle,
+    ListArticles,
+    FavoriteArticle,
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -25,6 +54,12 @@ pub enum AppError {
     #[error("{0} already exists")]
     AlreadyExists(String),
 
+    #[error("conflict: {0}")]
+    Conflict(String),
+
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
     #[error("")]
     Ignorable,
 
@@ -39,6 +74,121 @@ pub enum AppError {
 
     #[error("unauthorized: {0}")]
     Unauthorized(String),
+
+    #[error("{0} is unavailable")]
+    Unavailable(String),
+
+    #[error("invalid fields: {}", fields_of(.0))]
+    Validation(HashMap<String, Vec<String>>),
+}
+
+impl AppError {
+    //
+    /// A stable, machine-readable code of the error, to be used by the clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::AlreadyExists(_) => "already_exists",
+            AppError::Conflict(_) => "conflict",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Ignorable => "ignorable",
+            AppError::InternalErr => "internal",
+            AppError::InvalidRequest(_) => "invalid_input",
+            AppError::NotFound(_) => "not_found",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Unavailable(_) => "db_unavailable",
+            AppError::Validation(_) => "validation",
+        }
+    }
+
+    /// The terse, end-user facing message of the error, to be included in responses. <br/>
+    /// Unlike the `Display` one (that is meant for logs), it does not reveal internal details.
+    pub fn user_message(&self) -> String {
+        match self {
+            AppError::AlreadyExists(what) => format!("{what} already exists"),
+            AppError::Conflict(_) => "the request conflicts with the current state".into(),
+            AppError::Forbidden(_) => "the operation is not allowed".into(),
+            AppError::Ignorable | AppError::InternalErr => "something went wrong".into(),
+            AppError::InvalidRequest(msg) => format!("invalid request: {msg}"),
+            AppError::NotFound(what) => format!("{what} not found"),
+            AppError::Unauthorized(msg) => format!("unauthorized: {msg}"),
+            AppError::Unavailable(_) => "the service is temporarily unavailable".into(),
+            AppError::Validation(errors) => format!("invalid fields: {}", fields_of(errors)),
+        }
+    }
+
+    /// Same as `user_message`, but the errors that tell nothing to the end-user
+    /// (such as an internal error) are described as a failure of the `usecase`.
+    pub fn user_message_for(&self, usecase: &AppUseCase) -> String {
+        match self {
+            AppError::Ignorable | AppError::InternalErr | AppError::Unavailable(_) => {
+                format!("could not {}", usecase.action())
+            }
+            _ => self.user_message(),
+        }
+    }
+}
+
+impl AppUseCase {
+    //
+    /// What the use case does, as used in the end-user facing messages.
+    fn action(&self) -> &'static str {
+        match self {
+            AppUseCase::UserRegistration => "register",
+            AppUseCase::UserLogin => "log in",
+            AppUseCase::AnyTokenProtectedOperation => "complete the operation",
+            AppUseCase::UpdateUser => "update the user",
+            AppUseCase::GetUserProfile => "get the profile",
+            AppUseCase::FollowUser => "update the following",
+            AppUseCase::CreateArticle => "create the article",
+            AppUseCase::UpdateArticle => "update the article",
+            AppUseCase::DeleteArticle => "delete the article",
+            AppUseCase::GetArticle => "get the article",
+            AppUseCase::ListArticles => "list the articles",
+            AppUseCase::FavoriteArticle => "update the favorite",
+        }
+    }
+}
+
+/// The (sorted) names of the fields that failed the validation.
+fn fields_of(errors: &HashMap<String, Vec<String>>) -> String {
+    let mut fields: Vec<&str> = errors.keys().map(String::as_str).collect();
+    fields.sort_unstable();
+    fields.join(", ")
+}
+
+/// An accumulator of field-level validation errors, so that all of
+/// an input's invalid fields are reported at once (in an `AppError::Validation`).
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: HashMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+    //
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an error `msg` (such as `can't be blank`) of the `field`.
+    pub fn add(&mut self, field: &str, msg: &str) -> &mut Self {
+        self.errors
+            .entry(field.to_string())
+            .or_default()
+            .push(msg.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Ok` if no error was added, otherwise an `AppError::Validation` with all of them.
+    pub fn into_result(self) -> Result<()> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(AppError::Validation(self.errors)),
+        }
+    }
 }
 
 impl From<(sqlx::Error, AppUseCase)> for AppError {
@@ -46,18 +196,21 @@ impl From<(sqlx::Error, AppUseCase)> for AppError {
     fn from(ctx: (sqlx::Error, AppUseCase)) -> Self {
         log::debug!("from((sqlx::Error, AppUseCase)): ctx={:?}", ctx);
         let err = ctx.0;
+        // A timed out statement is the same for any use case.
+        if crate::db::is_statement_timeout(&err) {
+            return AppError::Unavailable("database".into());
+        }
         // Start with the use case as the context, and then cover the possible errors within.
         match ctx.1 {
-            AppUseCase::UserRegistration => match &err.into_database_error() {
-                Some(e) => match e.code() {
-                    Some(code) => match code.as_ref() {
-                        "23505" => AppError::AlreadyExists("email".into()),
-                        _ => AppError::InternalErr,
-                    },
+            AppUseCase::UserRegistration | AppUseCase::UpdateUser => {
+                match accounts_unique_violation(&err) {
+                    Some("username_skeleton") => {
+                        AppError::Conflict("username is too similar to an existing one".into())
+                    }
+                    Some(field) => AppError::AlreadyExists(field.into()),
                     None => AppError::InternalErr,
-                },
-                None => AppError::InternalErr,
-            },
+                }
+            }
 
             AppUseCase::UserLogin => match &err {
                 sqlx::Error::RowNotFound => AppError::Unauthorized("wrong credentials".into()),
@@ -73,6 +226,10 @@ impl From<(sqlx::Error, AppUseCase)> for AppError {
                 Some(dbe) => match dbe.code() {
                     Some(code) => match code.as_ref() {
                         "23505" => AppError::Ignorable,
+                        // The constraint name is part of the message.
+                        "23514" if dbe.message().contains("followings_no_self_follow") => {
+                            AppError::InvalidRequest(SELF_FOLLOW_MSG.into())
+                        }
                         _ => AppError::InternalErr,
                     },
                     None => AppError::InternalErr,
@@ -80,17 +237,69 @@ impl From<(sqlx::Error, AppUseCase)> for AppError {
                 None => AppError::InternalErr,
             },
 
+            AppUseCase::CreateArticle | AppUseCase::UpdateArticle => match &err {
+                sqlx::Error::RowNotFound => AppError::NotFound("article".into()),
+                sqlx::Error::Database(e) => match e.code() {
+                    Some(code) if code == "23505" => AppError::AlreadyExists("slug".into()),
+                    _ => AppError::InternalErr,
+                },
+                _ => AppError::InternalErr,
+            },
+
+            AppUseCase::DeleteArticle | AppUseCase::GetArticle => match &err {
+                sqlx::Error::RowNotFound => AppError::NotFound("article".into()),
+                _ => AppError::InternalErr,
+            },
+
+            AppUseCase::ListArticles => match &err {
+                sqlx::Error::RowNotFound => AppError::NotFound("articles".into()),
+                _ => AppError::InternalErr,
+            },
+
+            AppUseCase::FavoriteArticle => match &err {
+                sqlx::Error::RowNotFound => AppError::NotFound("article".into()),
+                sqlx::Error::Database(e) => match e.code() {
+                    // Already favorited.
+                    Some(code) if code == "23505" => AppError::Ignorable,
+                    _ => AppError::InternalErr,
+                },
+                _ => AppError::InternalErr,
+            },
+
             // Anything else is treated as an internal error.
             _ => AppError::InternalErr,
         }
     }
 }
 
+/// Get the (accounts) field whose uniqueness is violated, in case of a unique violation error.
+fn accounts_unique_violation(err: &sqlx::Error) -> Option<&'static str> {
+    //
+    let e = err.as_database_error()?;
+    match e.code()?.as_ref() {
+        // The constraint name is part of the message.
+        "23505" => {
+            let msg = e.message();
+            match msg.contains("accounts_username_key") {
+                true => Some("username"),
+                false => match msg.contains("accounts_username_skeleton_key") {
+                    true => Some("username_skeleton"),
+                    false => Some("email"),
+                },
+            }
+        }
+        _ => None,
+    }
+}
+
 impl From<sqlx::Error> for AppError {
     //
     fn from(err: sqlx::Error) -> Self {
         let mut app_err = AppError::Ignorable;
         log::debug!("from(sqlx:Error): err={:?}", err);
+        if crate::db::is_statement_timeout(&err) {
+            return AppError::Unavailable("database".into());
+        }
         if err.as_database_error().is_some() {
             // TODO: For now, any db error is classified as internal error.
             app_err = AppError::InternalErr
@@ -99,9 +308,206 @@ impl From<sqlx::Error> for AppError {
     }
 }
 
-impl From<jsonwebtoken::errors::Error> for AppError {
-    //
-    fn from(err: jsonwebtoken::errors::Error) -> Self {
-        AppError::Unauthorized(err.to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_a_missing_row_of_each_article_usecase() {
+        for (usecase, what) in [
+            (AppUseCase::CreateArticle, "article"),
+            (AppUseCase::UpdateArticle, "article"),
+            (AppUseCase::DeleteArticle, "article"),
+            (AppUseCase::GetArticle, "article"),
+            (AppUseCase::ListArticles, "articles"),
+            (AppUseCase::FavoriteArticle, "article"),
+        ] {
+            let case = format!("{usecase:?}");
+            let err = AppError::from((sqlx::Error::RowNotFound, usecase));
+            assert!(
+                matches!(&err, AppError::NotFound(w) if w == what),
+                "for {case}"
+            );
+            assert_eq!(err.user_message(), format!("{what} not found"));
+        }
+    }
+
+    #[test]
+    fn the_codes_are_stable() {
+        for (err, code) in [
+            (AppError::AlreadyExists("email".into()), "already_exists"),
+            (AppError::Conflict("version".into()), "conflict"),
+            (AppError::Forbidden("not yours".into()), "forbidden"),
+            (AppError::Ignorable, "ignorable"),
+            (AppError::InternalErr, "internal"),
+            (AppError::InvalidRequest("bad".into()), "invalid_input"),
+            (AppError::NotFound("user".into()), "not_found"),
+            (AppError::Unauthorized("expired".into()), "unauthorized"),
+            (AppError::Unavailable("database".into()), "db_unavailable"),
+            (AppError::Validation(HashMap::new()), "validation"),
+        ] {
+            assert_eq!(err.code(), code, "for {err:?}");
+        }
+    }
+
+    #[test]
+    fn a_failed_registration_has_a_generic_public_message() {
+        let err = AppError::from((sqlx::Error::PoolTimedOut, AppUseCase::UserRegistration));
+        assert!(matches!(err, AppError::InternalErr));
+        assert_eq!(
+            err.user_message_for(&AppUseCase::UserRegistration),
+            "could not register"
+        );
+        // The (logged) `Display` has the specifics, unlike the public message.
+        let err = AppError::Unavailable("database".into());
+        assert_eq!(err.to_string(), "database is unavailable");
+        assert_eq!(
+            err.user_message_for(&AppUseCase::UserRegistration),
+            "could not register"
+        );
+        // The errors that the end-user can act on are kept.
+        let err = AppError::AlreadyExists("email".into());
+        assert_eq!(
+            err.user_message_for(&AppUseCase::UserRegistration),
+            "email already exists"
+        );
+    }
+
+    /// A database error of the `code` (SQLSTATE), having the `message`.
+    #[derive(Debug)]
+    struct FakeDbError {
+        code: &'static str,
+        message: &'static str,
+    }
+
+    impl std::fmt::Display for FakeDbError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    impl std::error::Error for FakeDbError {}
+
+    impl sqlx::error::DatabaseError for FakeDbError {
+        fn message(&self) -> &str {
+            self.message
+        }
+
+        fn code(&self) -> Option<std::borrow::Cow<'_, str>> {
+            Some(self.code.into())
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+    }
+
+    fn db_err(code: &'static str, message: &'static str) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(FakeDbError { code, message }))
+    }
+
+    fn unique_violation(constraint: &'static str) -> sqlx::Error {
+        // The message (as Postgres has it) includes the constraint name.
+        match constraint {
+            "accounts_username_key" => db_err(
+                "23505",
+                "duplicate key value violates unique constraint \"accounts_username_key\"",
+            ),
+            "accounts_username_skeleton_key" => db_err(
+                "23505",
+                "duplicate key value violates unique constraint \"accounts_username_skeleton_key\"",
+            ),
+            _ => db_err(
+                "23505",
+                "duplicate key value violates unique constraint \"accounts_email_key\"",
+            ),
+        }
+    }
+
+    #[test]
+    fn maps_a_missing_row_per_usecase() {
+        let err = AppError::from((sqlx::Error::RowNotFound, AppUseCase::UserLogin));
+        assert!(matches!(err, AppError::Unauthorized(msg) if msg == "wrong credentials"));
+        let err = AppError::from((sqlx::Error::RowNotFound, AppUseCase::GetUserProfile));
+        assert!(matches!(err, AppError::NotFound(what) if what == "profile"));
+        let err = AppError::from((sqlx::Error::RowNotFound, AppUseCase::UserRegistration));
+        assert!(matches!(err, AppError::InternalErr));
+    }
+
+    #[test]
+    fn maps_a_unique_violation_per_usecase() {
+        for usecase in [AppUseCase::UserRegistration, AppUseCase::UpdateUser] {
+            let case = format!("{usecase:?}");
+            let err = AppError::from((unique_violation("accounts_email_key"), usecase));
+            assert!(
+                matches!(err, AppError::AlreadyExists(f) if f == "email"),
+                "for {case}"
+            );
+        }
+        let err = AppError::from((
+            unique_violation("accounts_username_key"),
+            AppUseCase::UserRegistration,
+        ));
+        assert!(matches!(err, AppError::AlreadyExists(f) if f == "username"));
+        let err = AppError::from((
+            unique_violation("accounts_username_skeleton_key"),
+            AppUseCase::UserRegistration,
+        ));
+        assert!(matches!(err, AppError::Conflict(_)));
+
+        let err = AppError::from((unique_violation(""), AppUseCase::FollowUser));
+        assert!(matches!(err, AppError::Ignorable));
+        let err = AppError::from((unique_violation(""), AppUseCase::FavoriteArticle));
+        assert!(matches!(err, AppError::Ignorable));
+        let err = AppError::from((unique_violation(""), AppUseCase::CreateArticle));
+        assert!(matches!(err, AppError::AlreadyExists(f) if f == "slug"));
+    }
+
+    #[test]
+    fn maps_a_self_follow_check_violation() {
+        let err = AppError::from((
+            db_err(
+                "23514",
+                "new row for relation \"followings\" violates check constraint \"followings_no_self_follow\"",
+            ),
+            AppUseCase::FollowUser,
+        ));
+        assert!(matches!(err, AppError::InvalidRequest(msg) if msg == SELF_FOLLOW_MSG));
+        // Any other check violation is not the end-user's fault.
+        let err = AppError::from((db_err("23514", "other check"), AppUseCase::FollowUser));
+        assert!(matches!(err, AppError::InternalErr));
+    }
+
+    #[test]
+    fn maps_a_statement_timeout_to_unavailable_for_any_usecase() {
+        for usecase in [
+            AppUseCase::UserRegistration,
+            AppUseCase::UserLogin,
+            AppUseCase::FollowUser,
+            AppUseCase::GetArticle,
+        ] {
+            let case = format!("{usecase:?}");
+            let err = AppError::from((db_err("57014", "canceling statement"), usecase));
+            assert!(matches!(err, AppError::Unavailable(_)), "for {case}");
+        }
+        let err = AppError::from(db_err("57014", "canceling statement"));
+        assert!(matches!(err, AppError::Unavailable(_)));
+    }
+
+    #[test]
+    fn maps_a_pool_timeout_to_an_internal_error() {
+        for usecase in [AppUseCase::UserRegistration, AppUseCase::GetArticle] {
+            let case = format!("{usecase:?}");
+            let err = AppError::from((sqlx::Error::PoolTimedOut, usecase));
+            assert!(matches!(err, AppError::InternalErr), "for {case}");
+        }
     }
 }
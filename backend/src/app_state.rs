@@ -1,7 +1,7 @@
 use crate::{
     db::DbConnPool,
     domain::logic::{ArticlesMgr, AuthMgr},
-    repos::{ArticlesRepo, UsersRepo},
+    repos::{CachedUserRepo, MeteredUserRepo, Repos, UserRepository, UsersRepo},
 };
 use axum::extract::FromRef;
 use std::sync::Arc;
@@ -10,7 +10,10 @@ use std::sync::Arc;
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub dbcp: Arc<DbConnPool>,
-    pub user_repo: Arc<UsersRepo>,
+    pub repos: Arc<Repos>,
+    /// The (metered and cached) users repo, the only one that is used by the handlers and the managers. <br/>
+    /// The profiles that are cached by it are invalidated on the writes that go through it.
+    pub user_repository: Arc<dyn UserRepository>,
     pub auth_mgr: AuthMgr,
     pub articles_mgr: ArticlesMgr,
 }
@@ -19,13 +22,17 @@ impl AppState {
     //
     pub fn new(dbcp: DbConnPool) -> Self {
         let dbcp = Arc::new(dbcp);
-        let user_repo = Arc::new(UsersRepo::new(dbcp.clone()));
-        let auth_mgr = AuthMgr::new(user_repo.clone());
-        let articles_repo = ArticlesRepo::new(dbcp.clone());
-        let articles_mgr = ArticlesMgr::new(articles_repo, user_repo.clone());
+        let repos = Arc::new(Repos::new(dbcp.clone()));
+        // The calls that reach the users repo (thus not the cached ones) are counted.
+        let user_repository: Arc<dyn UserRepository> = Arc::new(CachedUserRepo::new(Arc::new(
+            MeteredUserRepo::new(Arc::new(UsersRepo::new(dbcp.clone()))),
+        )));
+        let auth_mgr = AuthMgr::new(user_repository.clone());
+        let articles_mgr = ArticlesMgr::new(repos.articles.clone(), user_repository.clone());
         Self {
             dbcp,
-            user_repo,
+            repos,
+            user_repository,
             auth_mgr,
             articles_mgr,
         }
@@ -0,0 +1,158 @@
+//! The issuance and verification of the (JWT based) access tokens, tied to a `UserId`. <br/>
+//! The tokens are (HS256) signed using the secret that is set on startup from the config.
+//! The (opaque) refresh tokens are stored and rotated by `RefreshTokensRepo`.
+
+use crate::{domain::model::UserId, AppError};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// For how long (in minutes) an access token can be used.
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 5;
+
+/// For how long (in days) a refresh token can be used.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// For how long (in hours) an email change can be confirmed.
+pub const EMAIL_CHANGE_TOKEN_TTL_HOURS: i64 = 24;
+
+/// The secret used for signing and verifying the tokens.
+static JWT_SECRET: OnceLock<Secret<String>> = OnceLock::new();
+
+/// Set the secret used for signing and verifying the tokens. <br/>
+/// It is meant to be called once, on startup. Any subsequent call is ignored.
+pub fn set_jwt_secret(secret: Secret<String>) {
+    if JWT_SECRET.set(secret).is_err() {
+        log::warn!("Ignoring the JWT secret, as it was already set.");
+    }
+}
+
+/// Get the secret used for signing and verifying the tokens.
+pub(crate) fn jwt_secret() -> Result<&'static [u8], AppError> {
+    match JWT_SECRET.get() {
+        Some(secret) => Ok(secret.expose_secret().as_bytes()),
+        None => {
+            log::error!("The JWT secret is not set.");
+            Err(AppError::InternalErr)
+        }
+    }
+}
+
+/// The claims of a token that are relevant for authenticating its user.
+#[derive(Debug, Deserialize, Serialize)]
+struct TokenClaims {
+    sub: i64,
+    exp: i64,
+    iat: i64,
+}
+
+/// Issue a token for the user with the provided id, that expires after `ttl`.
+pub fn issue_token(user_id: &UserId, ttl: Duration) -> Result<String, AppError> {
+    //
+    let iat = Utc::now();
+    let claims = TokenClaims {
+        sub: user_id.as_value(),
+        exp: (iat + ttl).timestamp(),
+        iat: iat.timestamp(),
+    };
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(jwt_secret()?),
+    )
+    .map_err(|err| {
+        log::error!("Failed to issue the token: {err}");
+        AppError::InternalErr
+    })
+}
+
+/// Verify the provided token, returning the id of its user. <br/>
+/// An expired, tampered (wrongly signed), or malformed token is `AppError::Unauthorized`.
+pub fn verify_token(token: &str) -> Result<UserId, AppError> {
+    //
+    let mut validation = Validation::new(Algorithm::HS256);
+    // Expiring exactly now is expired.
+    validation.leeway = 0;
+    match jsonwebtoken::decode::<TokenClaims>(
+        token,
+        &DecodingKey::from_secret(jwt_secret()?),
+        &validation,
+    ) {
+        Ok(data) => UserId::try_new(data.claims.sub)
+            .map_err(|_| AppError::Unauthorized("token has an invalid subject".into())),
+        Err(err) => Err(match err.kind() {
+            ErrorKind::ExpiredSignature => AppError::Unauthorized("token is expired".into()),
+            ErrorKind::InvalidSignature => AppError::Unauthorized("token is tampered".into()),
+            _ => AppError::Unauthorized("token is invalid".into()),
+        }),
+    }
+}
+
+/// Generate a new (random and opaque) refresh token.
+pub fn new_refresh_token() -> String {
+    // Two random (v4) UUIDs provide 244 random bits.
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Hash the refresh token, as only its hash is stored. Being random (not a password),
+/// a fast hash is enough.
+pub fn hash_refresh_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() {
+        set_jwt_secret(Secret::new("test_jwt_secret".into()));
+    }
+
+    #[test]
+    fn verifies_an_issued_token() {
+        setup();
+        let token = issue_token(&UserId::from_trusted(7), Duration::minutes(5)).unwrap();
+        assert_eq!(verify_token(&token).unwrap().as_value(), 7);
+    }
+
+    #[test]
+    fn rejects_an_expired_token() {
+        setup();
+        let token = issue_token(&UserId::from_trusted(7), Duration::seconds(-1)).unwrap();
+        assert!(matches!(
+            verify_token(&token),
+            Err(AppError::Unauthorized(msg)) if msg == "token is expired"
+        ));
+    }
+
+    #[test]
+    fn rejects_a_tampered_token() {
+        setup();
+        let token = issue_token(&UserId::from_trusted(7), Duration::minutes(5)).unwrap();
+        let other = issue_token(&UserId::from_trusted(8), Duration::minutes(5)).unwrap();
+        // The claims (payload) of the other token, with the signature of the first one.
+        let parts: Vec<&str> = token.split('.').collect();
+        let other_parts: Vec<&str> = other.split('.').collect();
+        let tampered = format!("{}.{}.{}", parts[0], other_parts[1], parts[2]);
+        assert!(matches!(
+            verify_token(&tampered),
+            Err(AppError::Unauthorized(msg)) if msg == "token is tampered"
+        ));
+    }
+
+    #[test]
+    fn rejects_a_malformed_token() {
+        setup();
+        assert!(matches!(
+            verify_token("not-a-token"),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+}
@@ -1,17 +1,20 @@
 use axum::{
     extract::State,
+    http::StatusCode,
     response::IntoResponse,
     routing::{get, post, put},
     Json, Router,
 };
 use axum_extra::routing::SpaRouter;
 use backend::{
+    auth::set_jwt_secret,
     config::get_config,
-    db::{init_db_pool, ping_db},
+    db::{connect, log_pool_stats_periodically, ping, pool_stats, run_migrations, PoolConfig},
+    domain::model::{ImageCheckMode, ImageChecker, ImageHeadClient, ImageUrlResolver},
     web_api::{
-        create_article, delete_article, follow_user, get_articles, get_current_user,
-        get_user_profile, login_user, register_user, unfollow_user, update_article,
-        update_current_user,
+        confirm_email_change, create_article, delete_article, favorite_article, follow_user,
+        get_articles, get_current_user, get_feed, get_user_profile, login_user, register_user,
+        unfavorite_article, unfollow_user, update_article, update_current_user,
     },
     AppState,
 };
@@ -21,6 +24,7 @@ use std::{
     net::{IpAddr, Ipv6Addr, SocketAddr},
     process::exit,
     str::FromStr,
+    time::Duration,
 };
 use tokio::signal::{self, unix::SignalKind};
 use tower_http::{
@@ -44,21 +48,38 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let app_cfg = get_config().expect("Failed to load the app config.");
+    set_jwt_secret(app_cfg.auth.jwt_secret);
+    ImageUrlResolver::set_global(ImageUrlResolver::new(app_cfg.images.base_url));
+    ImageChecker::set_global(ImageChecker::new(
+        app_cfg.images.check,
+        image_head_client(app_cfg.images.check),
+    ));
 
-    let db_conn_pool = init_db_pool(&app_cfg)
-        .await
-        .expect("Failed to connect to database.");
-    match ping_db(&db_conn_pool).await {
-        true => log::info!(
+    let db_conn_pool = connect(
+        &app_cfg.database,
+        PoolConfig::from_env().expect("Invalid database pool config."),
+    )
+    .await
+    .expect("Failed to connect to database.");
+    match ping(&db_conn_pool).await {
+        Ok(_) => log::info!(
             "Connected to the database (with {} conns).",
             db_conn_pool.size()
         ),
-        false => {
-            log::error!("Failed to ping the database. Exiting now.");
+        Err(err) => {
+            log::error!("Failed to ping the database: {err}. Exiting now.");
+            exit(1);
+        }
+    }
+    if !opt.skip_migrations {
+        if let Err(err) = run_migrations(&db_conn_pool).await {
+            log::error!("Failed to migrate the database: {err}. Exiting now.");
             exit(1);
         }
     }
 
+    log_pool_stats_periodically(db_conn_pool.clone(), Duration::from_secs(60));
+
     let state = AppState::new(db_conn_pool);
 
     let routes = routes(state, opt.assets_dir);
@@ -88,16 +109,22 @@ fn routes(state: AppState, assets_dir: String) -> Router {
         .route("/api/users/login", post(login_user))
         .route("/api/users", post(register_user))
         .route("/api/user", get(get_current_user).put(update_current_user))
+        .route("/api/user/email/confirm", post(confirm_email_change))
         .route("/api/profiles/:username", get(get_user_profile))
         .route(
             "/api/profiles/:username/follow",
             post(follow_user).delete(unfollow_user),
         )
         .route("/api/articles", get(get_articles).post(create_article))
+        .route("/api/articles/feed", get(get_feed))
         .route(
             "/api/articles/:slug",
             put(update_article).delete(delete_article),
         )
+        .route(
+            "/api/articles/:slug/favorite",
+            post(favorite_article).delete(unfavorite_article),
+        )
         .layer(tracing_layer)
         .layer(cors_layer)
         .with_state(state)
@@ -106,9 +133,15 @@ fn routes(state: AppState, assets_dir: String) -> Router {
 
 async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
     //
-    match ping_db(&state.dbcp).await {
-        true => Json(json!({ "database": "ok" })),
-        false => Json(json!({ "database": "err" })),
+    match ping(&state.dbcp).await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "database": "ok", "pool": pool_stats(&state.dbcp) })),
+        ),
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "database": "err" })),
+        ),
     }
 }
 
@@ -158,4 +191,30 @@ struct Opt {
     /// These assets are fetched by requests using `/assets/*` path.
     #[clap(short = 's', long = "assets-dir", default_value = "../dist")]
     assets_dir: String,
+
+    /// Do not run the database migrations on startup.
+    #[clap(long = "skip-migrations")]
+    skip_migrations: bool,
+}
+
+/// The timeout of checking the content type of an image URL.
+#[cfg(feature = "image-check")]
+const IMAGE_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Get the client for checking the content type of the image URLs, if the `mode` needs one.
+#[cfg(feature = "image-check")]
+fn image_head_client(mode: ImageCheckMode) -> Option<std::sync::Arc<dyn ImageHeadClient>> {
+    //
+    if mode != ImageCheckMode::ContentType {
+        return None;
+    }
+    match backend::domain::model::ReqwestImageHeadClient::new(IMAGE_CHECK_TIMEOUT) {
+        Ok(client) => Some(std::sync::Arc::new(client)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(not(feature = "image-check"))]
+fn image_head_client(_mode: ImageCheckMode) -> Option<std::sync::Arc<dyn ImageHeadClient>> {
+    None
 }
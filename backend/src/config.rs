@@ -1,10 +1,31 @@
+use crate::domain::model::ImageCheckMode;
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct AppConfig {
+    pub auth: AuthSettings,
     pub database: DatabaseSettings,
     pub http: HttpSettings,
+    #[serde(default)]
+    pub images: ImagesSettings,
+}
+
+#[derive(Default, Deserialize)]
+pub struct ImagesSettings {
+    /// The (CDN) base URL of the images that are stored as relative paths. <br/>
+    /// If not provided, the images are exposed as stored.
+    pub base_url: Option<String>,
+    /// How the image URLs of the users are checked (`none`, `extension`, or `content_type`). <br/>
+    /// The `content_type` one needs the `image-check` feature, otherwise the extension is checked.
+    #[serde(default)]
+    pub check: ImageCheckMode,
+}
+
+#[derive(Deserialize)]
+pub struct AuthSettings {
+    /// The secret used for signing (and verifying) the JWT tokens.
+    pub jwt_secret: Secret<String>,
 }
 
 #[derive(Deserialize)]
@@ -95,11 +116,25 @@ pub fn get_config() -> Result<AppConfig, config::ConfigError> {
     let env_src =
         config::File::with_name(config_dir.join(env.as_str()).to_str().unwrap()).required(true);
 
-    config::Config::builder()
+    let cfg = config::Config::builder()
         // Load the config.(yml|yaml|toml|...) file.
         .add_source(base_src)
         .add_source(env_src)
+        // Any value can be provided (or overridden) as `APP_<SECTION>__<KEY>` env var.
+        .add_source(
+            config::Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        )
         .build()
-        .unwrap()
-        .try_deserialize()
+        .unwrap();
+
+    // The JWT secret is not part of the base (committed) config, thus it must be provided.
+    match cfg.get_string("auth.jwt_secret") {
+        Ok(secret) if !secret.trim().is_empty() => cfg.try_deserialize(),
+        _ => Err(config::ConfigError::Message(format!(
+            "The JWT secret (auth.jwt_secret or APP_AUTH__JWT_SECRET) is not set for the {} environment",
+            env.as_str()
+        ))),
+    }
 }
@@ -0,0 +1,185 @@
+//! The password hashing, kept in one place so that all the use cases use the same KDF. <br/>
+//! New passwords are hashed using Argon2id, while the legacy (MD5 based) ones can still be verified,
+//! as the algorithm of each password is stored alongside it (see `PasswordAlgo`).
+
+use crate::AppError;
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+/// The length of the (random, per user) salt, as stored in the database.
+const SALT_LEN: usize = 12;
+
+/// The algorithm (KDF) that a password was hashed with, stored (as `accounts.pwd_algo`)
+/// alongside the hash, so that its verification uses the matching one. <br/>
+/// This allows migrating to another algorithm, while the existing passwords can still be verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordAlgo {
+    /// The legacy (salted MD5) one, used before switching to Argon2.
+    Md5,
+    Argon2id,
+}
+
+impl PasswordAlgo {
+    //
+    /// The algorithm of the newly hashed passwords.
+    pub const CURRENT: PasswordAlgo = PasswordAlgo::Argon2id;
+
+    /// The value stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordAlgo::Md5 => "md5",
+            PasswordAlgo::Argon2id => "argon2id",
+        }
+    }
+
+    /// Get the algorithm of a stored value, if it is a known one.
+    pub fn from_stored(val: &str) -> Option<Self> {
+        match val {
+            "md5" => Some(PasswordAlgo::Md5),
+            "argon2id" => Some(PasswordAlgo::Argon2id),
+            _ => None,
+        }
+    }
+}
+
+/// Hash the provided plain password (using `PasswordAlgo::CURRENT`), with a random salt. <br/>
+/// It returns the hash (in PHC string format) and the salt.
+pub fn hash_password(plain: &str) -> Result<(String, String), AppError> {
+    //
+    let salt: String = std::iter::repeat_with(fastrand::alphanumeric)
+        .take(SALT_LEN)
+        .collect();
+    // The alphanumeric chars are valid (B64) salt chars.
+    let salt_string = SaltString::from_b64(&salt).map_err(|err| {
+        log::error!("Failed to create the password salt: {err}");
+        AppError::InternalErr
+    })?;
+    let hash = Argon2::default()
+        .hash_password(plain.as_bytes(), &salt_string)
+        .map_err(|err| {
+            log::error!("Failed to hash the password: {err}");
+            AppError::InternalErr
+        })?;
+    Ok((hash.to_string(), salt))
+}
+
+/// Check whether the `candidate` (plain) password matches the provided hash and salt,
+/// using the `algo` that it was hashed with. <br/>
+/// The comparison is done in constant time.
+pub fn verify_password(candidate: &str, hash: &str, salt: &str, algo: PasswordAlgo) -> bool {
+    //
+    if algo == PasswordAlgo::Md5 {
+        return verify_legacy_password(candidate, hash, salt);
+    }
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(candidate.as_bytes(), &parsed)
+            .is_ok(),
+        Err(err) => {
+            log::error!("Failed to parse a stored password hash: {err}");
+            false
+        }
+    }
+}
+
+/// The verification of the passwords hashed before switching to Argon2.
+fn verify_legacy_password(candidate: &str, hash: &str, salt: &str) -> bool {
+    let digest = md5::compute(format!("@{salt}${candidate}").as_bytes());
+    constant_time_eq(format!("{:x}", digest).as_bytes(), hash.as_bytes())
+}
+
+/// Compare two byte slices in a time that depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    //
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_only_the_correct_password() {
+        let (hash, salt) = hash_password("correct-password").unwrap();
+        assert!(verify_password(
+            "correct-password",
+            &hash,
+            &salt,
+            PasswordAlgo::Argon2id
+        ));
+        for candidate in ["wrong-password", "", "correct-password "] {
+            assert!(
+                !verify_password(candidate, &hash, &salt, PasswordAlgo::Argon2id),
+                "for '{candidate}'"
+            );
+        }
+    }
+
+    #[test]
+    fn compares_in_constant_time_by_content() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn hashing_the_same_password_twice_yields_different_hashes() {
+        let (hash1, salt1) = hash_password("same-password").unwrap();
+        let (hash2, salt2) = hash_password("same-password").unwrap();
+        assert_ne!(salt1, salt2);
+        assert_ne!(hash1, hash2);
+        assert_eq!(salt1.len(), SALT_LEN);
+        assert!(hash1.starts_with("$argon2id$"));
+        assert!(verify_password(
+            "same-password",
+            &hash1,
+            &salt1,
+            PasswordAlgo::Argon2id
+        ));
+        assert!(verify_password(
+            "same-password",
+            &hash2,
+            &salt2,
+            PasswordAlgo::Argon2id
+        ));
+    }
+
+    #[test]
+    fn verifies_a_password_of_the_legacy_algo() {
+        let salt = "legacy-salt";
+        let hash = format!("{:x}", md5::compute("@legacy-salt$old-password"));
+        assert!(verify_password(
+            "old-password",
+            &hash,
+            salt,
+            PasswordAlgo::Md5
+        ));
+        assert!(!verify_password(
+            "wrong-password",
+            &hash,
+            salt,
+            PasswordAlgo::Md5
+        ));
+        // The algo tag picks the routine, thus a legacy hash is not an Argon2 one.
+        assert!(!verify_password(
+            "old-password",
+            &hash,
+            salt,
+            PasswordAlgo::Argon2id
+        ));
+    }
+
+    #[test]
+    fn parses_the_stored_algo_tags() {
+        for algo in [PasswordAlgo::Md5, PasswordAlgo::Argon2id] {
+            assert_eq!(PasswordAlgo::from_stored(algo.as_str()), Some(algo));
+        }
+        assert_eq!(PasswordAlgo::from_stored("sha1"), None);
+        assert_eq!(PasswordAlgo::CURRENT, PasswordAlgo::Argon2id);
+    }
+}
@@ -1,30 +1,586 @@
-use crate::config::AppConfig;
+use crate::{config::DatabaseSettings, AppError};
 use secrecy::ExposeSecret;
-use sqlx::postgres::PgPoolOptions;
-use std::time::Duration;
+use serde::Serialize;
+use sqlx::{migrate::MigrateError, postgres::PgPoolOptions, Executor, Postgres, Transaction};
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(any(test, feature = "seed"))]
+pub mod seed;
 
 pub type DbConnPool = sqlx::Pool<sqlx::Postgres>;
 
-pub async fn init_db_pool(cfg: &AppConfig) -> Result<DbConnPool, sqlx::Error> {
+/// The settings of the database connection pool.
+#[derive(Debug)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Duration,
+    /// The connections older than this are closed (and replaced), so that they don't go stale,
+    /// such as behind a proxy that silently drops the long-lived ones.
+    pub max_lifetime: Duration,
+    /// Whether a connection is checked (pinged) before being acquired, so that the dead ones are discarded.
+    pub test_before_acquire: bool,
+    /// The statements that take longer than this are cancelled (by the database),
+    /// so that a pathological query cannot hang a request. Zero means no timeout. <br/>
+    /// It does not apply to the migrations (see `run_migrations`).
+    pub statement_timeout: Duration,
+    /// The queries that take longer than this are logged.
+    pub slow_query_threshold: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 30,
+            min_connections: 1,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(3),
+            max_lifetime: Duration::from_secs(60 * 60),
+            test_before_acquire: true,
+            statement_timeout: Duration::from_secs(30),
+            slow_query_threshold: Duration::from_millis(100),
+        }
+    }
+}
+
+impl PoolConfig {
     //
-    let db_url = cfg.database.connection_string();
+    /// Get the default pool settings, overridden by any of the `APP_DB_MAX_CONNECTIONS`,
+    /// `APP_DB_MIN_CONNECTIONS`, `APP_DB_ACQUIRE_TIMEOUT_SECS`, `APP_DB_IDLE_TIMEOUT_SECS`,
+    /// `APP_DB_MAX_LIFETIME_SECS`, `APP_DB_TEST_BEFORE_ACQUIRE` (`0` disables it),
+    /// `APP_DB_STATEMENT_TIMEOUT_MS`, `APP_DB_SLOW_QUERY_MS` environment variables that are set. <br/>
+    /// An invalid (or out of range) value is `AppError::InvalidRequest`, naming its env var.
+    pub fn from_env() -> Result<Self, AppError> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Same as `from_env`, getting the values of the variables using `var`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, AppError> {
+        //
+        let num = |name: &str| -> Result<Option<u64>, AppError> {
+            match var(name) {
+                Some(val) => match val.trim().parse() {
+                    Ok(v) => Ok(Some(v)),
+                    Err(_) => Err(Self::invalid(name, &val)),
+                },
+                None => Ok(None),
+            }
+        };
+        let count = |name: &str| -> Result<Option<u32>, AppError> {
+            match num(name)? {
+                Some(v) => match u32::try_from(v) {
+                    Ok(v) => Ok(Some(v)),
+                    Err(_) => Err(Self::invalid(name, &v.to_string())),
+                },
+                None => Ok(None),
+            }
+        };
+        let mut cfg = Self::default();
+        if let Some(v) = count("APP_DB_MAX_CONNECTIONS")? {
+            cfg.max_connections = v;
+        }
+        if let Some(v) = count("APP_DB_MIN_CONNECTIONS")? {
+            cfg.min_connections = v;
+        }
+        if let Some(v) = num("APP_DB_ACQUIRE_TIMEOUT_SECS")? {
+            cfg.acquire_timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = num("APP_DB_IDLE_TIMEOUT_SECS")? {
+            cfg.idle_timeout = Duration::from_secs(v);
+        }
+        if let Some(v) = num("APP_DB_MAX_LIFETIME_SECS")? {
+            cfg.max_lifetime = Duration::from_secs(v);
+        }
+        if let Some(v) = num("APP_DB_TEST_BEFORE_ACQUIRE")? {
+            cfg.test_before_acquire = v != 0;
+        }
+        if let Some(v) = num("APP_DB_STATEMENT_TIMEOUT_MS")? {
+            cfg.statement_timeout = Duration::from_millis(v);
+        }
+        if let Some(v) = num("APP_DB_SLOW_QUERY_MS")? {
+            cfg.slow_query_threshold = Duration::from_millis(v);
+        }
+        Ok(cfg)
+    }
+
+    fn invalid(name: &str, val: &str) -> AppError {
+        AppError::InvalidRequest(format!("invalid value '{val}' of {name} env var"))
+    }
+}
+
+/// Create the database connection pool.
+pub async fn connect(db_cfg: &DatabaseSettings, cfg: PoolConfig) -> Result<DbConnPool, AppError> {
+    //
+    let db_url = db_cfg.connection_string();
     let db_url = db_url.expose_secret();
+    set_slow_query_threshold(cfg.slow_query_threshold);
+    // Set on each (new) connection, thus it applies to all of its statements.
+    let statement_timeout = format!(
+        "SET statement_timeout = {}",
+        cfg.statement_timeout.as_millis()
+    );
     PgPoolOptions::new()
-        .idle_timeout(Duration::from_secs(3))
-        .min_connections(1)
-        .max_connections(30)
-        .max_lifetime(Duration::from_secs(60 * 60))
+        .max_connections(cfg.max_connections)
+        .min_connections(cfg.min_connections)
+        .acquire_timeout(cfg.acquire_timeout)
+        .idle_timeout(cfg.idle_timeout)
+        .max_lifetime(cfg.max_lifetime)
+        .test_before_acquire(cfg.test_before_acquire)
+        .after_connect(move |conn, _| {
+            let statement_timeout = statement_timeout.clone();
+            Box::pin(async move {
+                conn.execute(statement_timeout.as_str()).await?;
+                Ok(())
+            })
+        })
         .connect_lazy(db_url)
+        .map_err(|err| {
+            log::error!("Failed to create the database connection pool: {err}");
+            AppError::InternalErr
+        })
+}
+
+/// A database transaction, for the use cases that do several writes. <br/>
+/// It is rolled back unless it is committed, including when it is dropped (such as on an early
+/// return of an error). It derefs to the `sqlx` transaction, thus it can be passed to the `*_tx` operations.
+pub struct Tx(Transaction<'static, Postgres>);
+
+impl Tx {
+    //
+    pub async fn begin(conn: &DbConnPool) -> Result<Self, AppError> {
+        //
+        match conn.begin().await {
+            Ok(txn) => Ok(Self(txn)),
+            Err(err) => {
+                log::error!("Failed to begin a transaction: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    pub async fn commit(self) -> Result<(), AppError> {
+        //
+        self.0.commit().await.map_err(|err| {
+            log::error!("Failed to commit the transaction: {err}");
+            AppError::InternalErr
+        })
+    }
+
+    pub async fn rollback(self) -> Result<(), AppError> {
+        //
+        self.0.rollback().await.map_err(|err| {
+            log::error!("Failed to rollback the transaction: {err}");
+            AppError::InternalErr
+        })
+    }
+}
+
+impl Deref for Tx {
+    type Target = Transaction<'static, Postgres>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Tx {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// The threshold (in milliseconds) used by `timed`.
+static SLOW_QUERY_THRESHOLD_MS: AtomicU64 = AtomicU64::new(100);
+
+/// Set the threshold above which `timed` logs a query as slow.
+pub fn set_slow_query_threshold(threshold: Duration) {
+    SLOW_QUERY_THRESHOLD_MS.store(threshold.as_millis() as u64, Ordering::Relaxed);
 }
 
-pub async fn ping_db(conn: &DbConnPool) -> bool {
+/// Run the provided query, and log it (as a warning) if it takes longer than
+/// the slow query threshold. The `query` is a short label, such as `users.get_by_id`.
+pub async fn timed<Fut>(query: &'static str, fut: Fut) -> Fut::Output
+where
+    Fut: Future,
+{
     //
-    let z = sqlx::query("SELECT 1").execute(conn).await;
-    match z {
-        Ok(_) => true,
-        Err(e) => {
-            log::error!("Failed to ping the database: {e}");
-            false
+    let start = Instant::now();
+    let res = fut.await;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    let threshold_ms = SLOW_QUERY_THRESHOLD_MS.load(Ordering::Relaxed);
+    if elapsed_ms > threshold_ms {
+        tracing::warn!(query, elapsed_ms, threshold_ms, "slow query");
+    }
+    res
+}
+
+/// The number of attempts of an operation that is run with `retry`.
+pub const RETRY_MAX_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry. It doubles for each subsequent one.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Run the provided (database) operation up to `max_attempts` times, with an exponential backoff. <br/>
+/// Only transient errors (pool timeout or I/O) are retried. Any other error
+/// (such as a constraint violation or `RowNotFound`) is returned immediately.
+pub async fn retry<T, F, Fut>(max_attempts: u32, mut op: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    //
+    let mut attempt = 1;
+    let mut delay = RETRY_BASE_DELAY;
+    loop {
+        match op().await {
+            Err(err) if attempt < max_attempts && is_transient(&err) => {
+                log::warn!("Retrying (attempt {attempt}/{max_attempts} failed): {err}");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                delay *= 2;
+            }
+            res => return res,
         }
     }
 }
+
+/// Whether the statement was cancelled because of the statement timeout (see `PoolConfig::statement_timeout`).
+pub fn is_statement_timeout(err: &sqlx::Error) -> bool {
+    // It is a `query_canceled` (57014) error.
+    err.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "57014")
+}
+
+fn is_transient(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::PoolTimedOut | sqlx::Error::Io(_))
+}
+
+/// Apply the (not yet applied) database migrations of `migrations/` directory. <br/>
+/// It is idempotent, thus safe to be run on each startup. A migration that previously
+/// failed midway (a dirty state) is reported as a conflict, to be resolved manually. <br/>
+/// The migrations are not limited by the statement timeout, thus they use a dedicated connection
+/// (detached from the pool, so that its disabled timeout is not reused).
+pub async fn run_migrations(conn: &DbConnPool) -> Result<(), AppError> {
+    //
+    let mut conn = match conn.acquire().await {
+        Ok(conn) => conn.detach(),
+        Err(err) => {
+            log::error!("Failed to acquire a connection for the database migrations: {err}");
+            return Err(AppError::Unavailable("database".into()));
+        }
+    };
+    if let Err(err) = conn.execute("SET statement_timeout = 0").await {
+        log::error!("Failed to disable the statement timeout for the database migrations: {err}");
+        return Err(AppError::InternalErr);
+    }
+    match sqlx::migrate!("./migrations").run(&mut conn).await {
+        Ok(_) => Ok(()),
+        Err(MigrateError::Dirty(version)) => {
+            log::error!("The database migration {version} is dirty (partially applied).");
+            Err(AppError::Conflict(format!(
+                "database migration {version} is dirty"
+            )))
+        }
+        Err(err) => {
+            log::error!("Failed to run the database migrations: {err}");
+            Err(AppError::InternalErr)
+        }
+    }
+}
+
+/// A snapshot of the connection pool usage.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PoolStats {
+    /// The number of (idle and in use) connections.
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+}
+
+/// Get the current usage of the connection pool.
+pub fn pool_stats(conn: &DbConnPool) -> PoolStats {
+    //
+    let size = conn.size();
+    let idle = conn.num_idle() as u32;
+    PoolStats {
+        size,
+        idle,
+        in_use: size.saturating_sub(idle),
+    }
+}
+
+/// Log (at debug level) the usage of the connection pool, every `interval`, in a background task.
+pub fn log_pool_stats_periodically(conn: DbConnPool, interval: Duration) -> JoinHandle<()> {
+    //
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let stats = pool_stats(&conn);
+            tracing::debug!(
+                size = stats.size,
+                idle = stats.idle,
+                in_use = stats.in_use,
+                "db pool stats"
+            );
+        }
+    })
+}
+
+/// Check that the database is reachable. <br/>
+/// Getting a connection from the pool respects the pool's acquire timeout,
+/// so a stalled database does not make the caller wait forever.
+pub async fn ping(conn: &DbConnPool) -> Result<(), AppError> {
+    //
+    let mut conn = conn.acquire().await.map_err(|err| {
+        log::error!("Failed to acquire a database connection: {err}");
+        AppError::Unavailable("database".into())
+    })?;
+    match sqlx::query("SELECT 1").execute(&mut conn).await {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            log::error!("Failed to ping the database: {err}");
+            Err(AppError::Unavailable("database".into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+    use std::collections::HashMap;
+
+    fn pool_config(vars: &[(&str, &str)]) -> Result<PoolConfig, AppError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, val)| (name.to_string(), val.to_string()))
+            .collect();
+        PoolConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn overrides_the_pool_defaults_with_the_set_vars() {
+        let cfg = pool_config(&[
+            ("APP_DB_MAX_CONNECTIONS", "7"),
+            ("APP_DB_ACQUIRE_TIMEOUT_SECS", "2"),
+            ("APP_DB_TEST_BEFORE_ACQUIRE", "0"),
+        ])
+        .unwrap();
+        assert_eq!(cfg.max_connections, 7);
+        assert_eq!(cfg.acquire_timeout, Duration::from_secs(2));
+        assert!(!cfg.test_before_acquire);
+        assert_eq!(cfg.min_connections, PoolConfig::default().min_connections);
+    }
+
+    #[test]
+    fn rejects_an_out_of_range_pool_size_naming_its_var() {
+        let res = pool_config(&[("APP_DB_MAX_CONNECTIONS", "4294967296")]);
+        assert!(matches!(
+            res,
+            Err(AppError::InvalidRequest(msg)) if msg.contains("APP_DB_MAX_CONNECTIONS")
+        ));
+    }
+
+    #[test]
+    fn rejects_an_invalid_value_naming_its_var() {
+        let res = pool_config(&[("APP_DB_IDLE_TIMEOUT_SECS", "soon")]);
+        assert!(matches!(
+            res,
+            Err(AppError::InvalidRequest(msg)) if msg.contains("APP_DB_IDLE_TIMEOUT_SECS")
+        ));
+    }
+
+    #[tokio::test]
+    async fn retries_a_transient_error_until_it_succeeds() {
+        let attempts = &AtomicU64::new(0);
+        let res = retry(RETRY_MAX_ATTEMPTS, || async move {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                0 | 1 => Err(sqlx::Error::PoolTimedOut),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_a_missing_row() {
+        let attempts = &AtomicU64::new(0);
+        let res: Result<(), _> = retry(RETRY_MAX_ATTEMPTS, || async move {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(sqlx::Error::RowNotFound)
+        })
+        .await;
+        assert!(matches!(res, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    /// A tracing layer that captures the `query` (field) of the warning events.
+    #[derive(Clone, Default)]
+    struct SlowQueries(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SlowQueries {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if *event.metadata().level() == tracing::Level::WARN {
+                event.record(
+                    &mut |field: &tracing::field::Field, val: &dyn std::fmt::Debug| {
+                        if field.name() == "query" {
+                            self.0.lock().unwrap().push(format!("{val:?}"));
+                        }
+                    },
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn warns_about_a_slow_query_only() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let captured = SlowQueries::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(captured.clone()));
+        set_slow_query_threshold(Duration::from_millis(20));
+        timed("test.fast", async {}).await;
+        timed("test.slow", tokio::time::sleep(Duration::from_millis(50))).await;
+        set_slow_query_threshold(Duration::from_millis(100));
+
+        assert_eq!(*captured.0.lock().unwrap(), ["\"test.slow\""]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a database"]
+    async fn a_second_acquire_waits_for_the_release_of_the_single_connection() {
+        let app_cfg = get_config().unwrap();
+        let cfg = PoolConfig {
+            max_connections: 1,
+            min_connections: 0,
+            ..PoolConfig::default()
+        };
+        let dbcp = connect(&app_cfg.database, cfg).await.unwrap();
+        let first = dbcp.acquire().await.unwrap();
+        let blocked = tokio::time::timeout(Duration::from_millis(200), dbcp.acquire()).await;
+        assert!(blocked.is_err(), "the second acquire should wait");
+
+        let second = tokio::spawn({
+            let dbcp = dbcp.clone();
+            async move { dbcp.acquire().await.map(|_| ()) }
+        });
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(5), second)
+            .await
+            .expect("the second acquire should complete after the release")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a database"]
+    async fn the_pool_stats_reflect_an_acquired_connection() {
+        let app_cfg = get_config().unwrap();
+        let cfg = PoolConfig {
+            min_connections: 0,
+            ..PoolConfig::default()
+        };
+        let dbcp = connect(&app_cfg.database, cfg).await.unwrap();
+        let conn = dbcp.acquire().await.unwrap();
+        let stats = pool_stats(&dbcp);
+        assert_eq!(stats.in_use, 1);
+        assert_eq!(stats.size, stats.idle + 1);
+
+        drop(conn);
+        // The released connection is returned to the pool asynchronously.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(pool_stats(&dbcp).in_use, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a database"]
+    async fn pings_a_working_pool() {
+        let app_cfg = get_config().unwrap();
+        let dbcp = connect(&app_cfg.database, PoolConfig::default())
+            .await
+            .unwrap();
+        assert!(ping(&dbcp).await.is_ok());
+    }
+
+    #[test]
+    fn sets_the_max_lifetime_from_its_var() {
+        let cfg = pool_config(&[("APP_DB_MAX_LIFETIME_SECS", "600")]).unwrap();
+        assert_eq!(cfg.max_lifetime, Duration::from_secs(600));
+        assert!(cfg.test_before_acquire);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a database"]
+    async fn reconnects_after_the_backend_connection_is_killed() {
+        let app_cfg = get_config().unwrap();
+        let cfg = PoolConfig {
+            max_connections: 1,
+            min_connections: 0,
+            ..PoolConfig::default()
+        };
+        let dbcp = connect(&app_cfg.database, cfg).await.unwrap();
+        let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(&dbcp)
+            .await
+            .unwrap();
+
+        // Kill the (now idle) connection of the pool, using another pool.
+        let other = connect(&app_cfg.database, PoolConfig::default())
+            .await
+            .unwrap();
+        let killed: bool = sqlx::query_scalar("SELECT pg_terminate_backend($1)")
+            .bind(pid)
+            .fetch_one(&other)
+            .await
+            .unwrap();
+        assert!(killed);
+
+        // The dead connection is discarded on acquire, and a new one is used.
+        let new_pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(&dbcp)
+            .await
+            .unwrap();
+        assert_ne!(new_pid, pid);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a database"]
+    async fn cancels_a_statement_at_the_configured_timeout() {
+        let app_cfg = get_config().unwrap();
+        let cfg = PoolConfig {
+            statement_timeout: Duration::from_secs(1),
+            ..PoolConfig::default()
+        };
+        let dbcp = connect(&app_cfg.database, cfg).await.unwrap();
+        let started = std::time::Instant::now();
+        let err = sqlx::query("SELECT pg_sleep(10)")
+            .execute(&dbcp)
+            .await
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(is_statement_timeout(&err));
+        assert!(matches!(
+            AppError::from((err, crate::AppUseCase::GetUserProfile)),
+            AppError::Unavailable(_)
+        ));
+    }
+}
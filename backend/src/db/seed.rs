@@ -0,0 +1,89 @@
+//! Bulk seeding of users and followings, for load tests and benchmarks. <br/>
+//! The seeded data is inserted using multi-row inserts, thus large amounts are created quickly.
+
+use super::DbConnPool;
+use crate::{
+    domain::model::{Password, UserId, Username},
+    AppError,
+};
+use sqlx::{postgres::PgRow, Row};
+
+/// The (plain) password of all the seeded users.
+pub const SEED_USER_PASSWORD: &str = "seed-password";
+
+/// Create `n` users, all of them having the `SEED_USER_PASSWORD`, and return their ids. <br/>
+/// Their usernames (and emails) are unique per call, thus it can be called repeatedly.
+pub async fn seed_users(dbcp: &DbConnPool, n: usize) -> Result<Vec<UserId>, AppError> {
+    //
+    // The password is hashed only once, as hashing is (by design) slow.
+    let pwd = Password::generate(SEED_USER_PASSWORD)?;
+    let run = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let usernames: Vec<String> = (0..n).map(|i| format!("seed-{run}-{i}")).collect();
+    let emails: Vec<String> = usernames
+        .iter()
+        .map(|username| format!("{username}@example.com"))
+        .collect();
+    let skeletons = usernames
+        .iter()
+        .map(|username| Ok(Username::try_new(username)?.skeleton()))
+        .collect::<Result<Vec<String>, AppError>>()?;
+    sqlx::query(
+        "INSERT INTO accounts (email, username, username_skeleton, password, salt, pwd_algo)
+         SELECT email, username, skeleton, $4, $5, $6
+         FROM UNNEST($1::text[], $2::text[], $3::text[]) AS u(email, username, skeleton)
+         RETURNING id",
+    )
+    .bind(&emails)
+    .bind(&usernames)
+    .bind(&skeletons)
+    .bind(&pwd.hash)
+    .bind(&pwd.salt)
+    .bind(pwd.algo.as_str())
+    .map(|row: PgRow| UserId::from_trusted(row.get("id")))
+    .fetch_all(dbcp)
+    .await
+    .map_err(|err| seed_err("users", err))
+}
+
+/// Create followings among the `users`, each (non-self) pair being a following with
+/// the `density` probability (between 0 and 1). The follow counts of the users are updated. <br/>
+/// It returns the number of created followings (the already existing ones are skipped).
+pub async fn seed_follows(
+    dbcp: &DbConnPool,
+    users: &[UserId],
+    density: f64,
+) -> Result<u64, AppError> {
+    //
+    let ids: Vec<i64> = users.iter().map(UserId::as_value).collect();
+    let mut txn = dbcp.begin().await.map_err(|err| seed_err("follows", err))?;
+    let created = sqlx::query(
+        "INSERT INTO followings (user_id, followed_user_id)
+         SELECT f.id, t.id FROM UNNEST($1::bigint[]) AS f(id) CROSS JOIN UNNEST($1::bigint[]) AS t(id)
+         WHERE f.id <> t.id AND random() < $2
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(&ids)
+    .bind(density.clamp(0.0, 1.0))
+    .execute(&mut txn)
+    .await
+    .map_err(|err| seed_err("follows", err))?
+    .rows_affected();
+    sqlx::query(
+        "UPDATE accounts a SET
+             follower_count = (SELECT count(*) FROM followings f WHERE f.followed_user_id = a.id),
+             following_count = (SELECT count(*) FROM followings f WHERE f.user_id = a.id)
+         WHERE a.id = ANY($1)",
+    )
+    .bind(&ids)
+    .execute(&mut txn)
+    .await
+    .map_err(|err| seed_err("follows", err))?;
+    txn.commit().await.map_err(|err| seed_err("follows", err))?;
+    Ok(created)
+}
+
+fn seed_err(what: &str, err: sqlx::Error) -> AppError {
+    //
+    log::error!("Failed to seed the {what}: {err}");
+    AppError::InternalErr
+}
@@ -0,0 +1,79 @@
+//! A harness for the tests that need a (real) database. <br/>
+//! Each `TestDb` uses its own schema, so tests can run in parallel without interfering.
+
+use super::{run_migrations, DbConnPool};
+use crate::config::get_config;
+use secrecy::ExposeSecret;
+use sqlx::{postgres::PgPoolOptions, Connection, Executor, PgConnection};
+
+/// An isolated (uniquely named) schema, having the migrations applied. <br/>
+/// The schema is dropped when the `TestDb` is dropped.
+pub struct TestDb {
+    pub dbcp: DbConnPool,
+    schema: String,
+    db_url: String,
+}
+
+impl TestDb {
+    //
+    /// Create the schema, using the database of the app config.
+    pub async fn new() -> Self {
+        //
+        let app_cfg = get_config().expect("Failed to load the app config.");
+        let db_url = app_cfg.database.connection_string().expose_secret().clone();
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+
+        let mut conn = PgConnection::connect(&db_url)
+            .await
+            .expect("Failed to connect to the test database.");
+        conn.execute(format!(r#"CREATE SCHEMA "{schema}""#).as_str())
+            .await
+            .expect("Failed to create the test schema.");
+
+        // Any connection of the pool uses only the test schema.
+        let search_path = format!(r#"SET search_path TO "{schema}""#);
+        let dbcp = PgPoolOptions::new()
+            .max_connections(5)
+            .after_connect(move |conn, _| {
+                let search_path = search_path.clone();
+                Box::pin(async move {
+                    conn.execute(search_path.as_str()).await?;
+                    Ok(())
+                })
+            })
+            .connect(&db_url)
+            .await
+            .expect("Failed to connect to the test database.");
+        run_migrations(&dbcp)
+            .await
+            .expect("Failed to migrate the test schema.");
+
+        Self {
+            dbcp,
+            schema,
+            db_url,
+        }
+    }
+}
+
+impl Drop for TestDb {
+    //
+    fn drop(&mut self) {
+        // Dropping cannot be async, thus the cleanup runs on its own (blocked on) runtime.
+        let schema = self.schema.clone();
+        let db_url = self.db_url.clone();
+        let cleanup = std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .expect("Failed to create the cleanup runtime.")
+                .block_on(async move {
+                    let mut conn = PgConnection::connect(&db_url).await?;
+                    conn.execute(format!(r#"DROP SCHEMA "{schema}" CASCADE"#).as_str())
+                        .await?;
+                    Ok::<_, sqlx::Error>(())
+                })
+        });
+        if let Ok(Err(err)) = cleanup.join() {
+            log::warn!("Failed to drop the test schema {}: {err}", self.schema);
+        }
+    }
+}
@@ -1,16 +1,22 @@
 use crate::{
-    domain::model::{Article, UserId},
-    repos::{ArticlesRepo, UsersRepo},
+    domain::{
+        model::{Article, ArticleFilter, UserId},
+        Page, Pagination,
+    },
+    repos::{ArticlesRepo, UserRepository},
     AppError,
 };
 use serde::Deserialize;
 use slug::slugify;
 use std::sync::Arc;
 
+/// The maximum number of attempts to find a unique slug for a new article.
+const MAX_SLUG_ATTEMPTS: u32 = 10;
+
 #[derive(Clone)]
 pub struct ArticlesMgr {
     articles_repo: ArticlesRepo,
-    user_repo: Arc<UsersRepo>,
+    user_repo: Arc<dyn UserRepository>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,19 +30,60 @@ pub struct UpdateArticleInput {
 
 impl ArticlesMgr {
     //
-    pub fn new(articles_repo: ArticlesRepo, user_repo: Arc<UsersRepo>) -> Self {
+    pub fn new(articles_repo: ArticlesRepo, user_repo: Arc<dyn UserRepository>) -> Self {
         Self {
             articles_repo,
             user_repo,
         }
     }
 
-    pub async fn get_articles(&self) -> Result<Vec<Article>, AppError> {
-        self.articles_repo.get_articles().await
+    pub async fn list_global(
+        &self,
+        viewer: Option<&UserId>,
+        filter: &ArticleFilter,
+    ) -> Result<Page<Article>, AppError> {
+        self.articles_repo.list_global(viewer, filter).await
+    }
+
+    /// Get the personalized feed: the articles of the authors that the `viewer` follows.
+    pub async fn feed(
+        &self,
+        viewer: &UserId,
+        page: &Pagination,
+    ) -> Result<Page<Article>, AppError> {
+        self.articles_repo.feed(viewer, page).await
     }
 
-    pub async fn get_article(&self, slug: &String) -> Result<Option<Article>, AppError> {
-        self.articles_repo.get_article(slug).await
+    pub async fn get_article(
+        &self,
+        slug: &String,
+        viewer: Option<&UserId>,
+    ) -> Result<Option<Article>, AppError> {
+        self.articles_repo.get_article(slug, viewer).await
+    }
+
+    pub async fn favorite_article(
+        &self,
+        curr_user_id: &UserId,
+        slug: String,
+    ) -> Result<Article, AppError> {
+        //
+        self.articles_repo.favorite(curr_user_id, &slug).await?;
+        self.get_article(&slug, Some(curr_user_id))
+            .await?
+            .ok_or_else(|| AppError::NotFound("article".into()))
+    }
+
+    pub async fn unfavorite_article(
+        &self,
+        curr_user_id: &UserId,
+        slug: String,
+    ) -> Result<Article, AppError> {
+        //
+        self.articles_repo.unfavorite(curr_user_id, &slug).await?;
+        self.get_article(&slug, Some(curr_user_id))
+            .await?
+            .ok_or_else(|| AppError::NotFound("article".into()))
     }
 
     pub async fn create_article(
@@ -50,24 +97,32 @@ impl ArticlesMgr {
         //
         let slug = slugify(&title);
         let mut a = Article::new_basic(
-            slug,
+            slug.clone(),
             title,
             description,
             body,
             tag_list,
             author_id.as_value(),
         );
-        self.articles_repo.add(&mut a).await?;
-        a.author = self
-            .user_repo
-            .get_profile_by_id(author_id.as_value())
-            .await?;
+        // On slug collision, retry with a numeric suffix (such as "my-post-2").
+        let mut attempt = 1;
+        loop {
+            match self.articles_repo.add(&mut a).await {
+                Ok(_) => break,
+                Err(AppError::AlreadyExists(_)) if attempt < MAX_SLUG_ATTEMPTS => {
+                    attempt += 1;
+                    a.slug = format!("{slug}-{attempt}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        a.author = self.user_repo.get_profile_by_id(&author_id, None).await?;
         Ok(a)
     }
 
     pub async fn delete_article(&self, curr_user_id: UserId, slug: String) -> Result<(), AppError> {
         //
-        match self.get_article(&slug).await {
+        match self.get_article(&slug, Some(&curr_user_id)).await {
             Ok(a) => match a {
                 Some(a) => {
                     if a.author.user_id != curr_user_id.as_value() {
@@ -93,7 +148,7 @@ impl ArticlesMgr {
         input: UpdateArticleInput,
     ) -> Result<Article, AppError> {
         //
-        let res = self.get_article(&slug).await?;
+        let res = self.get_article(&slug, Some(&curr_user_id)).await?;
         if res.is_none() {
             return Err(AppError::NotFound("article".into()));
         }
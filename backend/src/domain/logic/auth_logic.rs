@@ -1,47 +1,45 @@
-use crate::{domain::model::User, repos::UsersRepo, AppError, AppUseCase};
+use crate::{
+    domain::model::{Password, User, UserId, ValidatedRegister},
+    repos::UserRepository,
+    AppError,
+};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AuthMgr {
-    user_repo: Arc<UsersRepo>,
+    user_repo: Arc<dyn UserRepository>,
 }
 
 impl AuthMgr {
     //
-    pub fn new(user_repo: Arc<UsersRepo>) -> Self {
+    pub fn new(user_repo: Arc<dyn UserRepository>) -> Self {
         Self { user_repo }
     }
 
-    pub async fn register_user(&self, user: &User, pwd: String) -> Result<i64, AppError> {
+    /// Register the (validated) user, returning it as saved. A repeated registration
+    /// with the same `idempotency_key` returns the originally registered user.
+    pub async fn register_user(
+        &self,
+        reg: &ValidatedRegister,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
         //
-        let (pwd, salt) = Self::generate_password(pwd);
-        self.user_repo.save(user, pwd, salt).await
+        let pwd = Password::generate(reg.password())?;
+        self.user_repo.save(reg, &pwd, idempotency_key).await
     }
 
     pub async fn login_user(&self, email: String, pwd: String) -> Result<User, AppError> {
         //
-        let user_entry = self
-            .user_repo
-            .get_by_email(&email, AppUseCase::UserLogin)
-            .await?;
-        match Self::check_password(&pwd, &user_entry.password, &user_entry.salt) {
-            true => Ok(user_entry.into()),
-            false => Err(AppError::Unauthorized("wrong credentials".into())),
+        // An unknown email and a wrong password are not distinguished.
+        match self.user_repo.get_by_email_opt(&email).await? {
+            Some(entry) if entry.verify_password(&pwd) => Ok(entry.into()),
+            _ => Err(AppError::Unauthorized("wrong credentials".into())),
         }
     }
 
-    fn generate_password(pwd: String) -> (String, String) {
+    pub async fn change_password(&self, id: &UserId, pwd: String) -> Result<(), AppError> {
         //
-        let salt: String = std::iter::repeat_with(fastrand::alphanumeric)
-            .take(12)
-            .collect();
-        let digest = md5::compute(format!("@{salt}${pwd}").as_bytes());
-        (format!("{:x}", digest), salt)
-    }
-
-    fn check_password(input_pwd: &str, pwd: &str, salt: &str) -> bool {
-        //
-        let digest = md5::compute(format!("@{salt}${input_pwd}").as_bytes());
-        pwd == format!("{:x}", digest)
+        let pwd = Password::generate(&pwd)?;
+        self.user_repo.update_password(id, &pwd).await
     }
 }
@@ -1,2 +1,5 @@
 pub mod logic;
 pub mod model;
+
+mod pagination;
+pub use pagination::*;
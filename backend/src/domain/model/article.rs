@@ -1,11 +1,12 @@
 use super::UserProfile;
-use chrono::{DateTime, Utc};
-use serde::Serialize;
+use crate::{domain::Pagination, AppError};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common_model::ArticleDTO;
+use serde::Deserialize;
 
 /// `Article` domain model is what a `User` can read or write.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug)]
 pub struct Article {
-    #[serde(skip_serializing)]
     pub id: i64,
     pub slug: String,
     pub title: String,
@@ -15,7 +16,7 @@ pub struct Article {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub favorited: bool,
-    pub favorites_count: i32,
+    pub favorites_count: i64,
     pub author: UserProfile,
 }
 
@@ -71,3 +72,47 @@ impl Article {
         }
     }
 }
+
+impl From<Article> for ArticleDTO {
+    fn from(a: Article) -> Self {
+        Self {
+            slug: a.slug,
+            title: a.title,
+            description: a.description,
+            body: a.body,
+            tag_list: a.tag_list,
+            created_at: to_rfc3339(&a.created_at),
+            updated_at: to_rfc3339(&a.updated_at),
+            favorited: a.favorited,
+            favorites_count: a.favorites_count,
+            author: a.author.into(),
+        }
+    }
+}
+
+/// The timestamp format used by the API (RFC 3339, in UTC, with milliseconds).
+pub(crate) fn to_rfc3339(ts: &DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// The criteria for listing articles. The provided ones are combined (all must match). <br/>
+/// It can be used as is for the query parameters of a listing request.
+#[derive(Debug, Default, Deserialize)]
+pub struct ArticleFilter {
+    /// The username of the author.
+    pub author: Option<String>,
+    pub tag: Option<String>,
+    /// The username of a user that favorited the article.
+    #[serde(rename = "favorited")]
+    pub favorited_by: Option<String>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl ArticleFilter {
+    //
+    /// The (validated) page of the listing.
+    pub fn pagination(&self) -> Result<Pagination, AppError> {
+        Pagination::new(self.offset, self.limit)
+    }
+}
@@ -0,0 +1,25 @@
+use super::{article::to_rfc3339, UserProfile};
+use chrono::{DateTime, Utc};
+use common_model::CommentDTO;
+
+/// A `Comment` that a `User` wrote on an article.
+#[derive(Clone, Debug)]
+pub struct Comment {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub body: String,
+    pub author: UserProfile,
+}
+
+impl From<Comment> for CommentDTO {
+    fn from(c: Comment) -> Self {
+        Self {
+            id: c.id,
+            created_at: to_rfc3339(&c.created_at),
+            updated_at: to_rfc3339(&c.updated_at),
+            body: c.body,
+            author: c.author.into(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::AppError;
+use std::fmt;
+
+/// A (syntactically) valid email address, kept in lowercase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Email(String);
+
+impl Email {
+    //
+    /// Create an `Email` from an untrusted value (such as a request input).
+    pub fn try_new(email: &str) -> Result<Self, AppError> {
+        //
+        let email = email.trim().to_lowercase();
+        match Self::is_valid(&email) {
+            true => Ok(Email(email)),
+            false => Err(AppError::InvalidRequest(format!(
+                "email '{email}' is invalid"
+            ))),
+        }
+    }
+
+    /// Create an `Email` from a trusted value, such as one read from the database.
+    pub fn from_trusted(email: String) -> Self {
+        Email(email)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// A basic check: one `@` that separates a non-empty local part from a domain
+    /// having at least two non-empty labels, with no whitespace.
+    fn is_valid(email: &str) -> bool {
+        //
+        if email.chars().any(char::is_whitespace) {
+            return false;
+        }
+        match email.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !local.starts_with('.')
+                    && !local.ends_with('.')
+                    && !local.contains("..")
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && domain.split('.').all(|label| !label.is_empty())
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        email.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_the_invalid_forms() {
+        for email in [
+            "",
+            "  ",
+            "ann.example.com",
+            "@example.com",
+            "ann@",
+            "ann@example",
+            "ann@example.com.",
+            "ann@.example.com",
+            "ann.@example.com",
+            ".ann@example.com",
+            "an..n@example.com",
+            "ann@ex@ample.com",
+            "an n@example.com",
+        ] {
+            assert!(
+                matches!(Email::try_new(email), Err(AppError::InvalidRequest(_))),
+                "for '{email}'"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_a_valid_email_trimmed_and_in_lowercase() {
+        let email = Email::try_new(" Ann.B@Example.COM ").unwrap();
+        assert_eq!(email.as_str(), "ann.b@example.com");
+    }
+}
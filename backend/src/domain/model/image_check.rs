@@ -0,0 +1,362 @@
+use crate::AppError;
+use axum::async_trait;
+use serde::Deserialize;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, OnceLock},
+};
+
+/// The checker used when accepting (untrusted) image URLs.
+static IMAGE_CHECKER: OnceLock<ImageChecker> = OnceLock::new();
+
+/// The allowed image (URL path) extensions.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif"];
+
+/// The allowed image content types.
+pub const IMAGE_CONTENT_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/gif"];
+
+/// How the image URLs are checked to be (allowed) images, besides being valid URLs.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageCheckMode {
+    /// Any (valid) URL is accepted.
+    #[default]
+    None,
+    /// The URL path must have one of the `IMAGE_EXTENSIONS`.
+    Extension,
+    /// The URL must respond (to a `HEAD` request) with one of the `IMAGE_CONTENT_TYPES`. <br/>
+    /// Only the `https` URLs of public hosts are requested, and any redirect is not followed.
+    ContentType,
+}
+
+/// A client that gets the content type of a URL, used by the `ImageCheckMode::ContentType` check.
+#[async_trait]
+pub trait ImageHeadClient: Send + Sync {
+    /// Get the `Content-Type` header (if any) of a `HEAD` request to the URL,
+    /// whose host is resolved to the provided (checked) `addr`.
+    async fn content_type(&self, url: &str, addr: SocketAddr) -> Result<Option<String>, AppError>;
+}
+
+/// It checks that the (untrusted) image URLs point to allowed images, according to its mode. <br/>
+/// The network based check (`ImageCheckMode::ContentType`) is opt-in, and it needs a client
+/// (see `ReqwestImageHeadClient` of the `image-check` feature).
+#[derive(Clone, Default)]
+pub struct ImageChecker {
+    mode: ImageCheckMode,
+    client: Option<Arc<dyn ImageHeadClient>>,
+}
+
+impl ImageChecker {
+    //
+    /// Create a checker of the `mode`. <br/>
+    /// A `ContentType` mode without a client falls back to checking the extension.
+    pub fn new(mode: ImageCheckMode, client: Option<Arc<dyn ImageHeadClient>>) -> Self {
+        if mode == ImageCheckMode::ContentType && client.is_none() {
+            log::warn!("No client to check the image content types, checking their extensions.");
+            return Self {
+                mode: ImageCheckMode::Extension,
+                client,
+            };
+        }
+        Self { mode, client }
+    }
+
+    /// Set the checker to be used by `ImageChecker::global()`. <br/>
+    /// It is meant to be called once, on startup. Any subsequent call is ignored.
+    pub fn set_global(checker: ImageChecker) {
+        if IMAGE_CHECKER.set(checker).is_err() {
+            log::warn!("Ignoring the image checker, as it was already set.");
+        }
+    }
+
+    /// Get the checker that was set on startup, or one that accepts any URL.
+    pub fn global() -> &'static ImageChecker {
+        IMAGE_CHECKER.get_or_init(ImageChecker::default)
+    }
+
+    /// Check the image URL. An empty value (meaning no image) is always accepted.
+    pub async fn check(&self, image: &str) -> Result<(), AppError> {
+        //
+        if image.trim().is_empty() {
+            return Ok(());
+        }
+        match (self.mode, &self.client) {
+            (ImageCheckMode::None, _) => Ok(()),
+            (ImageCheckMode::ContentType, Some(client)) => {
+                let addr = Self::public_addr(image).await?;
+                let content_type = client.content_type(image, addr).await?;
+                // Any parameters (such as `; charset=...`) are ignored.
+                let mime = content_type
+                    .as_deref()
+                    .and_then(|ct| ct.split(';').next())
+                    .map(|mime| mime.trim().to_ascii_lowercase());
+                match mime {
+                    Some(mime) if IMAGE_CONTENT_TYPES.contains(&mime.as_str()) => Ok(()),
+                    _ => Err(Self::not_allowed()),
+                }
+            }
+            _ => match Self::has_allowed_extension(image) {
+                true => Ok(()),
+                false => Err(Self::not_allowed()),
+            },
+        }
+    }
+
+    /// Whether the path of the URL (without its query or fragment) ends with an allowed extension.
+    pub fn has_allowed_extension(image: &str) -> bool {
+        //
+        let path = image.split(['?', '#']).next().unwrap_or_default();
+        match path.rsplit_once('.') {
+            Some((_, ext)) => IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()),
+            None => false,
+        }
+    }
+
+    /// Resolve the host of the URL, as for requesting it. <br/>
+    /// Only an `https` URL whose host has only public addresses is accepted, so that
+    /// the checks cannot reach any internal service (such as a loopback or private one).
+    async fn public_addr(url: &str) -> Result<SocketAddr, AppError> {
+        //
+        let (host, port) = Self::https_host_port(url).ok_or_else(Self::not_reachable)?;
+        let addrs: Vec<SocketAddr> = match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => addrs.collect(),
+            Err(err) => {
+                log::debug!("Failed to resolve the image URL host: {err}");
+                return Err(Self::not_reachable());
+            }
+        };
+        // All of the addresses are checked, as any of them might be used.
+        match addrs.first() {
+            Some(addr) if addrs.iter().all(|addr| Self::is_public(addr.ip())) => Ok(*addr),
+            _ => Err(Self::not_reachable()),
+        }
+    }
+
+    /// The host and port of an `https` URL, unless it has any credentials.
+    fn https_host_port(url: &str) -> Option<(&str, u16)> {
+        //
+        let scheme = url.get(..8)?;
+        if !scheme.eq_ignore_ascii_case("https://") {
+            return None;
+        }
+        let authority = url[8..].split(['/', '?', '#']).next()?;
+        if authority.contains('@') {
+            return None;
+        }
+        // A (bracketed) IPv6 host has colons as well.
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.ends_with(']') => (host, port.parse().ok()?),
+            _ => (authority, 443),
+        };
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        match host.is_empty() {
+            true => None,
+            false => Some((host, port)),
+        }
+    }
+
+    /// Whether the address is neither a loopback, private, link-local nor unspecified one.
+    fn is_public(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified())
+            }
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => Self::is_public(IpAddr::V4(ip)),
+                None => {
+                    // The unique local (`fc00::/7`) ones are the private ones,
+                    // and `fe80::/10` are the link-local ones.
+                    let first = ip.segments()[0];
+                    !(ip.is_loopback()
+                        || ip.is_unspecified()
+                        || (first & 0xfe00) == 0xfc00
+                        || (first & 0xffc0) == 0xfe80)
+                }
+            },
+        }
+    }
+
+    /// The same error for any URL that cannot be (or fails to be) requested,
+    /// thus it does not reveal anything about the (possibly internal) host.
+    fn not_reachable() -> AppError {
+        AppError::InvalidRequest("image URL is not reachable".into())
+    }
+
+    fn not_allowed() -> AppError {
+        AppError::InvalidRequest(format!(
+            "image must be one of {}",
+            IMAGE_EXTENSIONS.join(", ")
+        ))
+    }
+}
+
+/// An `ImageHeadClient` using `reqwest`, with a timeout for each request. <br/>
+/// It requests only `https` URLs, it does not follow any redirect (that could point to any host),
+/// and it connects only to the checked address of the host.
+#[cfg(feature = "image-check")]
+pub struct ReqwestImageHeadClient {
+    timeout: std::time::Duration,
+}
+
+#[cfg(feature = "image-check")]
+impl ReqwestImageHeadClient {
+    //
+    pub fn new(timeout: std::time::Duration) -> Result<Self, AppError> {
+        let client = Self { timeout };
+        // Any failure to create the client is reported on startup, rather than on each check.
+        client.build(None)?;
+        Ok(client)
+    }
+
+    /// Build the client, with the `host` (if provided) resolved to `addr`.
+    fn build(&self, pinned: Option<(&str, SocketAddr)>) -> Result<reqwest::Client, AppError> {
+        //
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .https_only(true);
+        if let Some((host, addr)) = pinned {
+            builder = builder.resolve(host, addr);
+        }
+        match builder.build() {
+            Ok(client) => Ok(client),
+            Err(err) => {
+                log::error!("Failed to create the image check client: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image-check")]
+#[async_trait]
+impl ImageHeadClient for ReqwestImageHeadClient {
+    //
+    async fn content_type(&self, url: &str, addr: SocketAddr) -> Result<Option<String>, AppError> {
+        //
+        let host = ImageChecker::https_host_port(url).map(|(host, _)| host);
+        let client = self.build(host.map(|host| (host, addr)))?;
+        // An unreachable (or failing, or redirecting) URL is not an acceptable image.
+        match client.head(url).send().await {
+            Ok(res) if res.status().is_success() => Ok(res
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|val| val.to_str().ok())
+                .map(String::from)),
+            Ok(res) => {
+                log::debug!("The image URL responded with {}", res.status());
+                Err(ImageChecker::not_reachable())
+            }
+            Err(err) => {
+                log::debug!("Failed to check the image URL: {err}");
+                Err(ImageChecker::not_reachable())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A client that responds with the same content type to any URL.
+    struct FixedContentType(Option<&'static str>);
+
+    #[async_trait]
+    impl ImageHeadClient for FixedContentType {
+        async fn content_type(
+            &self,
+            _url: &str,
+            _addr: SocketAddr,
+        ) -> Result<Option<String>, AppError> {
+            Ok(self.0.map(String::from))
+        }
+    }
+
+    /// An image URL of a public address, thus not needing a lookup.
+    const PUBLIC_URL: &str = "https://203.0.113.7/ann.png";
+
+    fn content_type_checker(content_type: Option<&'static str>) -> ImageChecker {
+        ImageChecker::new(
+            ImageCheckMode::ContentType,
+            Some(Arc::new(FixedContentType(content_type))),
+        )
+    }
+
+    #[tokio::test]
+    async fn accepts_an_allowed_content_type() {
+        for content_type in ["image/png", "IMAGE/JPEG", "image/webp; charset=binary"] {
+            let checker = content_type_checker(Some(content_type));
+            assert!(
+                checker.check(PUBLIC_URL).await.is_ok(),
+                "for {content_type}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_disallowed_or_missing_content_type() {
+        for content_type in [Some("text/html"), Some("image/svg+xml"), None] {
+            let checker = content_type_checker(content_type);
+            assert!(
+                matches!(
+                    checker.check(PUBLIC_URL).await,
+                    Err(AppError::InvalidRequest(_))
+                ),
+                "for {content_type:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_the_urls_of_internal_hosts() {
+        let checker = content_type_checker(Some("image/png"));
+        for url in [
+            "http://127.0.0.1/ann.png",
+            "http://203.0.113.7/ann.png",
+            "https://127.0.0.1/ann.png",
+            "https://10.0.0.1:8443/ann.png",
+            "https://192.168.1.1/ann.png",
+            "https://169.254.169.254/ann.png",
+            "https://0.0.0.0/ann.png",
+            "https://[::1]/ann.png",
+            "https://[fd00::1]/ann.png",
+            "https://[::ffff:127.0.0.1]/ann.png",
+            "https://ann@203.0.113.7/ann.png",
+        ] {
+            assert!(
+                matches!(
+                    checker.check(url).await,
+                    Err(AppError::InvalidRequest(msg)) if msg == "image URL is not reachable"
+                ),
+                "for {url}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_the_extension_without_a_client() {
+        let checker = ImageChecker::new(ImageCheckMode::ContentType, None);
+        assert!(checker
+            .check("https://example.com/ann.PNG?v=1")
+            .await
+            .is_ok());
+        assert!(matches!(
+            checker.check("https://example.com/ann.svg").await,
+            Err(AppError::InvalidRequest(_))
+        ));
+        // An empty value is no image, thus it is accepted.
+        assert!(checker.check(" ").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn accepts_any_url_by_default() {
+        assert!(ImageChecker::default()
+            .check("https://example.com/ann.svg")
+            .await
+            .is_ok());
+    }
+}
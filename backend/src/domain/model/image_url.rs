@@ -0,0 +1,97 @@
+use std::sync::OnceLock;
+
+/// The resolver used by the (serialization boundary) conversions into DTOs.
+static IMAGE_URL_RESOLVER: OnceLock<ImageUrlResolver> = OnceLock::new();
+
+/// It rewrites the stored relative image paths (such as `avatars/1.png`) into absolute URLs,
+/// by prefixing them with the (CDN) base URL, if one is configured. <br/>
+/// The absolute URLs are left untouched. The repos store (and return) the raw values,
+/// while this applies when building the DTOs.
+#[derive(Clone, Debug, Default)]
+pub struct ImageUrlResolver {
+    base_url: Option<String>,
+}
+
+impl ImageUrlResolver {
+    //
+    pub fn new(base_url: Option<String>) -> Self {
+        Self {
+            base_url: base_url
+                .map(|url| url.trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+        }
+    }
+
+    /// Set the resolver to be used by `ImageUrlResolver::global()`. <br/>
+    /// It is meant to be called once, on startup. Any subsequent call is ignored.
+    pub fn set_global(resolver: ImageUrlResolver) {
+        if IMAGE_URL_RESOLVER.set(resolver).is_err() {
+            log::warn!("Ignoring the image URL resolver, as it was already set.");
+        }
+    }
+
+    /// Get the resolver that was set on startup, or one that leaves any URL untouched.
+    pub fn global() -> &'static ImageUrlResolver {
+        IMAGE_URL_RESOLVER.get_or_init(ImageUrlResolver::default)
+    }
+
+    /// Resolve the stored image into the URL to be exposed.
+    pub fn resolve(&self, image: Option<String>) -> Option<String> {
+        //
+        let image = image?;
+        match &self.base_url {
+            Some(base_url) if !Self::is_absolute(&image) => {
+                Some(format!("{base_url}/{}", image.trim_start_matches('/')))
+            }
+            _ => Some(image),
+        }
+    }
+
+    /// Whether it is an absolute (including a scheme relative) URL.
+    fn is_absolute(image: &str) -> bool {
+        image.starts_with("//") || image.contains("://")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(base_url: Option<&str>, image: &str) -> Option<String> {
+        ImageUrlResolver::new(base_url.map(String::from)).resolve(Some(image.into()))
+    }
+
+    #[test]
+    fn rewrites_the_relative_paths() {
+        let cdn = Some("https://cdn.example.com/");
+        for image in ["avatars/ann.png", "/avatars/ann.png"] {
+            assert_eq!(
+                resolve(cdn, image).as_deref(),
+                Some("https://cdn.example.com/avatars/ann.png"),
+                "for {image}"
+            );
+        }
+    }
+
+    #[test]
+    fn passes_through_the_absolute_urls() {
+        let cdn = Some("https://cdn.example.com");
+        for image in ["https://example.com/ann.png", "//example.com/ann.png"] {
+            assert_eq!(resolve(cdn, image).as_deref(), Some(image), "for {image}");
+        }
+        assert_eq!(
+            ImageUrlResolver::new(cdn.map(String::from)).resolve(None),
+            None
+        );
+    }
+
+    #[test]
+    fn without_a_base_url_leaves_any_image_untouched() {
+        for base_url in [None, Some(""), Some("/")] {
+            assert_eq!(
+                resolve(base_url, "avatars/ann.png").as_deref(),
+                Some("avatars/ann.png")
+            );
+        }
+    }
+}
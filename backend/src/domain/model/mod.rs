@@ -1,5 +1,29 @@
 mod article;
 pub use article::*;
 
+mod comment;
+pub use comment::*;
+
+mod email;
+pub use email::*;
+
+mod image_check;
+pub use image_check::*;
+
+mod image_url;
+pub use image_url::*;
+
+mod password;
+pub use password::*;
+
+mod registration;
+pub use registration::*;
+
+mod tag;
+pub use tag::*;
+
 mod user;
 pub use user::*;
+
+mod username;
+pub use username::*;
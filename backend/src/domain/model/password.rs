@@ -0,0 +1,84 @@
+use crate::{
+    crypto::{self, PasswordAlgo},
+    AppError,
+};
+use std::fmt;
+
+/// The (hashed) password of a `User`, together with the salt and the algorithm used for hashing it. <br/>
+/// Keeping them together prevents mixing them up when passing them around.
+/// For example, the repos do not accept the loose hash and salt:
+///
+/// ```compile_fail
+/// use backend::{domain::model::ValidatedRegister, repos::UsersRepo};
+///
+/// async fn save(repo: &UsersRepo, reg: &ValidatedRegister, salt: String, hash: String) {
+///     repo.save(reg, &(salt, hash), None).await;
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Password {
+    pub hash: String,
+    pub salt: String,
+    pub algo: PasswordAlgo,
+}
+
+impl Password {
+    //
+    /// Generate the (hashed) password from the provided plain one, using a random salt.
+    pub fn generate(plain: &str) -> Result<Self, AppError> {
+        let (hash, salt) = crypto::hash_password(plain)?;
+        Ok(Self {
+            hash,
+            salt,
+            algo: PasswordAlgo::CURRENT,
+        })
+    }
+
+    /// Check whether the `candidate` (plain) password matches this one.
+    pub fn verify(&self, candidate: &str) -> bool {
+        crypto::verify_password(candidate, &self.hash, &self.salt, self.algo)
+    }
+}
+
+/// It shows only the algorithm, thus the hash and salt do not end up in the logs.
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Password")
+            .field("hash", &"<redacted>")
+            .field("salt", &"<redacted>")
+            .field("algo", &self.algo)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_only_the_same_password() {
+        let pwd = Password::generate("test-password").unwrap();
+        assert!(pwd.verify("test-password"));
+        assert!(!pwd.verify("other-password"));
+    }
+
+    #[test]
+    fn transposed_hash_and_salt_do_not_verify() {
+        let pwd = Password::generate("test-password").unwrap();
+        let transposed = Password {
+            hash: pwd.salt.clone(),
+            salt: pwd.hash.clone(),
+            algo: pwd.algo,
+        };
+        assert!(!transposed.verify("test-password"));
+    }
+
+    #[test]
+    fn the_debug_output_redacts_the_hash_and_salt() {
+        let pwd = Password::generate("test-password").unwrap();
+        let debug = format!("{pwd:?}");
+        assert!(!debug.contains(&pwd.hash));
+        assert!(!debug.contains(&pwd.salt));
+        assert!(debug.contains(&format!("{:?}", pwd.algo)));
+    }
+}
@@ -0,0 +1,146 @@
+use super::{Email, User, Username};
+use crate::{AppError, ValidationErrors};
+use chrono::DateTime;
+
+/// The minimum length (in chars) of a password.
+pub const PASSWORD_MIN_LEN: usize = 8;
+
+/// The (untrusted) input of a user registration.
+pub struct RegisterInput {
+    pub email: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl RegisterInput {
+    //
+    /// Validate all the fields, reporting all the invalid ones at once
+    /// (as an `AppError::Validation`).
+    pub fn validate(self) -> Result<ValidatedRegister, AppError> {
+        //
+        let mut errors = ValidationErrors::new();
+        let email = Email::try_new(&self.email);
+        if email.is_err() {
+            match self.email.trim().is_empty() {
+                true => errors.add("email", "can't be blank"),
+                false => errors.add("email", "is invalid"),
+            };
+        }
+        let username = Username::parse(&self.username);
+        if let Err(err) = &username {
+            errors.add("username", &err.message());
+        }
+        if self.password.is_empty() {
+            errors.add("password", "can't be blank");
+        } else if self.password.chars().count() < PASSWORD_MIN_LEN {
+            errors.add(
+                "password",
+                &format!("is too short (minimum is {PASSWORD_MIN_LEN} characters)"),
+            );
+        }
+        errors.into_result()?;
+        Ok(ValidatedRegister {
+            user: User {
+                id: 0, // not relevant
+                email: email?,
+                username: username?.into(),
+                bio: String::new(),
+                image: None,
+                // Set by the database on save.
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
+            },
+            password: self.password,
+        })
+    }
+}
+
+/// A registration whose input was validated. It can be created only by `RegisterInput::validate`,
+/// thus the repos' `save` (which takes it) cannot persist an unvalidated input. <br/>
+/// It does not implement `Debug`, so that its (plain) password cannot be logged.
+pub struct ValidatedRegister {
+    user: User,
+    password: String,
+}
+
+impl ValidatedRegister {
+    //
+    /// The user to be saved.
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// The plain password, to be hashed before saving.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(
+        email: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<ValidatedRegister, AppError> {
+        RegisterInput {
+            email: email.into(),
+            username: username.into(),
+            password: password.into(),
+        }
+        .validate()
+    }
+
+    #[test]
+    fn reports_all_the_invalid_fields_at_once() {
+        match validate("not-an-email", "admin", "short") {
+            Err(AppError::Validation(errors)) => {
+                assert_eq!(errors["email"], vec!["is invalid"]);
+                assert_eq!(errors["username"], vec!["is reserved"]);
+                assert_eq!(
+                    errors["password"],
+                    vec!["is too short (minimum is 8 characters)"]
+                );
+            }
+            _ => panic!("expected a validation error"),
+        }
+    }
+
+    #[test]
+    fn reports_the_reason_of_an_invalid_username() {
+        let too_long = "a".repeat(Username::MAX_LEN + 1);
+        for (username, msg) in [
+            ("ann!", "is invalid"),
+            (too_long.as_str(), "is too long (maximum is 32 characters)"),
+        ] {
+            match validate("ann@example.com", username, "long-enough") {
+                Err(AppError::Validation(errors)) => {
+                    assert_eq!(errors["username"], vec![msg], "for '{username}'");
+                }
+                _ => panic!("expected a validation error for '{username}'"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_the_blank_fields() {
+        match validate(" ", " ", "") {
+            Err(AppError::Validation(errors)) => {
+                for field in ["email", "username", "password"] {
+                    assert_eq!(errors[field], vec!["can't be blank"], "for {field}");
+                }
+            }
+            _ => panic!("expected a validation error"),
+        }
+    }
+
+    #[test]
+    fn validates_a_valid_input() {
+        let reg = validate("Ann@Example.com", "ann", "long-enough").unwrap();
+        assert_eq!(reg.user().email.as_str(), "ann@example.com");
+        assert_eq!(reg.user().username, "ann");
+        assert_eq!(reg.password(), "long-enough");
+    }
+}
@@ -0,0 +1,54 @@
+use crate::AppError;
+
+/// The maximum length (in chars) of a tag name.
+pub const TAG_NAME_MAX_LEN: usize = 32;
+
+/// A `Tag` that can be attached to articles.
+#[derive(Clone, Debug)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+}
+
+impl Tag {
+    //
+    /// Normalize the provided (untrusted) tag names: trim, lowercase, and deduplicate them,
+    /// keeping their order. Empty names are skipped, while too long ones are invalid.
+    pub fn normalize_names(names: &[String]) -> Result<Vec<String>, AppError> {
+        //
+        let mut res: Vec<String> = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.trim().to_lowercase();
+            if name.is_empty() || res.contains(&name) {
+                continue;
+            }
+            if name.chars().count() > TAG_NAME_MAX_LEN {
+                return Err(AppError::InvalidRequest(format!(
+                    "tag '{name}' must have at most {TAG_NAME_MAX_LEN} characters"
+                )));
+            }
+            res.push(name);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_the_names_keeping_their_order() {
+        let names: Vec<String> = vec![" Web ".into(), "rust".into(), "".into(), "WEB".into()];
+        assert_eq!(Tag::normalize_names(&names).unwrap(), ["web", "rust"]);
+    }
+
+    #[test]
+    fn rejects_a_too_long_name() {
+        let names = vec!["t".repeat(TAG_NAME_MAX_LEN + 1)];
+        assert!(matches!(
+            Tag::normalize_names(&names),
+            Err(AppError::InvalidRequest(_))
+        ));
+    }
+}
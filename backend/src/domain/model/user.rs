@@ -1,37 +1,234 @@
-use serde::Serialize;
+use super::{Email, ImageUrlResolver, Password, Username};
+use crate::AppError;
+use chrono::{DateTime, Utc};
+use common_model::UserProfileDTO;
+use std::hash::{Hash, Hasher};
 
 /// The (public) id of the User.
-#[derive(Debug, Serialize, Default)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UserId(i64);
 
 impl UserId {
+    /// Create a `UserId` from an untrusted value (such as a request input). <br/>
+    /// Only positive values are valid ids.
+    pub fn try_new(id: i64) -> Result<Self, AppError> {
+        match id > 0 {
+            true => Ok(UserId(id)),
+            false => Err(AppError::InvalidRequest(format!("invalid user id {id}"))),
+        }
+    }
+
+    /// Create a `UserId` from a trusted value, such as one read from the database
+    /// or from a verified token.
+    pub fn from_trusted(id: i64) -> Self {
+        UserId(id)
+    }
+
     pub fn as_value(&self) -> i64 {
         self.0
     }
 }
 
 impl From<i64> for UserId {
+    /// It must be used only for trusted values. See `UserId::from_trusted`.
     fn from(id: i64) -> Self {
-        UserId(id)
+        UserId::from_trusted(id)
     }
 }
 
 /// The main representation of the User. <br/>
 /// It contains most of the details (except for password).
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct User {
     pub id: i64,
-    pub email: String,
+    pub email: Email,
     pub username: String,
+    /// It is never NULL in the database, but it can be empty.
     pub bio: String,
     pub image: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-/// It includes all user attributes that are persisted in the database.
+/// The maximum length (in chars) of a user's bio.
+pub const BIO_MAX_LEN: usize = 1024;
+
+/// The maximum length of a user's image URL.
+pub const IMAGE_URL_MAX_LEN: usize = 2048;
+
+impl User {
+    //
+    /// Validate a bio (an untrusted value, such as a request input).
+    pub fn validate_bio(bio: &str) -> Result<(), AppError> {
+        match bio.chars().count() <= BIO_MAX_LEN {
+            true => Ok(()),
+            false => Err(AppError::InvalidRequest(format!(
+                "bio must have at most {BIO_MAX_LEN} characters"
+            ))),
+        }
+    }
+
+    /// Normalize an image (read or input) value, as an empty image is the same as no image.
+    pub fn normalize_image(image: Option<String>) -> Option<String> {
+        image.filter(|image| !image.trim().is_empty())
+    }
+
+    /// Validate an image URL (an untrusted value, such as a request input). <br/>
+    /// It must be an `http` or `https` URL, with a host and no whitespace.
+    /// An empty value is valid, meaning no image.
+    pub fn validate_image(image: &str) -> Result<(), AppError> {
+        //
+        if image.trim().is_empty() {
+            return Ok(());
+        }
+        if image.len() > IMAGE_URL_MAX_LEN {
+            return Err(AppError::InvalidRequest(format!(
+                "image must have at most {IMAGE_URL_MAX_LEN} characters"
+            )));
+        }
+        let host = image
+            .strip_prefix("https://")
+            .or_else(|| image.strip_prefix("http://"))
+            .and_then(|rest| rest.split(['/', '?', '#']).next());
+        match host {
+            Some(host) if !host.is_empty() && !image.chars().any(char::is_whitespace) => Ok(()),
+            _ => Err(AppError::InvalidRequest(
+                "image must be a valid http(s) URL".into(),
+            )),
+        }
+    }
+}
+
+/// The update of an optional (nullable) attribute, which distinguishes between leaving it
+/// unchanged and clearing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FieldUpdate<T> {
+    /// It remains unchanged.
+    #[default]
+    Keep,
+    Set(T),
+    /// It is removed (stored as NULL).
+    Clear,
+}
+
+impl<T> FieldUpdate<T> {
+    //
+    pub fn is_keep(&self) -> bool {
+        matches!(self, FieldUpdate::Keep)
+    }
+
+    /// Apply the update to the `current` value.
+    pub fn apply(self, current: Option<T>) -> Option<T> {
+        match self {
+            FieldUpdate::Keep => current,
+            FieldUpdate::Set(val) => Some(val),
+            FieldUpdate::Clear => None,
+        }
+    }
+}
+
+/// The changes of a user's attributes. The ones that are not provided remain unchanged. <br/>
+/// The email is not among them, as its change needs to be confirmed (see `EmailChangesRepo`).
+#[derive(Debug, Default)]
+pub struct UserUpdate {
+    pub username: Option<String>,
+    pub bio: Option<String>,
+    /// An empty `Set` image is the same as `Clear`.
+    pub image: FieldUpdate<String>,
+}
+
+impl UserUpdate {
+    //
+    /// Whether there is nothing to change.
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none() && self.bio.is_none() && self.image.is_keep()
+    }
+
+    /// Validate the changes (an untrusted input).
+    /// The username is validated as a `Username`.
+    pub fn validate(&self) -> Result<(), AppError> {
+        //
+        if self.is_empty() {
+            return Err(AppError::InvalidRequest(
+                "username, bio, and image is missing from request body".into(),
+            ));
+        }
+        if let Some(username) = &self.username {
+            Username::try_new(username)?;
+        }
+        if let Some(bio) = &self.bio {
+            User::validate_bio(bio)?;
+        }
+        if let FieldUpdate::Set(image) = &self.image {
+            User::validate_image(image)?;
+        }
+        Ok(())
+    }
+}
+
+/// It includes all user attributes that are persisted in the database. <br/>
+/// Having the password, it cannot be serialized (thus it cannot leak into a response):
+///
+/// ```compile_fail
+/// use backend::domain::model::UserEntry;
+///
+/// fn respond(entry: &UserEntry) -> String {
+///     serde_json::to_string(entry).unwrap()
+/// }
+/// ```
+#[derive(Clone)]
 pub struct UserEntry {
     pub user: User,
-    pub password: String,
-    pub salt: String,
+    pub(crate) password: Password,
+    /// The version of the entry, used for detecting concurrent updates.
+    pub version: i32,
+}
+
+/// A compile-time guard that `UserEntry` and `Password` never implement `Serialize`,
+/// so that the password hashes (and salts) cannot be leaked into a response. <br/>
+/// If either did, the `AmbiguousIfSerialize` item below would be ambiguous (having two impls),
+/// and thus it wouldn't compile.
+const _: fn() = || {
+    trait AmbiguousIfSerialize<A> {
+        fn some_item() {}
+    }
+    impl<T: ?Sized> AmbiguousIfSerialize<()> for T {}
+    struct IsSerialize;
+    impl<T: ?Sized + serde::Serialize> AmbiguousIfSerialize<IsSerialize> for T {}
+
+    let _ = <UserEntry as AmbiguousIfSerialize<_>>::some_item;
+    let _ = <Password as AmbiguousIfSerialize<_>>::some_item;
+};
+
+impl UserEntry {
+    pub fn password(&self) -> &Password {
+        &self.password
+    }
+
+    /// Check whether the `candidate` (plain) password is the user's one.
+    pub fn verify_password(&self, candidate: &str) -> bool {
+        self.password.verify(candidate)
+    }
+
+    /// Get the `User`, after checking its invariants (a non-empty username and email). <br/>
+    /// Unlike the (infallible) `From<UserEntry> for User`, this is meant for the entries
+    /// that are built from raw rows, which are not yet trusted.
+    pub fn into_validated_user(self) -> Result<User, AppError> {
+        //
+        if self.user.username.trim().is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "user {} has an empty username",
+                self.user.id
+            )));
+        }
+        if self.user.email.as_str().trim().is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "user {} has an empty email",
+                self.user.id
+            )));
+        }
+        Ok(self.into())
+    }
 }
 
 impl From<UserEntry> for User {
@@ -42,19 +239,28 @@ impl From<UserEntry> for User {
             username: val.user.username,
             bio: val.user.bio,
             image: val.user.image,
+            created_at: val.user.created_at,
+            updated_at: val.user.updated_at,
         }
     }
 }
 
-/// A common representation of a `User`, used in multiple use cases.
-#[derive(Clone, Debug, Serialize)]
+/// A common representation of a `User`, used in multiple use cases. <br/>
+/// This is what the backend layers (repos, logic, web api) use, while `UserProfileDTO`
+/// is the representation shared with the frontend. <br/>
+/// Its equality (and hash) is identity based: the profiles of the same `user_id` are equal,
+/// regardless of the other attributes (such as the viewer dependent `following`).
+#[derive(Clone, Debug)]
 pub struct UserProfile {
-    #[serde(skip_serializing)]
     pub user_id: i64,
     pub username: String,
     pub bio: String,
     pub image: Option<String>,
     pub following: bool,
+    /// The number of followers, if it was requested.
+    pub followers_count: Option<i64>,
+    /// The number of followed users, if it was requested.
+    pub following_count: Option<i64>,
 }
 
 impl UserProfile {
@@ -65,6 +271,284 @@ impl UserProfile {
             bio: "".into(),
             image: None,
             following: false,
+            followers_count: None,
+            following_count: None,
+        }
+    }
+
+    /// Set whether the viewer follows this user.
+    pub fn with_following(mut self, following: bool) -> Self {
+        self.following = following;
+        self
+    }
+}
+
+impl PartialEq for UserProfile {
+    fn eq(&self, other: &Self) -> bool {
+        self.user_id == other.user_id
+    }
+}
+
+impl Eq for UserProfile {}
+
+impl Hash for UserProfile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.user_id.hash(state);
+    }
+}
+
+impl From<UserEntry> for UserProfile {
+    /// The `following` flag depends on the viewer, thus it is left to the caller (see `with_following`).
+    fn from(val: UserEntry) -> Self {
+        Self {
+            user_id: val.user.id,
+            username: val.user.username,
+            bio: val.user.bio,
+            image: val.user.image,
+            following: false,
+            followers_count: None,
+            following_count: None,
+        }
+    }
+}
+
+impl From<UserProfileDTO> for UserProfile {
+    /// The `user_id` is not known from a DTO, thus it is 0.
+    fn from(dto: UserProfileDTO) -> Self {
+        Self {
+            user_id: 0,
+            username: dto.username,
+            bio: dto.bio,
+            image: dto.image,
+            following: dto.following.unwrap_or_default(),
+            followers_count: dto.followers_count,
+            following_count: dto.following_count,
+        }
+    }
+}
+
+impl From<UserProfile> for UserProfileDTO {
+    fn from(p: UserProfile) -> Self {
+        Self {
+            username: p.username,
+            bio: p.bio,
+            image: ImageUrlResolver::global().resolve(p.image),
+            following: Some(p.following),
+            followers_count: p.followers_count,
+            following_count: p.following_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_id_must_be_positive() {
+        for id in [0, -1] {
+            assert!(
+                matches!(UserId::try_new(id), Err(AppError::InvalidRequest(msg)) if msg == format!("invalid user id {id}"))
+            );
+        }
+        assert_eq!(UserId::try_new(1).unwrap().as_value(), 1);
+    }
+
+    #[test]
+    fn a_profile_round_trips_through_its_dto() {
+        let profile = UserProfile {
+            user_id: 0, // not known from a DTO
+            username: "ann".into(),
+            bio: "bio".into(),
+            image: Some("https://example.com/ann.png".into()),
+            following: true,
+            followers_count: Some(2),
+            following_count: None,
+        };
+        let dto = UserProfileDTO::from(profile.clone());
+        assert_eq!(dto.following, Some(true));
+        let back = UserProfile::from(dto);
+        assert_eq!(back.user_id, profile.user_id);
+        assert_eq!(back.username, profile.username);
+        assert_eq!(back.bio, profile.bio);
+        assert_eq!(back.image, profile.image);
+        assert_eq!(back.following, profile.following);
+        assert_eq!(back.followers_count, profile.followers_count);
+        assert_eq!(back.following_count, profile.following_count);
+    }
+
+    #[test]
+    fn a_dto_without_following_is_not_followed() {
+        let dto = UserProfileDTO {
+            username: "ann".into(),
+            bio: String::new(),
+            image: None,
+            following: None,
+            followers_count: None,
+            following_count: None,
+        };
+        assert!(!UserProfile::from(dto).following);
+    }
+
+    #[test]
+    fn rejects_an_over_long_bio() {
+        assert!(User::validate_bio(&"b".repeat(BIO_MAX_LEN)).is_ok());
+        let changes = UserUpdate {
+            bio: Some("b".repeat(BIO_MAX_LEN + 1)),
+            ..UserUpdate::default()
+        };
+        assert!(matches!(
+            changes.validate(),
+            Err(AppError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_a_non_url_image() {
+        for image in [
+            "not a url",
+            "ftp://example.com/a.png",
+            "https://",
+            "data:image/png;base64,AAAA",
+        ] {
+            let changes = UserUpdate {
+                image: FieldUpdate::Set(image.into()),
+                ..UserUpdate::default()
+            };
+            assert!(
+                matches!(changes.validate(), Err(AppError::InvalidRequest(_))),
+                "for '{image}'"
+            );
+        }
+        let too_long = format!("https://example.com/{}", "a".repeat(IMAGE_URL_MAX_LEN));
+        assert!(User::validate_image(&too_long).is_err());
+        assert!(User::validate_image("https://example.com/a.png").is_ok());
+        assert!(User::validate_image("").is_ok());
+    }
+
+    #[test]
+    fn an_update_without_changes_is_empty_and_invalid() {
+        let changes = UserUpdate::default();
+        assert!(changes.is_empty());
+        assert!(matches!(
+            changes.validate(),
+            Err(AppError::InvalidRequest(_))
+        ));
+        for changes in [
+            UserUpdate {
+                bio: Some(String::new()),
+                ..UserUpdate::default()
+            },
+            UserUpdate {
+                image: FieldUpdate::Clear,
+                ..UserUpdate::default()
+            },
+        ] {
+            assert!(!changes.is_empty(), "for {changes:?}");
+        }
+    }
+
+    #[test]
+    fn a_profile_has_the_json_shape_of_the_spec() {
+        let profile = UserProfile {
+            user_id: 7,
+            username: "ann".into(),
+            bio: "bio".into(),
+            image: None,
+            following: false,
+            followers_count: Some(3),
+            following_count: None,
+        };
+        let envelope = common_model::ProfileEnvelope {
+            profile: profile.into(),
+        };
+        // The user id is not exposed, and the counts are included only if requested.
+        assert_eq!(
+            serde_json::to_value(envelope).unwrap(),
+            serde_json::json!({
+                "profile": {
+                    "username": "ann",
+                    "bio": "bio",
+                    "image": null,
+                    "following": false,
+                    "followersCount": 3
+                }
+            })
+        );
+    }
+
+    fn entry(id: i64, username: &str) -> UserEntry {
+        UserEntry {
+            user: User {
+                id,
+                email: Email::from_trusted(format!("{username}@example.com")),
+                username: username.into(),
+                bio: "bio".into(),
+                image: Some("https://example.com/a.png".into()),
+                created_at: DateTime::default(),
+                updated_at: DateTime::default(),
+            },
+            password: Password::generate("test-password").unwrap(),
+            version: 0,
         }
     }
+
+    #[test]
+    fn a_profile_of_an_entry_carries_its_user_id() {
+        let profile = UserProfile::from(entry(42, "ann"));
+        assert_eq!(profile.user_id, 42);
+        assert_eq!(profile.username, "ann");
+        assert_eq!(profile.bio, "bio");
+        assert_eq!(profile.image.as_deref(), Some("https://example.com/a.png"));
+        assert!(!profile.following);
+        assert!(profile.with_following(true).following);
+    }
+
+    #[test]
+    fn the_profiles_of_the_same_user_are_deduplicated() {
+        let ann = UserProfile {
+            username: "ann".into(),
+            ..UserProfile::new_basic(1)
+        };
+        // The same user, as seen by a viewer that follows ann.
+        let followed_ann = ann.clone().with_following(true);
+        let bob = UserProfile::new_basic(2);
+        assert_eq!(ann, followed_ann);
+        assert_ne!(ann, bob);
+
+        let profiles: std::collections::HashSet<UserProfile> =
+            [ann, followed_ann, bob].into_iter().collect();
+        assert_eq!(profiles.len(), 2);
+    }
+
+    #[test]
+    fn an_entry_with_an_empty_email_or_username_is_invalid() {
+        let mut empty_email = entry(1, "ann");
+        empty_email.user.email = Email::from_trusted(String::new());
+        assert!(matches!(
+            empty_email.into_validated_user(),
+            Err(AppError::InvalidRequest(msg)) if msg == "user 1 has an empty email"
+        ));
+        let empty_username = entry(2, " ");
+        assert!(matches!(
+            empty_username.into_validated_user(),
+            Err(AppError::InvalidRequest(msg)) if msg == "user 2 has an empty username"
+        ));
+        let user = entry(3, "ann").into_validated_user().unwrap();
+        assert_eq!(user.id, 3);
+        assert_eq!(user.email.as_str(), "ann@example.com");
+    }
+
+    #[test]
+    fn a_field_update_keeps_sets_or_clears_the_value() {
+        let current = || Some("current".to_string());
+        assert_eq!(FieldUpdate::Keep.apply(current()), current());
+        assert_eq!(FieldUpdate::Keep.apply(None::<String>), None);
+        assert_eq!(
+            FieldUpdate::Set("new".to_string()).apply(current()),
+            Some("new".into())
+        );
+        assert_eq!(FieldUpdate::<String>::Clear.apply(current()), None);
+        assert!(FieldUpdate::<String>::default().is_keep());
+    }
 }
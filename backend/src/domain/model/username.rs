@@ -0,0 +1,200 @@
+use crate::AppError;
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+
+/// The usernames that are reserved by default, as they may be confused with some app routes or roles.
+pub const RESERVED_USERNAMES: &[&str] = &["admin", "me"];
+
+/// A valid username: 1 to `Username::MAX_LEN` alphanumeric, `_` or `-` characters,
+/// which neither starts nor ends with a separator (`_` or `-`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Username(String);
+
+impl Username {
+    //
+    pub const MAX_LEN: usize = 32;
+
+    /// Create a `Username` from an untrusted value (such as a request input). <br/>
+    /// The `RESERVED_USERNAMES` are rejected.
+    pub fn try_new(username: &str) -> Result<Self, AppError> {
+        Self::try_new_with_reserved(username, RESERVED_USERNAMES)
+    }
+
+    /// Same as `try_new`, but rejecting the provided `reserved` usernames (case insensitively).
+    pub fn try_new_with_reserved(username: &str, reserved: &[&str]) -> Result<Self, AppError> {
+        //
+        Self::parse_with_reserved(username, reserved).map_err(|err| {
+            let username = username.trim();
+            AppError::InvalidRequest(match err {
+                UsernameError::Blank | UsernameError::TooLong => {
+                    format!("username must have between 1 and {} chars", Self::MAX_LEN)
+                }
+                UsernameError::Invalid => format!("username '{username}' is invalid"),
+                UsernameError::Reserved => format!("username '{username}' is reserved"),
+            })
+        })
+    }
+
+    /// Same as `try_new`, but telling the reason of an invalid username (see `UsernameError`).
+    pub fn parse(username: &str) -> Result<Self, UsernameError> {
+        Self::parse_with_reserved(username, RESERVED_USERNAMES)
+    }
+
+    fn parse_with_reserved(username: &str, reserved: &[&str]) -> Result<Self, UsernameError> {
+        //
+        let username = username.trim();
+        if username.is_empty() {
+            return Err(UsernameError::Blank);
+        }
+        if username.chars().count() > Self::MAX_LEN {
+            return Err(UsernameError::TooLong);
+        }
+        let is_separator = |c: char| c == '_' || c == '-';
+        if !username
+            .chars()
+            .all(|c| c.is_alphanumeric() || is_separator(c))
+            || username.starts_with(is_separator)
+            || username.ends_with(is_separator)
+        {
+            return Err(UsernameError::Invalid);
+        }
+        if reserved.iter().any(|r| r.eq_ignore_ascii_case(username)) {
+            return Err(UsernameError::Reserved);
+        }
+        Ok(Username(username.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// The skeleton of the username (its NFKC, lowercased, confusable skeleton, per the Unicode TR39). <br/>
+    /// The usernames that look alike (such as `alice`, `Alice`, and `аlice` with a Cyrillic `а`)
+    /// have the same skeleton, thus only one of them may be used.
+    pub fn skeleton(&self) -> String {
+        let normalized: String = self.0.nfkc().flat_map(char::to_lowercase).collect();
+        unicode_security::skeleton(&normalized).collect()
+    }
+}
+
+/// The reason of an invalid username.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsernameError {
+    Blank,
+    TooLong,
+    /// Having other than alphanumeric or separator characters, or starting or ending with a separator.
+    Invalid,
+    Reserved,
+}
+
+impl UsernameError {
+    //
+    /// The message of the reason, as a validation error of the username field.
+    pub fn message(&self) -> String {
+        match self {
+            UsernameError::Blank => "can't be blank".into(),
+            UsernameError::TooLong => {
+                format!("is too long (maximum is {} characters)", Username::MAX_LEN)
+            }
+            UsernameError::Invalid => "is invalid".into(),
+            UsernameError::Reserved => "is reserved".into(),
+        }
+    }
+}
+
+impl From<UsernameError> for AppError {
+    fn from(err: UsernameError) -> Self {
+        AppError::InvalidRequest(format!("username {}", err.message()))
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Username> for String {
+    fn from(username: Username) -> Self {
+        username.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skeleton(username: &str) -> String {
+        Username::try_new(username).unwrap().skeleton()
+    }
+
+    #[test]
+    fn mixed_script_near_duplicates_have_the_same_skeleton() {
+        // Having a Cyrillic 'а' (U+0430).
+        assert_eq!(skeleton("p\u{0430}ypal"), skeleton("paypal"));
+        assert_eq!(skeleton("Alice"), skeleton("alice"));
+    }
+
+    #[test]
+    fn different_usernames_have_different_skeletons() {
+        assert_ne!(skeleton("alice"), skeleton("alicia"));
+    }
+
+    #[test]
+    fn rejects_the_invalid_usernames() {
+        let too_long = "a".repeat(Username::MAX_LEN + 1);
+        for username in [
+            "",
+            "   ",
+            "ann b",
+            "ann!",
+            "_ann",
+            "ann-",
+            too_long.as_str(),
+        ] {
+            assert!(
+                matches!(
+                    Username::try_new(username),
+                    Err(AppError::InvalidRequest(_))
+                ),
+                "for '{username}'"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_the_reserved_usernames() {
+        for username in ["admin", "Admin", "me"] {
+            assert!(
+                matches!(Username::try_new(username), Err(AppError::InvalidRequest(msg)) if msg.contains("reserved")),
+                "for '{username}'"
+            );
+        }
+        // The deny-list is configurable.
+        assert!(Username::try_new_with_reserved("admin", &[]).is_ok());
+        assert!(Username::try_new_with_reserved("root", &["root"]).is_err());
+    }
+
+    #[test]
+    fn tells_the_reason_of_an_invalid_username() {
+        let too_long = "a".repeat(Username::MAX_LEN + 1);
+        for (username, reason) in [
+            (" ", UsernameError::Blank),
+            (too_long.as_str(), UsernameError::TooLong),
+            ("ann!", UsernameError::Invalid),
+            ("Admin", UsernameError::Reserved),
+        ] {
+            assert_eq!(Username::parse(username), Err(reason), "for '{username}'");
+        }
+        assert_eq!(
+            UsernameError::TooLong.message(),
+            "is too long (maximum is 32 characters)"
+        );
+    }
+
+    #[test]
+    fn keeps_a_valid_username_trimmed() {
+        let username = Username::try_new(" ann_b-2 ").unwrap();
+        assert_eq!(username.as_str(), "ann_b-2");
+    }
+}
@@ -0,0 +1,162 @@
+use crate::AppError;
+use std::future::Future;
+
+/// The number of entries in a page, if not provided.
+pub const PAGE_DEFAULT_LIMIT: i64 = 20;
+
+/// The maximum number of entries in a page.
+pub const PAGE_MAX_LIMIT: i64 = 100;
+
+/// The (validated) offset and limit of a page of entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    offset: i64,
+    limit: i64,
+}
+
+impl Pagination {
+    //
+    /// Create a `Pagination` from untrusted values (such as request inputs). <br/>
+    /// The limit defaults to `PAGE_DEFAULT_LIMIT` and it is clamped to `[1, PAGE_MAX_LIMIT]`,
+    /// while a negative offset is invalid.
+    pub fn new(offset: Option<i64>, limit: Option<i64>) -> Result<Self, AppError> {
+        //
+        let offset = offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(AppError::InvalidRequest(format!(
+                "offset {offset} must not be negative"
+            )));
+        }
+        let limit = limit.unwrap_or(PAGE_DEFAULT_LIMIT).clamp(1, PAGE_MAX_LIMIT);
+        Ok(Self { offset, limit })
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit
+    }
+
+    /// The page of only the first entry.
+    pub fn first() -> Self {
+        Self {
+            offset: 0,
+            limit: 1,
+        }
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: PAGE_DEFAULT_LIMIT,
+        }
+    }
+}
+
+/// A page of entries, together with the total number of the entries (in all pages).
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    //
+    /// Create the page from rows that include the total number of entries (such as
+    /// the result of `count(*) OVER ()`). <br/>
+    /// Since there are no rows for a page past the last one, its `total` is 0 (see `fetch_counted`).
+    pub fn from_counted(rows: Vec<(T, i64)>, pagination: &Pagination) -> Self {
+        //
+        let total = rows.first().map(|(_, total)| *total).unwrap_or(0);
+        let items: Vec<T> = rows.into_iter().map(|(item, _)| item).collect();
+        let has_more = pagination.offset() + (items.len() as i64) < total;
+        Self {
+            items,
+            total,
+            has_more,
+        }
+    }
+
+    /// Fetch a page, using `fetch` for getting the rows of the provided pagination together with
+    /// the total number of entries (see `from_counted`). <br/>
+    /// A page past the last one has no rows, thus its `total` is the one of the first page.
+    pub async fn fetch_counted<F, Fut>(pagination: &Pagination, fetch: F) -> Result<Self, AppError>
+    where
+        F: Fn(Pagination) -> Fut,
+        Fut: Future<Output = Result<Vec<(T, i64)>, AppError>>,
+    {
+        //
+        let mut page = Self::from_counted(fetch(*pagination).await?, pagination);
+        if page.items.is_empty() && pagination.offset() > 0 {
+            let first = fetch(Pagination::first()).await?;
+            page.total = first.first().map(|(_, total)| *total).unwrap_or(0);
+        }
+        Ok(page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_clamps_the_limit() {
+        let page = Pagination::new(None, None).unwrap();
+        assert_eq!((page.offset(), page.limit()), (0, PAGE_DEFAULT_LIMIT));
+        assert_eq!(page, Pagination::default());
+        for (limit, clamped) in [(0, 1), (-5, 1), (50, 50), (101, PAGE_MAX_LIMIT)] {
+            let page = Pagination::new(Some(10), Some(limit)).unwrap();
+            assert_eq!((page.offset(), page.limit()), (10, clamped), "for {limit}");
+        }
+    }
+
+    #[test]
+    fn rejects_a_negative_offset() {
+        assert!(matches!(
+            Pagination::new(Some(-1), None),
+            Err(AppError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn has_more_flips_at_the_last_page() {
+        // 5 entries, in pages of 2.
+        let page_of = |offset: i64, len: usize| {
+            let pagination = Pagination::new(Some(offset), Some(2)).unwrap();
+            Page::from_counted(vec![((), 5); len], &pagination)
+        };
+        assert!(page_of(0, 2).has_more);
+        assert!(page_of(2, 2).has_more);
+        let last = page_of(4, 1);
+        assert!(!last.has_more);
+        assert_eq!(last.total, 5);
+    }
+
+    #[tokio::test]
+    async fn a_page_past_the_last_has_the_total_of_the_first() {
+        // 5 entries, each one counted.
+        let fetch = |pagination: Pagination| async move {
+            let rows: Vec<(i32, i64)> = (0..5)
+                .skip(pagination.offset() as usize)
+                .take(pagination.limit() as usize)
+                .map(|entry| (entry, 5))
+                .collect();
+            Ok::<_, AppError>(rows)
+        };
+        let last = Page::fetch_counted(&Pagination::new(Some(4), Some(2)).unwrap(), fetch)
+            .await
+            .unwrap();
+        assert_eq!((last.items, last.total, last.has_more), (vec![4], 5, false));
+        let past_the_last = Page::fetch_counted(&Pagination::new(Some(6), Some(2)).unwrap(), fetch)
+            .await
+            .unwrap();
+        assert!(past_the_last.items.is_empty());
+        assert!(!past_the_last.has_more);
+        assert_eq!(past_the_last.total, 5);
+    }
+}
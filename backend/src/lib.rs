@@ -4,8 +4,12 @@ pub use app_errors::*;
 mod app_state;
 pub use app_state::*;
 
+pub mod auth;
+
 pub mod config;
 
+pub mod crypto;
+
 pub mod db;
 
 pub mod domain;
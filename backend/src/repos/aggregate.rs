@@ -0,0 +1,46 @@
+use crate::{
+    db::DbConnPool,
+    repos::{ArticlesRepo, CommentsRepo, EmailChangesRepo, RefreshTokensRepo, TagsRepo},
+};
+use std::sync::Arc;
+
+/// All the repos, built once (sharing the same database connection pool). <br/>
+/// The users repo is not one of them, as it is used only through a `UserRepository`
+/// (see `AppState::user_repository`), so that its caching cannot be bypassed.
+pub struct Repos {
+    pub articles: ArticlesRepo,
+    pub comments: CommentsRepo,
+    pub tags: TagsRepo,
+    pub refresh_tokens: RefreshTokensRepo,
+    pub email_changes: EmailChangesRepo,
+}
+
+impl Repos {
+    //
+    pub fn new(dbcp: Arc<DbConnPool>) -> Self {
+        Self {
+            articles: ArticlesRepo::new(dbcp.clone()),
+            comments: CommentsRepo::new(dbcp.clone()),
+            tags: TagsRepo::new(dbcp.clone()),
+            refresh_tokens: RefreshTokensRepo::new(dbcp.clone()),
+            email_changes: EmailChangesRepo::new(dbcp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[tokio::test]
+    async fn builds_all_the_repos_sharing_one_pool() {
+        // A lazy pool does not connect until it is used.
+        let dbcp = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let dbcp = Arc::new(dbcp);
+        let _repos = Repos::new(dbcp.clone());
+        assert_eq!(Arc::strong_count(&dbcp), 6);
+    }
+}
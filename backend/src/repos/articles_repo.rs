@@ -1,10 +1,29 @@
 use crate::{
-    db::DbConnPool,
-    domain::model::{Article, UserProfile},
-    AppError,
+    db::{DbConnPool, Tx},
+    domain::{
+        model::{Article, ArticleFilter, User, UserId, UserProfile},
+        Page, Pagination,
+    },
+    repos::ArticleSortField,
+    AppError, AppUseCase,
 };
 use sqlx::{postgres::PgRow, Pool, Postgres, Row, Transaction};
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+
+/// The common part of the queries that read articles. <br/>
+/// `$1` is the id of the viewer (if any), used for resolving the `favorited` and `following` flags.
+/// The `total_count` is the number of all matching articles, regardless of any offset and limit.
+const ARTICLES_SELECT: &str = "
+    SELECT a.id, a.slug, a.title, a.description, a.body, a.created_at, a.updated_at,
+           u.id AS user_id, u.username, u.bio, u.image,
+           count(*) OVER () AS total_count,
+           (SELECT count(*) FROM favorited_articles fa WHERE fa.article_id = a.id) AS favorites_count,
+           EXISTS(SELECT 1 FROM favorited_articles fa
+                  WHERE fa.article_id = a.id AND fa.user_id = $1) AS favorited,
+           EXISTS(SELECT 1 FROM followings f
+                  WHERE f.followed_user_id = u.id AND f.user_id = $1) AS following
+    FROM articles a
+    JOIN accounts u ON a.author_id = u.id";
 
 #[derive(Clone)]
 pub struct ArticlesRepo {
@@ -17,86 +36,141 @@ impl ArticlesRepo {
         Self { dbcp }
     }
 
-    pub async fn get_articles(&self) -> Result<Vec<Article>, AppError> {
+    /// Get a page of the articles that match the `filter`, newest first. <br/>
+    /// The `favorited` and author's `following` flags are resolved against the `viewer`, if any. <br/>
+    /// Its SQL is built from the provided criteria, thus each (of the eight) combinations
+    /// of them has its own cached prepared statement. The values are always bound, never formatted.
+    pub async fn list_global(
+        &self,
+        viewer: Option<&UserId>,
+        filter: &ArticleFilter,
+    ) -> Result<Page<Article>, AppError> {
         //
+        let page = filter.pagination()?;
+        // Only the conditions of the provided criteria are included, each with its own parameter.
+        let mut conditions = vec![];
+        let mut param = 1;
+        if filter.author.is_some() {
+            param += 1;
+            conditions.push(format!("u.username = ${param}"));
+        }
+        if filter.tag.is_some() {
+            param += 1;
+            conditions.push(format!(
+                "EXISTS(SELECT 1 FROM tags_articles ta
+                        WHERE ta.article_id = a.id AND lower(ta.tag) = lower(${param}))"
+            ));
+        }
+        if filter.favorited_by.is_some() {
+            param += 1;
+            conditions.push(format!(
+                "EXISTS(SELECT 1 FROM favorited_articles fb JOIN accounts fu ON fb.user_id = fu.id
+                        WHERE fb.article_id = a.id AND fu.username = ${param})"
+            ));
+        }
+        let where_clause = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {}", conditions.join(" AND ")),
+        };
+        let sql = format!(
+            "{ARTICLES_SELECT} {where_clause}
+             ORDER BY {} OFFSET ${} LIMIT ${}",
+            ArticleSortField::NewestFirst.order_clause(),
+            param + 1,
+            param + 2
+        );
+
+        let sql = &sql;
         let conn = self.dbcp.as_ref();
-        let mut res = sqlx::query(
-            "SELECT count(fa.user_id) AS favorites_count,
-                    a.id, a.slug, a.title, a.description, a.body, a.created_at, a.updated_at,
-                    u.id as user_id, u.username, u.bio, u.image, count(f.user_id) as following 
-            FROM articles a
-            JOIN accounts u on a.author_id = u.id 
-            LEFT OUTER JOIN followings f ON u.id = f.user_id 
-            LEFT OUTER JOIN favorited_articles fa ON a.id = fa.article_id
-            GROUP BY a.id, u.id, u.username, u.bio, u.image;",
-        )
-        .map(|r: PgRow| {
-            let following = r.get::<i64, _>("following") > 0;
-            let author: UserProfile = UserProfile {
-                user_id: r.get("user_id"),
-                username: r.get("username"),
-                bio: r.get("bio"),
-                image: r.try_get("image").unwrap_or_default(),
-                following,
-            };
-            Article::new(
-                r.get("id"),
-                r.get("slug"),
-                r.get("title"),
-                r.get("description"),
-                r.get("body"),
-                r.get("created_at"),
-                r.get("updated_at"),
-                author,
-            )
+        let mut res = Page::fetch_counted(&page, |page| async move {
+            let mut query = sqlx::query(sql).bind(viewer.map(|v| v.as_value()));
+            for value in [&filter.author, &filter.tag, &filter.favorited_by]
+                .into_iter()
+                .flatten()
+            {
+                query = query.bind(value);
+            }
+            query
+                .bind(page.offset())
+                .bind(page.limit())
+                .map(|r: PgRow| (Self::article_from_row(&r), r.get("total_count")))
+                .fetch_all(conn)
+                .await
+                .map_err(|err| AppError::from((err, AppUseCase::ListArticles)))
         })
-        .fetch_all(conn)
-        .await
-        .map_err(AppError::from);
+        .await?;
+        self.with_tags(conn, &mut res.items).await?;
+        Ok(res)
+    }
 
-        if let Ok(ref mut articles) = res {
-            for a in articles {
-                self.get_tags(conn, a).await?
-            }
-        }
+    /// Get a page of the articles of the authors that the `viewer` follows, newest first.
+    pub async fn feed(
+        &self,
+        viewer: &UserId,
+        page: &Pagination,
+    ) -> Result<Page<Article>, AppError> {
+        //
+        let conn = self.dbcp.as_ref();
+        let sql = &format!(
+            "{ARTICLES_SELECT}
+             JOIN followings fo ON fo.followed_user_id = a.author_id AND fo.user_id = $1
+             ORDER BY {} OFFSET $2 LIMIT $3",
+            ArticleSortField::NewestFirst.order_clause()
+        );
+        let mut res = Page::fetch_counted(page, |page| async move {
+            sqlx::query(sql)
+                .bind(viewer.as_value())
+                .bind(page.offset())
+                .bind(page.limit())
+                .map(|r: PgRow| (Self::article_from_row(&r), r.get("total_count")))
+                .fetch_all(conn)
+                .await
+                .map_err(|err| AppError::from((err, AppUseCase::ListArticles)))
+        })
+        .await?;
+        self.with_tags(conn, &mut res.items).await?;
+        Ok(res)
+    }
 
-        res
+    /// Add the tags of all the `articles`, using one query.
+    async fn with_tags(
+        &self,
+        conn: &Pool<Postgres>,
+        articles: &mut [Article],
+    ) -> Result<(), AppError> {
+        //
+        if articles.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<i64> = articles.iter().map(|a| a.id).collect();
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        sqlx::query("SELECT article_id, tag FROM tags_articles WHERE article_id = ANY($1)")
+            .bind(&ids)
+            .map(|r: PgRow| (r.get::<i64, _>("article_id"), r.get::<String, _>("tag")))
+            .fetch_all(conn)
+            .await
+            .map_err(|err| AppError::from((err, AppUseCase::ListArticles)))?
+            .into_iter()
+            .for_each(|(id, tag)| tags.entry(id).or_default().push(tag));
+        for a in articles.iter_mut() {
+            a.tag_list.extend(tags.remove(&a.id).unwrap_or_default());
+        }
+        Ok(())
     }
 
-    pub async fn get_article(&self, slug: &String) -> Result<Option<Article>, AppError> {
+    pub async fn get_article(
+        &self,
+        slug: &String,
+        viewer: Option<&UserId>,
+    ) -> Result<Option<Article>, AppError> {
         //
         let conn = self.dbcp.as_ref();
-        let mut article = sqlx::query(
-            "SELECT COUNT(fa.user_id), a.id, a.slug, a.title, a.description, a.body, a.created_at, a.updated_at,
-             u.id as user_id, u.username, u.bio, u.image, COUNT(f.user_id) as following
-             FROM articles a
-             JOIN accounts u ON a.author_id = u.id
-             LEFT OUTER JOIN followings f ON u.id = f.user_id
-             LEFT OUTER JOIN favorited_articles fa ON a.id = fa.article_id
-             WHERE a.slug = $1
-             GROUP BY a.id, u.id, u.username, u.bio, u.image"
-        )
-        .bind(slug)
-        .map(|r: PgRow| {
-            let following = r.get::<i64, _>("following") > 0;
-            let author: UserProfile = UserProfile {
-                user_id: r.get("user_id"),
-                username: r.get("username"),
-                bio: r.get("bio"),
-                image: r.try_get("image").unwrap_or_default(),
-                following,
-            };
-            Article::new(
-                r.get("id"),
-                r.get("slug"),
-                r.get("title"),
-                r.get("description"),
-                r.get("body"),
-                r.get("created_at"),
-                r.get("updated_at"),
-                author,
-            )
-        }).fetch_optional(conn).await?;
+        let mut article = sqlx::query(&format!("{ARTICLES_SELECT} WHERE a.slug = $2"))
+            .bind(viewer.map(|v| v.as_value()))
+            .bind(slug)
+            .map(|r: PgRow| Self::article_from_row(&r))
+            .fetch_optional(conn)
+            .await?;
 
         if let Some(ref mut a) = article {
             self.get_tags(conn, a).await?;
@@ -106,6 +180,75 @@ impl ArticlesRepo {
         Ok(article)
     }
 
+    fn article_from_row(r: &PgRow) -> Article {
+        //
+        let author: UserProfile = UserProfile {
+            user_id: r.get("user_id"),
+            username: r.get("username"),
+            bio: r.get("bio"),
+            image: User::normalize_image(r.try_get("image").unwrap_or_default()),
+            following: r.get("following"),
+            followers_count: None,
+            following_count: None,
+        };
+        let mut a = Article::new(
+            r.get("id"),
+            r.get("slug"),
+            r.get("title"),
+            r.get("description"),
+            r.get("body"),
+            r.get("created_at"),
+            r.get("updated_at"),
+            author,
+        );
+        a.favorites_count = r.get("favorites_count");
+        a.favorited = r.get("favorited");
+        a
+    }
+
+    /// Mark the article as favorited by the `viewer`. Favoriting it again is not an error.
+    pub async fn favorite(&self, viewer: &UserId, slug: &str) -> Result<(), AppError> {
+        //
+        let article_id = self.get_id_by_slug(slug).await?;
+        match sqlx::query(
+            "INSERT INTO favorited_articles (article_id, user_id) VALUES ($1, $2)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(article_id)
+        .bind(viewer.as_value())
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(AppError::from((err, AppUseCase::FavoriteArticle))),
+        }
+    }
+
+    /// Unmark the article as favorited by the `viewer`. It is a no-op if it was not favorited.
+    pub async fn unfavorite(&self, viewer: &UserId, slug: &str) -> Result<(), AppError> {
+        //
+        let article_id = self.get_id_by_slug(slug).await?;
+        match sqlx::query("DELETE FROM favorited_articles WHERE article_id = $1 AND user_id = $2")
+            .bind(article_id)
+            .bind(viewer.as_value())
+            .execute(self.dbcp.as_ref())
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => Err(AppError::from((err, AppUseCase::FavoriteArticle))),
+        }
+    }
+
+    async fn get_id_by_slug(&self, slug: &str) -> Result<i64, AppError> {
+        //
+        sqlx::query("SELECT id FROM articles WHERE slug = $1")
+            .bind(slug)
+            .map(|r: PgRow| r.get::<i64, _>("id"))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+            .map_err(|err| AppError::from((err, AppUseCase::FavoriteArticle)))
+    }
+
     async fn get_tags(&self, conn: &Pool<Postgres>, a: &mut Article) -> Result<(), AppError> {
         //
         sqlx::query("SELECT tag FROM tags_articles WHERE article_id = $1")
@@ -116,12 +259,12 @@ impl ArticlesRepo {
         Ok(())
     }
 
-    /// Add an `Article` into the store. It updates its `id`, `created_at` and `updated_at` attributes.
+    /// Add an `Article` into the store. It updates its `id`, `created_at` and `updated_at` attributes. <br/>
+    /// The article and its tags are added within one transaction, thus none of them is added on a failure.
     pub async fn add(&self, a: &mut Article) -> Result<(), AppError> {
         //
-        let mut txn = self.dbcp.begin().await?;
-
-        match sqlx::query(
+        let mut tx = Tx::begin(self.dbcp.as_ref()).await?;
+        let row = sqlx::query(
             "INSERT INTO articles (slug, title, description, body, author_id) 
             VALUES ($1, $2, $3, $4, $5) RETURNING id, created_at",
         )
@@ -130,37 +273,17 @@ impl ArticlesRepo {
         .bind(&a.description)
         .bind(&a.body)
         .bind(a.author.user_id)
-        .fetch_one(&mut txn)
+        .fetch_one(&mut *tx)
         .await
-        {
-            Ok(row) => {
-                a.id = row.get("id");
-                a.created_at = row.get("created_at");
-                a.updated_at = a.created_at
-            }
-            Err(err) => {
-                let res_err = ArticlesRepo::render_app_error(err, &a.slug);
-
-                if let Err(e) = txn.rollback().await {
-                    log::error!("Txn rollback after insert into articles failed: {}", e)
-                };
-                return Err(res_err);
-            }
-        }
-
-        if self
-            .set_tags(&mut txn, a.id, &a.tag_list, true)
+        .map_err(|err| ArticlesRepo::render_app_error(err, &a.slug))?;
+        a.id = row.get("id");
+        a.created_at = row.get("created_at");
+        a.updated_at = a.created_at;
+        // On an error, the transaction is rolled back as it gets dropped.
+        self.set_tags(&mut tx, a.id, &a.tag_list, true)
             .await
-            .is_err()
-        {
-            if let Err(err) = &mut txn.rollback().await {
-                log::error!("Txn rollback after set_tags(_) failed: {}", err);
-                return Err(AppError::InternalErr);
-            }
-        } else {
-            txn.commit().await?;
-        }
-        Ok(())
+            .map_err(|_| AppError::InternalErr)?;
+        tx.commit().await
     }
 
     pub async fn delete(&self, slug: String) -> Result<(), AppError> {
@@ -241,6 +364,18 @@ impl ArticlesRepo {
                 return Err(err);
             }
         }
+        // Register the (normalized) tags, so that they are known by `TagsRepo`.
+        if let Err(err) = sqlx::query(
+            "INSERT INTO tags (name) SELECT DISTINCT lower(trim(t)) FROM UNNEST($1::varchar[]) AS t
+             WHERE trim(t) <> '' ON CONFLICT DO NOTHING",
+        )
+        .bind(tag_list)
+        .execute(&mut *txn)
+        .await
+        {
+            log::error!("Failed to register tags: {}", err);
+            return Err(err);
+        }
         Ok(())
     }
 
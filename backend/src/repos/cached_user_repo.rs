@@ -0,0 +1,367 @@
+use crate::{
+    domain::model::{
+        Password, User, UserEntry, UserId, UserProfile, UserUpdate, ValidatedRegister,
+    },
+    repos::UserRepository,
+    AppError, AppUseCase,
+};
+use axum::async_trait;
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The maximum number of profiles kept by `CachedUserRepo`.
+pub const PROFILES_CACHE_CAPACITY: usize = 1024;
+
+/// For how long a profile is kept by `CachedUserRepo`.
+pub const PROFILES_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// The key of a cached profile: the viewer's id (if any) and the profile's user id.
+type ProfileKey = (Option<i64>, i64);
+
+/// A decorator of a `UserRepository` that caches (read-through) the profiles
+/// read by `get_profile_by_id`, in an LRU cache with a short TTL. <br/>
+/// The cached profiles of a user are invalidated when it is updated, or (un)followed.
+pub struct CachedUserRepo {
+    inner: Arc<dyn UserRepository>,
+    profiles: Mutex<LruCache<ProfileKey, (Instant, UserProfile)>>,
+    ttl: Duration,
+}
+
+impl CachedUserRepo {
+    //
+    pub fn new(inner: Arc<dyn UserRepository>) -> Self {
+        Self::with_settings(inner, PROFILES_CACHE_CAPACITY, PROFILES_CACHE_TTL)
+    }
+
+    /// Create it with the provided (instead of the default) cache `capacity` and `ttl`.
+    pub fn with_settings(inner: Arc<dyn UserRepository>, capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner,
+            profiles: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    /// Get the cached profile, if it is not expired.
+    fn cached(&self, key: &ProfileKey) -> Option<UserProfile> {
+        //
+        let mut profiles = self.profiles.lock().unwrap();
+        match profiles.get(key) {
+            Some((cached_at, profile)) if cached_at.elapsed() < self.ttl => Some(profile.clone()),
+            Some(_) => {
+                profiles.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remove the cached profiles of the provided users, for any viewer.
+    fn invalidate(&self, user_ids: &[i64]) {
+        //
+        let mut profiles = self.profiles.lock().unwrap();
+        let keys: Vec<ProfileKey> = profiles
+            .iter()
+            .filter(|((_, id), _)| user_ids.contains(id))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in keys {
+            profiles.pop(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepo {
+    //
+    async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
+        self.inner.save(reg, pwd, idempotency_key).await
+    }
+
+    async fn get_by_email(
+        &self,
+        email: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError> {
+        self.inner.get_by_email(email, usecase).await
+    }
+
+    async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
+        self.inner.get_by_id(id, usecase).await
+    }
+
+    async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+        self.inner.get_by_email_opt(email).await
+    }
+
+    async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+        self.inner.get_by_id_opt(id).await
+    }
+
+    async fn get_profile_by_username(
+        &self,
+        viewer: Option<&UserId>,
+        username: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserProfile, AppError> {
+        self.inner
+            .get_profile_by_username(viewer, username, usecase)
+            .await
+    }
+
+    async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let key = (viewer.map(UserId::as_value), id.as_value());
+        if let Some(profile) = self.cached(&key) {
+            return Ok(profile);
+        }
+        let profile = self.inner.get_profile_by_id(id, viewer).await?;
+        self.profiles
+            .lock()
+            .unwrap()
+            .put(key, (Instant::now(), profile.clone()));
+        Ok(profile)
+    }
+
+    async fn follow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let profile = self
+            .inner
+            .follow_user(curr_user_id, followed_username)
+            .await?;
+        self.invalidate(&[profile.user_id, curr_user_id.as_value()]);
+        Ok(profile)
+    }
+
+    async fn unfollow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let profile = self
+            .inner
+            .unfollow_user(curr_user_id, followed_username)
+            .await?;
+        self.invalidate(&[profile.user_id, curr_user_id.as_value()]);
+        Ok(profile)
+    }
+
+    async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+        self.inner.update_password(id, pwd).await
+    }
+
+    async fn update_by_id(
+        &self,
+        actor: &UserId,
+        id: UserId,
+        changes: UserUpdate,
+    ) -> Result<UserEntry, AppError> {
+        //
+        let user_id = id.as_value();
+        let entry = self.inner.update_by_id(actor, id, changes).await?;
+        self.invalidate(&[user_id]);
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{domain::model::RegisterInput, repos::InMemoryUsersRepo};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An in-memory repo that counts its `get_profile_by_id` calls.
+    #[derive(Default)]
+    struct SpyRepo {
+        inner: InMemoryUsersRepo,
+        profile_reads: AtomicUsize,
+    }
+
+    impl SpyRepo {
+        fn profile_reads(&self) -> usize {
+            self.profile_reads.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl UserRepository for SpyRepo {
+        //
+        async fn save(
+            &self,
+            reg: &ValidatedRegister,
+            pwd: &Password,
+            idempotency_key: Option<&str>,
+        ) -> Result<User, AppError> {
+            self.inner.save(reg, pwd, idempotency_key).await
+        }
+
+        async fn get_by_email(
+            &self,
+            email: &String,
+            usecase: AppUseCase,
+        ) -> Result<UserEntry, AppError> {
+            self.inner.get_by_email(email, usecase).await
+        }
+
+        async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
+            self.inner.get_by_id(id, usecase).await
+        }
+
+        async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+            self.inner.get_by_email_opt(email).await
+        }
+
+        async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+            self.inner.get_by_id_opt(id).await
+        }
+
+        async fn get_profile_by_username(
+            &self,
+            viewer: Option<&UserId>,
+            username: &String,
+            usecase: AppUseCase,
+        ) -> Result<UserProfile, AppError> {
+            self.inner
+                .get_profile_by_username(viewer, username, usecase)
+                .await
+        }
+
+        async fn get_profile_by_id(
+            &self,
+            id: &UserId,
+            viewer: Option<&UserId>,
+        ) -> Result<UserProfile, AppError> {
+            self.profile_reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.get_profile_by_id(id, viewer).await
+        }
+
+        async fn follow_user(
+            &self,
+            curr_user_id: &UserId,
+            followed_username: &String,
+        ) -> Result<UserProfile, AppError> {
+            self.inner
+                .follow_user(curr_user_id, followed_username)
+                .await
+        }
+
+        async fn unfollow_user(
+            &self,
+            curr_user_id: &UserId,
+            followed_username: &String,
+        ) -> Result<UserProfile, AppError> {
+            self.inner
+                .unfollow_user(curr_user_id, followed_username)
+                .await
+        }
+
+        async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+            self.inner.update_password(id, pwd).await
+        }
+
+        async fn update_by_id(
+            &self,
+            actor: &UserId,
+            id: UserId,
+            changes: UserUpdate,
+        ) -> Result<UserEntry, AppError> {
+            self.inner.update_by_id(actor, id, changes).await
+        }
+    }
+
+    async fn save(repo: &dyn UserRepository, username: &str) -> UserId {
+        let reg = RegisterInput {
+            email: format!("{username}@example.com"),
+            username: username.into(),
+            password: "test-password".into(),
+        }
+        .validate()
+        .unwrap();
+        let pwd = Password::generate(reg.password()).unwrap();
+        UserId::from_trusted(repo.save(&reg, &pwd, None).await.unwrap().id)
+    }
+
+    fn cached_repo(ttl: Duration) -> (Arc<SpyRepo>, CachedUserRepo) {
+        let spy = Arc::new(SpyRepo::default());
+        let repo = CachedUserRepo::with_settings(spy.clone(), PROFILES_CACHE_CAPACITY, ttl);
+        (spy, repo)
+    }
+
+    #[tokio::test]
+    async fn a_second_read_hits_the_cache() {
+        let (spy, repo) = cached_repo(PROFILES_CACHE_TTL);
+        let ann = save(&repo, "ann").await;
+        let bob = save(&repo, "bob").await;
+
+        let first = repo.get_profile_by_id(&ann, None).await.unwrap();
+        let second = repo.get_profile_by_id(&ann, None).await.unwrap();
+        assert_eq!(spy.profile_reads(), 1);
+        assert_eq!(first.username, second.username);
+        // The profiles are cached per viewer.
+        repo.get_profile_by_id(&ann, Some(&bob)).await.unwrap();
+        assert_eq!(spy.profile_reads(), 2);
+    }
+
+    #[tokio::test]
+    async fn an_expired_profile_is_read_again() {
+        let (spy, repo) = cached_repo(Duration::ZERO);
+        let ann = save(&repo, "ann").await;
+        repo.get_profile_by_id(&ann, None).await.unwrap();
+        repo.get_profile_by_id(&ann, None).await.unwrap();
+        assert_eq!(spy.profile_reads(), 2);
+    }
+
+    #[tokio::test]
+    async fn an_update_invalidates_the_cached_profiles() {
+        let (spy, repo) = cached_repo(PROFILES_CACHE_TTL);
+        let ann = save(&repo, "ann").await;
+        repo.get_profile_by_id(&ann, None).await.unwrap();
+
+        let changes = UserUpdate {
+            bio: Some("updated".into()),
+            ..UserUpdate::default()
+        };
+        repo.update_by_id(&ann, ann.clone(), changes).await.unwrap();
+        let profile = repo.get_profile_by_id(&ann, None).await.unwrap();
+        assert_eq!(spy.profile_reads(), 2);
+        assert_eq!(profile.bio, "updated");
+    }
+
+    #[tokio::test]
+    async fn a_follow_invalidates_the_cached_profiles() {
+        let (spy, repo) = cached_repo(PROFILES_CACHE_TTL);
+        let ann = save(&repo, "ann").await;
+        let bob = save(&repo, "bob").await;
+        let profile = repo.get_profile_by_id(&ann, Some(&bob)).await.unwrap();
+        assert!(!profile.following);
+
+        repo.follow_user(&bob, &"ann".to_string()).await.unwrap();
+        let profile = repo.get_profile_by_id(&ann, Some(&bob)).await.unwrap();
+        assert_eq!(spy.profile_reads(), 2);
+        assert!(profile.following);
+
+        repo.unfollow_user(&bob, &"ann".to_string()).await.unwrap();
+        let profile = repo.get_profile_by_id(&ann, Some(&bob)).await.unwrap();
+        assert_eq!(spy.profile_reads(), 3);
+        assert!(!profile.following);
+    }
+}
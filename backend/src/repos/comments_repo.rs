@@ -0,0 +1,172 @@
+use crate::{
+    db::DbConnPool,
+    domain::{
+        model::{Comment, User, UserId, UserProfile},
+        Page, Pagination,
+    },
+    AppError,
+};
+use sqlx::{postgres::PgRow, Row};
+use std::sync::Arc;
+
+/// The common part of the queries that read comments. <br/>
+/// `$1` is the id of the viewer (if any), used for resolving the author's `following` flag.
+const COMMENTS_SELECT: &str = "
+    SELECT c.id, c.body, c.created_at, c.updated_at,
+           u.id AS user_id, u.username, u.bio, u.image,
+           count(*) OVER () AS total_count,
+           EXISTS(SELECT 1 FROM followings f
+                  WHERE f.followed_user_id = u.id AND f.user_id = $1) AS following
+    FROM comments c
+    JOIN accounts u ON c.author_id = u.id";
+
+#[derive(Clone)]
+pub struct CommentsRepo {
+    dbcp: Arc<DbConnPool>,
+}
+
+impl CommentsRepo {
+    //
+    pub fn new(dbcp: Arc<DbConnPool>) -> Self {
+        Self { dbcp }
+    }
+
+    /// Add a comment of the `author` on the article with the provided slug.
+    pub async fn create(
+        &self,
+        article_slug: &str,
+        author: &UserId,
+        body: &str,
+    ) -> Result<Comment, AppError> {
+        //
+        if body.trim().is_empty() {
+            return Err(AppError::InvalidRequest("comment body is empty".into()));
+        }
+        match sqlx::query(
+            "WITH c AS (
+                INSERT INTO comments (body, article_id, author_id)
+                SELECT $2, a.id, $1 FROM articles a WHERE a.slug = $3
+                RETURNING id, body, created_at, updated_at, author_id
+             )
+             SELECT c.id, c.body, c.created_at, c.updated_at,
+                    u.id AS user_id, u.username, u.bio, u.image, false AS following
+             FROM c JOIN accounts u ON c.author_id = u.id",
+        )
+        .bind(author.as_value())
+        .bind(body)
+        .bind(article_slug)
+        .map(|r: PgRow| Self::comment_from_row(&r))
+        .fetch_optional(self.dbcp.as_ref())
+        .await
+        {
+            Ok(Some(comment)) => Ok(comment),
+            Ok(None) => Err(AppError::NotFound("article".into())),
+            Err(err) => {
+                log::error!("Failed to create the comment: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Get a page of the comments of the article with the provided slug, oldest first
+    /// (so that they read as a conversation). <br/>
+    /// The author's `following` flag is resolved against the `viewer`, if any.
+    pub async fn list_for_article(
+        &self,
+        article_slug: &str,
+        viewer: Option<&UserId>,
+        page: &Pagination,
+    ) -> Result<Page<Comment>, AppError> {
+        //
+        let conn = self.dbcp.as_ref();
+        let sql = &format!(
+            "{COMMENTS_SELECT}
+             JOIN articles a ON c.article_id = a.id
+             WHERE a.slug = $2 ORDER BY c.created_at ASC, c.id ASC OFFSET $3 LIMIT $4"
+        );
+        let res = Page::fetch_counted(page, |page| async move {
+            sqlx::query(sql)
+                .bind(viewer.map(|v| v.as_value()))
+                .bind(article_slug)
+                .bind(page.offset())
+                .bind(page.limit())
+                .map(|r: PgRow| (Self::comment_from_row(&r), r.get("total_count")))
+                .fetch_all(conn)
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to list the comments: {err}");
+                    AppError::InternalErr
+                })
+        })
+        .await?;
+        // No comments might also mean that the article does not exist.
+        if res.total == 0 {
+            let exists = sqlx::query("SELECT EXISTS(SELECT 1 FROM articles WHERE slug = $1)")
+                .bind(article_slug)
+                .map(|r: PgRow| r.get::<bool, _>(0))
+                .fetch_one(conn)
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to check if the article exists: {err}");
+                    AppError::InternalErr
+                })?;
+            if !exists {
+                return Err(AppError::NotFound("article".into()));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Delete a comment. Only its author (the `requester`) can delete it.
+    pub async fn delete(&self, comment_id: i64, requester: &UserId) -> Result<(), AppError> {
+        //
+        let conn = self.dbcp.as_ref();
+        let author_id = sqlx::query("SELECT author_id FROM comments WHERE id = $1")
+            .bind(comment_id)
+            .map(|r: PgRow| r.get::<i64, _>("author_id"))
+            .fetch_optional(conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to get the author of the comment: {err}");
+                AppError::InternalErr
+            })?;
+        match author_id {
+            Some(author_id) if author_id == requester.as_value() => {
+                match sqlx::query("DELETE FROM comments WHERE id = $1")
+                    .bind(comment_id)
+                    .execute(conn)
+                    .await
+                {
+                    Ok(_) => Ok(()),
+                    Err(err) => {
+                        log::error!("Failed to delete the comment: {err}");
+                        Err(AppError::InternalErr)
+                    }
+                }
+            }
+            Some(_) => Err(AppError::Forbidden(
+                "only the author of a comment can delete it".into(),
+            )),
+            None => Err(AppError::NotFound("comment".into())),
+        }
+    }
+
+    fn comment_from_row(r: &PgRow) -> Comment {
+        //
+        Comment {
+            id: r.get("id"),
+            created_at: r.get("created_at"),
+            updated_at: r.get("updated_at"),
+            body: r.get("body"),
+            author: UserProfile {
+                user_id: r.get("user_id"),
+                username: r.get("username"),
+                bio: r.get("bio"),
+                image: User::normalize_image(r.get("image")),
+                following: r.get("following"),
+                followers_count: None,
+                following_count: None,
+            },
+        }
+    }
+}
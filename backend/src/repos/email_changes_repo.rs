@@ -0,0 +1,142 @@
+use crate::{
+    auth::{hash_refresh_token, new_refresh_token, EMAIL_CHANGE_TOKEN_TTL_HOURS},
+    db::DbConnPool,
+    domain::model::{Email, UserId},
+    AppError, AppUseCase,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, Row};
+use std::sync::Arc;
+
+/// The email changes that need to be confirmed (using a token sent to the new email)
+/// before taking effect. Until then, the account's email remains unchanged. <br/>
+/// The tokens are generated (and hashed) the same way as the refresh tokens.
+pub struct EmailChangesRepo {
+    dbcp: Arc<DbConnPool>,
+}
+
+impl EmailChangesRepo {
+    //
+    pub fn new(dbcp: Arc<DbConnPool>) -> Self {
+        Self { dbcp }
+    }
+
+    /// Request the change of the user's email to the `new_email`. <br/>
+    /// It returns the confirmation token (see `confirm_email_change`), as only its hash is stored.
+    /// Any previously requested (and not yet confirmed) change of the user is replaced.
+    /// An email that is already used is `AppError::AlreadyExists`.
+    pub async fn request_email_change(
+        &self,
+        id: &UserId,
+        new_email: &str,
+    ) -> Result<String, AppError> {
+        //
+        let new_email = Email::try_new(new_email)?;
+        let taken = sqlx::query("SELECT EXISTS(SELECT 1 FROM accounts WHERE lower(email) = $1)")
+            .bind(new_email.as_str())
+            .map(|row: PgRow| row.get::<bool, _>(0))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+            .map_err(Self::err)?;
+        if taken {
+            return Err(AppError::AlreadyExists("email".into()));
+        }
+        let token = new_refresh_token();
+        match sqlx::query(
+            "INSERT INTO email_changes (user_id, new_email, token_hash, expires_at)
+             VALUES ($1, $2, $3, now() + make_interval(hours => $4::int))
+             ON CONFLICT (user_id) DO UPDATE SET new_email = $2, token_hash = $3,
+                 created_at = now(), expires_at = now() + make_interval(hours => $4::int)",
+        )
+        .bind(id.as_value())
+        .bind(new_email.as_str())
+        .bind(hash_refresh_token(&token))
+        .bind(EMAIL_CHANGE_TOKEN_TTL_HOURS)
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(_) => Ok(token),
+            // A foreign key violation (23503) means that the user does not exist.
+            Err(err)
+                if err
+                    .as_database_error()
+                    .and_then(|e| e.code())
+                    .is_some_and(|code| code == "23503") =>
+            {
+                Err(AppError::NotFound("user".into()))
+            }
+            Err(err) => Err(Self::err(err)),
+        }
+    }
+
+    /// Confirm the email change of the provided token, making the new email the account's one. <br/>
+    /// An unknown or expired token is `AppError::InvalidRequest` (and an expired change is removed).
+    /// If the new email was taken meanwhile, it is `AppError::AlreadyExists`, and the change
+    /// remains pending (until it expires). It returns the id of the user.
+    pub async fn confirm_email_change(&self, token: &str) -> Result<UserId, AppError> {
+        //
+        let mut txn = self.dbcp.begin().await.map_err(Self::err)?;
+        let pending = sqlx::query(
+            "DELETE FROM email_changes WHERE token_hash = $1
+             RETURNING user_id, new_email, expires_at",
+        )
+        .bind(hash_refresh_token(token))
+        .map(|row: PgRow| {
+            (
+                row.get::<i64, _>("user_id"),
+                row.get::<String, _>("new_email"),
+                row.get::<DateTime<Utc>, _>("expires_at"),
+            )
+        })
+        .fetch_optional(&mut txn)
+        .await
+        .map_err(Self::err)?;
+        let (user_id, new_email, expires_at) = match pending {
+            Some(pending) => pending,
+            None => {
+                return Err(AppError::InvalidRequest(
+                    "unknown email change token".into(),
+                ))
+            }
+        };
+        if expires_at <= Utc::now() {
+            txn.commit().await.map_err(Self::err)?;
+            return Err(AppError::InvalidRequest(
+                "email change token is expired".into(),
+            ));
+        }
+        match sqlx::query(
+            "UPDATE accounts SET email = $1, version = version + 1, updated_at = now()
+             WHERE id = $2 AND deleted_at IS NULL",
+        )
+        .bind(&new_email)
+        .bind(user_id)
+        .execute(&mut txn)
+        .await
+        {
+            Ok(res) if res.rows_affected() == 0 => return Err(AppError::NotFound("user".into())),
+            Ok(_) => {}
+            Err(err) => return Err(AppError::from((err, AppUseCase::UpdateUser))),
+        }
+        txn.commit().await.map_err(Self::err)?;
+        Ok(UserId::from_trusted(user_id))
+    }
+
+    /// Delete the expired email changes. It returns the number of deleted ones.
+    pub async fn delete_expired(&self) -> Result<u64, AppError> {
+        //
+        match sqlx::query("DELETE FROM email_changes WHERE expires_at <= now()")
+            .execute(self.dbcp.as_ref())
+            .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(err) => Err(Self::err(err)),
+        }
+    }
+
+    fn err(err: sqlx::Error) -> AppError {
+        //
+        log::error!("Failed to handle the email changes: {err}");
+        AppError::InternalErr
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    domain::model::{
+        Password, User, UserEntry, UserId, UserProfile, UserUpdate, ValidatedRegister,
+    },
+    repos::UserRepository,
+    AppError, AppUseCase,
+};
+use axum::async_trait;
+use metrics::increment_counter;
+use std::{future::Future, sync::Arc};
+
+/// The counter of the calls, labeled by `method`.
+pub const REPO_CALLS_TOTAL: &str = "repo_calls_total";
+
+/// The counter of the failed calls, labeled by `method` and `kind` (see `AppError::code`).
+pub const REPO_ERRORS_TOTAL: &str = "repo_errors_total";
+
+/// A decorator of a `UserRepository` that counts the calls and the errors of each method. <br/>
+/// The counters are reported to the installed `metrics` recorder. Without one, counting is a no-op.
+pub struct MeteredUserRepo {
+    inner: Arc<dyn UserRepository>,
+}
+
+impl MeteredUserRepo {
+    //
+    pub fn new(inner: Arc<dyn UserRepository>) -> Self {
+        Self { inner }
+    }
+
+    /// Count the call of the `method`, and its error (if any).
+    async fn metered<T>(
+        method: &'static str,
+        call: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        //
+        increment_counter!(REPO_CALLS_TOTAL, "method" => method);
+        let res = call.await;
+        if let Err(err) = &res {
+            increment_counter!(REPO_ERRORS_TOTAL, "method" => method, "kind" => err.code());
+        }
+        res
+    }
+}
+
+#[async_trait]
+impl UserRepository for MeteredUserRepo {
+    //
+    async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
+        Self::metered("save", self.inner.save(reg, pwd, idempotency_key)).await
+    }
+
+    async fn get_by_email(
+        &self,
+        email: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError> {
+        Self::metered("get_by_email", self.inner.get_by_email(email, usecase)).await
+    }
+
+    async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
+        Self::metered("get_by_id", self.inner.get_by_id(id, usecase)).await
+    }
+
+    async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+        Self::metered("get_by_email_opt", self.inner.get_by_email_opt(email)).await
+    }
+
+    async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+        Self::metered("get_by_id_opt", self.inner.get_by_id_opt(id)).await
+    }
+
+    async fn get_profile_by_username(
+        &self,
+        viewer: Option<&UserId>,
+        username: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserProfile, AppError> {
+        Self::metered(
+            "get_profile_by_username",
+            self.inner
+                .get_profile_by_username(viewer, username, usecase),
+        )
+        .await
+    }
+
+    async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError> {
+        Self::metered(
+            "get_profile_by_id",
+            self.inner.get_profile_by_id(id, viewer),
+        )
+        .await
+    }
+
+    async fn follow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        Self::metered(
+            "follow_user",
+            self.inner.follow_user(curr_user_id, followed_username),
+        )
+        .await
+    }
+
+    async fn unfollow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        Self::metered(
+            "unfollow_user",
+            self.inner.unfollow_user(curr_user_id, followed_username),
+        )
+        .await
+    }
+
+    async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+        Self::metered("update_password", self.inner.update_password(id, pwd)).await
+    }
+
+    async fn update_by_id(
+        &self,
+        actor: &UserId,
+        id: UserId,
+        changes: UserUpdate,
+    ) -> Result<UserEntry, AppError> {
+        Self::metered("update_by_id", self.inner.update_by_id(actor, id, changes)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repos::InMemoryUsersRepo;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Recorder, SharedString, Unit};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex, OnceLock,
+        },
+    };
+
+    /// A recorder that keeps the counters (by their name and labels) in memory.
+    #[derive(Default)]
+    struct TestRecorder {
+        counters: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+    }
+
+    impl TestRecorder {
+        /// Install it (once) as the global recorder.
+        fn installed() -> &'static TestRecorder {
+            static RECORDER: OnceLock<TestRecorder> = OnceLock::new();
+            let recorder = RECORDER.get_or_init(TestRecorder::default);
+            // It is already set by a previous call.
+            let _ = metrics::set_recorder(recorder);
+            recorder
+        }
+
+        /// The value of the counter having the `name` and (at least) the `labels`.
+        fn count(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+            let counters = self.counters.lock().unwrap();
+            counters
+                .iter()
+                .filter(|(key, _)| key.name() == name)
+                .filter(|(key, _)| {
+                    labels.iter().all(|(label, value)| {
+                        key.labels()
+                            .any(|l| l.key() == *label && l.value() == *value)
+                    })
+                })
+                .map(|(_, counter)| counter.load(Ordering::SeqCst))
+                .sum()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key) -> Counter {
+            let mut counters = self.counters.lock().unwrap();
+            Counter::from_arc(counters.entry(key.clone()).or_default().clone())
+        }
+
+        fn register_gauge(&self, _: &Key) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failed_call_increments_the_error_counter() {
+        let recorder = TestRecorder::installed();
+        let method = [("method", "get_by_id")];
+        let errors = [("method", "get_by_id"), ("kind", "not_found")];
+        let (calls_before, errors_before) = (
+            recorder.count(REPO_CALLS_TOTAL, &method),
+            recorder.count(REPO_ERRORS_TOTAL, &errors),
+        );
+
+        let repo = MeteredUserRepo::new(Arc::new(InMemoryUsersRepo::new()));
+        let res = repo
+            .get_by_id(&UserId::from_trusted(1), AppUseCase::GetUserProfile)
+            .await;
+        assert!(matches!(res, Err(AppError::NotFound(_))));
+        assert_eq!(recorder.count(REPO_CALLS_TOTAL, &method), calls_before + 1);
+        assert_eq!(
+            recorder.count(REPO_ERRORS_TOTAL, &errors),
+            errors_before + 1
+        );
+
+        // A successful call is counted, but not as an error.
+        repo.get_by_id_opt(&UserId::from_trusted(1)).await.unwrap();
+        let opt_method = [("method", "get_by_id_opt")];
+        assert!(recorder.count(REPO_CALLS_TOTAL, &opt_method) >= 1);
+        assert_eq!(recorder.count(REPO_ERRORS_TOTAL, &opt_method), 0);
+    }
+}
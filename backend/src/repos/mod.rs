@@ -1,5 +1,35 @@
+mod aggregate;
+pub use aggregate::*;
+
 mod articles_repo;
 pub use articles_repo::*;
 
+mod cached_user_repo;
+pub use cached_user_repo::*;
+
+mod comments_repo;
+pub use comments_repo::*;
+
+mod email_changes_repo;
+pub use email_changes_repo::*;
+
+mod metered_user_repo;
+pub use metered_user_repo::*;
+
+mod refresh_tokens_repo;
+pub use refresh_tokens_repo::*;
+
+mod sort;
+pub use sort::*;
+
 mod users_repo;
 pub use users_repo::*;
+
+mod tags_repo;
+pub use tags_repo::*;
+
+mod user_repository;
+pub use user_repository::*;
+
+mod users_repo_mem;
+pub use users_repo_mem::*;
@@ -0,0 +1,134 @@
+use crate::{
+    auth::{hash_refresh_token, new_refresh_token, REFRESH_TOKEN_TTL_DAYS},
+    db::DbConnPool,
+    domain::model::UserId,
+    AppError,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, Postgres, Row, Transaction};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct RefreshTokensRepo {
+    dbcp: Arc<DbConnPool>,
+}
+
+impl RefreshTokensRepo {
+    //
+    pub fn new(dbcp: Arc<DbConnPool>) -> Self {
+        Self { dbcp }
+    }
+
+    /// Issue a refresh token for the provided user, starting a new family (of rotations).
+    pub async fn issue_refresh(&self, user_id: &UserId) -> Result<String, AppError> {
+        //
+        let mut txn = self.dbcp.begin().await.map_err(Self::err)?;
+        let token = Self::insert_tx(&mut txn, user_id.as_value(), Uuid::new_v4()).await?;
+        txn.commit().await.map_err(Self::err)?;
+        Ok(token)
+    }
+
+    /// Rotate the provided refresh token: it gets used (thus invalidated) and
+    /// a new one (of the same family) is returned, along with the user id. <br/>
+    /// An unknown, expired, or revoked token is `AppError::Unauthorized`. Presenting an already
+    /// used token is detected as a reuse, thus the whole family is revoked (and it is `Unauthorized`).
+    pub async fn rotate(&self, old: &str) -> Result<(UserId, String), AppError> {
+        //
+        let mut txn = self.dbcp.begin().await.map_err(Self::err)?;
+        // The row is locked, so that concurrent rotations of the same token are serialized.
+        let found = sqlx::query(
+            "SELECT id, user_id, family_id, expires_at, used_at, revoked_at FROM refresh_tokens
+             WHERE token_hash = $1 FOR UPDATE",
+        )
+        .bind(hash_refresh_token(old))
+        .map(|row: PgRow| {
+            (
+                row.get::<i64, _>("id"),
+                row.get::<i64, _>("user_id"),
+                row.get::<Uuid, _>("family_id"),
+                row.get::<DateTime<Utc>, _>("expires_at"),
+                row.get::<Option<DateTime<Utc>>, _>("used_at"),
+                row.get::<Option<DateTime<Utc>>, _>("revoked_at"),
+            )
+        })
+        .fetch_optional(&mut txn)
+        .await
+        .map_err(Self::err)?;
+        let (id, user_id, family_id, expires_at, used_at, revoked_at) = match found {
+            Some(found) => found,
+            None => return Err(AppError::Unauthorized("unknown refresh token".into())),
+        };
+        if revoked_at.is_some() {
+            return Err(AppError::Unauthorized("refresh token is revoked".into()));
+        }
+        if used_at.is_some() {
+            log::warn!("Reuse of a refresh token of user {user_id}. Revoking its family.");
+            sqlx::query(
+                "UPDATE refresh_tokens SET revoked_at = now()
+                 WHERE family_id = $1 AND revoked_at IS NULL",
+            )
+            .bind(family_id)
+            .execute(&mut txn)
+            .await
+            .map_err(Self::err)?;
+            txn.commit().await.map_err(Self::err)?;
+            return Err(AppError::Unauthorized("refresh token reuse".into()));
+        }
+        if expires_at <= Utc::now() {
+            return Err(AppError::Unauthorized("refresh token is expired".into()));
+        }
+        sqlx::query("UPDATE refresh_tokens SET used_at = now() WHERE id = $1")
+            .bind(id)
+            .execute(&mut txn)
+            .await
+            .map_err(Self::err)?;
+        let token = Self::insert_tx(&mut txn, user_id, family_id).await?;
+        txn.commit().await.map_err(Self::err)?;
+        Ok((UserId::from_trusted(user_id), token))
+    }
+
+    /// Revoke all the (not yet revoked) refresh tokens of the provided user,
+    /// such as on logout or password change. It returns the number of revoked ones.
+    pub async fn revoke(&self, user_id: &UserId) -> Result<u64, AppError> {
+        //
+        match sqlx::query(
+            "UPDATE refresh_tokens SET revoked_at = now()
+             WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(user_id.as_value())
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(res) => Ok(res.rows_affected()),
+            Err(err) => Err(Self::err(err)),
+        }
+    }
+
+    /// Insert a new refresh token, returning it (as only its hash is stored).
+    async fn insert_tx(
+        txn: &mut Transaction<'_, Postgres>,
+        user_id: i64,
+        family_id: Uuid,
+    ) -> Result<String, AppError> {
+        //
+        let token = new_refresh_token();
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+             VALUES ($1, $2, $3, now() + make_interval(days => $4::int))",
+        )
+        .bind(user_id)
+        .bind(family_id)
+        .bind(hash_refresh_token(&token))
+        .bind(REFRESH_TOKEN_TTL_DAYS)
+        .execute(&mut *txn)
+        .await
+        .map_err(Self::err)?;
+        Ok(token)
+    }
+
+    fn err(err: sqlx::Error) -> AppError {
+        //
+        log::error!("Failed to handle the refresh tokens: {err}");
+        AppError::InternalErr
+    }
+}
@@ -0,0 +1,85 @@
+//! The sort orders of the listings. <br/>
+//! Each variant maps to a fixed `ORDER BY` clause content, thus no (user provided)
+//! string ever reaches the SQL, and they are safe to be formatted into a query.
+
+/// The order of listing the users.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UserSortField {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+    UsernameAsc,
+    UsernameDesc,
+}
+
+impl UserSortField {
+    //
+    /// The `ORDER BY` clause content, for the `accounts` table.
+    pub fn order_clause(&self) -> &'static str {
+        match self {
+            UserSortField::NewestFirst => "created_at DESC, id DESC",
+            UserSortField::OldestFirst => "created_at ASC, id ASC",
+            UserSortField::UsernameAsc => "username ASC",
+            UserSortField::UsernameDesc => "username DESC",
+        }
+    }
+}
+
+/// The order of listing the articles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArticleSortField {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+    MostFavorited,
+}
+
+impl ArticleSortField {
+    //
+    /// The `ORDER BY` clause content, for the articles (`a`) query.
+    pub fn order_clause(&self) -> &'static str {
+        match self {
+            ArticleSortField::NewestFirst => "a.created_at DESC, a.id DESC",
+            ArticleSortField::OldestFirst => "a.created_at ASC, a.id ASC",
+            ArticleSortField::MostFavorited => "favorites_count DESC, a.id DESC",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A `SortField` is one of its (fixed) variants, thus no other (such as a user provided)
+    // string can be turned into a clause.
+    #[test]
+    fn the_user_order_clauses_are_the_expected_literals() {
+        assert_eq!(UserSortField::default(), UserSortField::NewestFirst);
+        for (order, clause) in [
+            (UserSortField::NewestFirst, "created_at DESC, id DESC"),
+            (UserSortField::OldestFirst, "created_at ASC, id ASC"),
+            (UserSortField::UsernameAsc, "username ASC"),
+            (UserSortField::UsernameDesc, "username DESC"),
+        ] {
+            assert_eq!(order.order_clause(), clause);
+        }
+    }
+
+    #[test]
+    fn the_article_order_clauses_are_the_expected_literals() {
+        assert_eq!(ArticleSortField::default(), ArticleSortField::NewestFirst);
+        for (order, clause) in [
+            (
+                ArticleSortField::NewestFirst,
+                "a.created_at DESC, a.id DESC",
+            ),
+            (ArticleSortField::OldestFirst, "a.created_at ASC, a.id ASC"),
+            (
+                ArticleSortField::MostFavorited,
+                "favorites_count DESC, a.id DESC",
+            ),
+        ] {
+            assert_eq!(order.order_clause(), clause);
+        }
+    }
+}
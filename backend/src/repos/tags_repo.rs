@@ -0,0 +1,68 @@
+use crate::{db::DbConnPool, domain::model::Tag, AppError};
+use sqlx::{postgres::PgRow, Row};
+use std::{collections::HashMap, sync::Arc};
+
+/// The maximum number of tags returned by a listing.
+pub const TAGS_MAX_LIMIT: i64 = 100;
+
+pub struct TagsRepo {
+    dbcp: Arc<DbConnPool>,
+}
+
+impl TagsRepo {
+    //
+    pub fn new(dbcp: Arc<DbConnPool>) -> Self {
+        Self { dbcp }
+    }
+
+    /// Get the names of the most used tags, ordered by the number of articles that use them.
+    pub async fn list_popular(&self, limit: i64) -> Result<Vec<String>, AppError> {
+        //
+        match sqlx::query(
+            "SELECT t.name FROM tags t
+             JOIN tags_articles ta ON lower(trim(ta.tag)) = t.name
+             GROUP BY t.name ORDER BY count(*) DESC, t.name LIMIT $1",
+        )
+        .bind(limit.clamp(1, TAGS_MAX_LIMIT))
+        .map(|row: PgRow| row.get("name"))
+        .fetch_all(self.dbcp.as_ref())
+        .await
+        {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("Failed to list the popular tags: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Make sure that the tags with the provided names exist, and get their ids. <br/>
+    /// The names are normalized (see `Tag::normalize_names`) and the ids follow their order.
+    pub async fn ensure(&self, names: &[String]) -> Result<Vec<i64>, AppError> {
+        //
+        let names = Tag::normalize_names(names)?;
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        // The no-op update makes the existing tags be returned as well.
+        let ids: HashMap<String, i64> = sqlx::query(
+            "INSERT INTO tags (name) SELECT UNNEST($1::varchar[])
+             ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+             RETURNING id, name",
+        )
+        .bind(&names)
+        .map(|row: PgRow| (row.get("name"), row.get("id")))
+        .fetch_all(self.dbcp.as_ref())
+        .await
+        .map_err(|err| {
+            log::error!("Failed to ensure the tags: {err}");
+            AppError::InternalErr
+        })?
+        .into_iter()
+        .collect();
+        names
+            .iter()
+            .map(|name| ids.get(name).copied().ok_or(AppError::InternalErr))
+            .collect()
+    }
+}
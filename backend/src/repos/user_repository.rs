@@ -0,0 +1,156 @@
+use crate::{
+    domain::model::{
+        Password, User, UserEntry, UserId, UserProfile, UserUpdate, ValidatedRegister,
+    },
+    repos::UsersRepo,
+    AppError, AppUseCase,
+};
+use axum::async_trait;
+
+/// The user related persistence operations that the logic and web api layers depend on. <br/>
+/// `UsersRepo` is the (Postgres based) implementation used by the app (through the
+/// `CachedUserRepo` decorator), while `InMemoryUsersRepo` can be used where a database is not available.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    //
+    /// Save the (validated) user, returning it as saved (with its id and timestamps). <br/>
+    /// A repeated save with the same `idempotency_key` returns the originally saved user.
+    async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError>;
+
+    async fn get_by_email(
+        &self,
+        email: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError>;
+
+    async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError>;
+
+    /// Like `get_by_email`, but a missing user is `None` and not an error.
+    async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError>;
+
+    /// Like `get_by_id`, but a missing user is `None` and not an error.
+    async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError>;
+
+    async fn get_profile_by_username(
+        &self,
+        viewer: Option<&UserId>,
+        username: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserProfile, AppError>;
+
+    /// Get the profile of the user with the provided `id`, with the `following` flag resolved against the `viewer`.
+    async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError>;
+
+    /// Make the current user follow the user with the given username, returning its profile.
+    async fn follow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError>;
+
+    /// Make the current user unfollow the user with the given username, returning its profile.
+    async fn unfollow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError>;
+
+    async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError>;
+
+    /// Update the user with the provided `id`, on behalf of the `actor` user. <br/>
+    /// A user can update only its own account, otherwise `AppError::Forbidden` is returned.
+    async fn update_by_id(
+        &self,
+        actor: &UserId,
+        id: UserId,
+        changes: UserUpdate,
+    ) -> Result<UserEntry, AppError>;
+}
+
+#[async_trait]
+impl UserRepository for UsersRepo {
+    //
+    async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
+        UsersRepo::save(self, reg, pwd, idempotency_key).await
+    }
+
+    async fn get_by_email(
+        &self,
+        email: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError> {
+        UsersRepo::get_by_email(self, email, usecase).await
+    }
+
+    async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
+        UsersRepo::get_by_id(self, id, usecase).await
+    }
+
+    async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+        UsersRepo::get_by_email_opt(self, email).await
+    }
+
+    async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+        UsersRepo::get_by_id_opt(self, id).await
+    }
+
+    async fn get_profile_by_username(
+        &self,
+        viewer: Option<&UserId>,
+        username: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserProfile, AppError> {
+        UsersRepo::get_profile_by_username(self, viewer, username, usecase).await
+    }
+
+    async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError> {
+        UsersRepo::get_profile_by_id(self, id, viewer).await
+    }
+
+    async fn follow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        UsersRepo::follow_user(self, curr_user_id, followed_username).await
+    }
+
+    async fn unfollow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        UsersRepo::unfollow_user(self, curr_user_id, followed_username).await
+    }
+
+    async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+        UsersRepo::update_password(self, id, pwd).await
+    }
+
+    async fn update_by_id(
+        &self,
+        actor: &UserId,
+        id: UserId,
+        changes: UserUpdate,
+    ) -> Result<UserEntry, AppError> {
+        UsersRepo::update_by_id(self, actor, id, changes).await
+    }
+}
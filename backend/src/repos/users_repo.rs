@@ -1,11 +1,53 @@
 use crate::{
-    db::DbConnPool,
-    domain::model::{User, UserEntry, UserId, UserProfile},
-    AppError, AppUseCase,
+    crypto::PasswordAlgo,
+    db::{self, DbConnPool},
+    domain::{
+        model::{
+            Email, Password, User, UserEntry, UserId, UserProfile, UserUpdate, Username,
+            ValidatedRegister,
+        },
+        Page, Pagination,
+    },
+    repos::UserSortField,
+    AppError, AppUseCase, SELF_FOLLOW_MSG,
 };
-use sqlx::{postgres::PgRow, FromRow, Row};
-use std::sync::Arc;
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    query::QueryAs,
+    FromRow, Postgres, Row, Transaction,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// The maximum number of entries returned by a search.
+pub const SEARCH_MAX_LIMIT: i64 = 50;
+
+/// For how long (in hours) an idempotency key of `UsersRepo::save` is considered.
+pub const IDEMPOTENCY_KEY_TTL_HOURS: i64 = 24;
+
+/// The outcome of `UsersRepo::save_or_get`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SaveOutcome {
+    /// The user was created, having this id.
+    Created(UserId),
+    /// A user with the same email or username (or a too similar username) already exists, having this id.
+    Existing(UserId),
+}
 
+const USER_ENTRY_BY_EMAIL: &str =
+    "SELECT id, email, username, password, salt, pwd_algo, bio, image, version, created_at, updated_at
+     FROM accounts 
+     WHERE lower(email) = lower($1) AND deleted_at IS NULL";
+
+const USER_ENTRY_BY_ID: &str =
+    "SELECT id, email, username, password, salt, pwd_algo, bio, image, version, created_at, updated_at
+     FROM accounts 
+     WHERE id = $1 AND deleted_at IS NULL";
+
+/// It is cheap to clone (sharing the connection pool), such as for using it in spawned tasks.
+#[derive(Clone)]
 pub struct UsersRepo {
     dbcp: Arc<DbConnPool>,
 }
@@ -16,230 +58,1192 @@ impl UsersRepo {
         Self { dbcp }
     }
 
-    pub async fn save(&self, user: &User, pwd: String, salt: String) -> Result<i64, AppError> {
+    /// Begin a transaction, to be used with the `*_tx` operations.
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, AppError> {
+        //
+        self.dbcp.begin().await.map_err(Self::txn_err)
+    }
+
+    /// Save the user. Its tracing span does not record the password (hash and salt) or the email. <br/>
+    /// If an `idempotency_key` is provided, a repeated save with the same (not yet expired) key
+    /// returns the originally saved user, instead of saving it again. <br/>
+    /// The saved user (with its id and timestamps) is built from the inserted row, without another query.
+    #[tracing::instrument(skip(self, reg, pwd, idempotency_key), fields(username = %reg.user().username))]
+    pub async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
+        //
+        let user = reg.user();
+        let key = match idempotency_key {
+            Some(key) => key,
+            None => {
+                return Self::save_query(user, pwd)
+                    .fetch_one(self.dbcp.as_ref())
+                    .await
+                    .map_err(|err| AppError::from((err, AppUseCase::UserRegistration)))
+            }
+        };
+        let mut txn = self.begin().await?;
+        // Concurrent saves with the same key are serialized, so only one of them inserts.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(key)
+            .execute(&mut txn)
+            .await
+            .map_err(Self::idempotency_err)?;
+        let existing = sqlx::query_as::<_, User>(
+            "SELECT a.id, a.email, a.username, a.bio, a.image, a.created_at, a.updated_at
+             FROM idempotency_keys k JOIN accounts a ON a.id = k.user_id
+             WHERE k.key = $1 AND k.created_at > now() - make_interval(hours => $2::int)",
+        )
+        .bind(key)
+        .bind(IDEMPOTENCY_KEY_TTL_HOURS)
+        .fetch_optional(&mut txn)
+        .await
+        .map_err(Self::idempotency_err)?;
+        if let Some(saved) = existing {
+            return Ok(saved);
+        }
+        let saved = self.save_tx(&mut txn, reg, pwd).await?;
+        // An expired key is reused.
+        sqlx::query(
+            "INSERT INTO idempotency_keys (key, user_id) VALUES ($1, $2)
+             ON CONFLICT (key) DO UPDATE SET user_id = $2, created_at = now()",
+        )
+        .bind(key)
+        .bind(saved.id)
+        .execute(&mut txn)
+        .await
+        .map_err(Self::idempotency_err)?;
+        txn.commit().await.map_err(Self::txn_err)?;
+        Ok(saved)
+    }
+
+    /// Delete the expired idempotency keys. It returns the number of deleted ones.
+    pub async fn delete_expired_idempotency_keys(&self) -> Result<u64, AppError> {
         //
         match sqlx::query(
-            "INSERT INTO accounts (email, username, password, salt) 
-             VALUES ($1, $2, $3, $4) RETURNING id",
+            "DELETE FROM idempotency_keys
+             WHERE created_at <= now() - make_interval(hours => $1::int)",
         )
-        .bind(&user.email)
-        .bind(&user.username)
-        .bind(pwd)
-        .bind(salt)
-        .fetch_one(self.dbcp.as_ref())
+        .bind(IDEMPOTENCY_KEY_TTL_HOURS)
+        .execute(self.dbcp.as_ref())
         .await
         {
-            Ok(row) => Ok(row.get("id")),
+            Ok(res) => Ok(res.rows_affected()),
+            Err(err) => Err(Self::idempotency_err(err)),
+        }
+    }
+
+    fn idempotency_err(err: sqlx::Error) -> AppError {
+        //
+        log::error!("Failed to handle the idempotency key: {err}");
+        AppError::InternalErr
+    }
+
+    /// Same as `save`, but an already existing user (with the same email or username, or with
+    /// a too similar username, having the same skeleton) is reported as `SaveOutcome::Existing`
+    /// (with its id), and not as an error. <br/>
+    /// This is useful for imports, where the existing users are skipped.
+    pub async fn save_or_get(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+    ) -> Result<SaveOutcome, AppError> {
+        //
+        let user = reg.user();
+        match self.save(reg, pwd, None).await {
+            Ok(saved) => Ok(SaveOutcome::Created(UserId::from_trusted(saved.id))),
+            // A `Conflict` is a unique violation of the username skeleton.
+            Err(err @ (AppError::AlreadyExists(_) | AppError::Conflict(_))) => {
+                // The (soft) deleted accounts are considered as well, as they keep their email and username.
+                match sqlx::query_as::<_, UserId>(
+                    "SELECT id FROM accounts
+                     WHERE lower(email) = lower($1) OR username = $2 OR username_skeleton = $3
+                     ORDER BY id LIMIT 1",
+                )
+                .bind(user.email.as_str())
+                .bind(&user.username)
+                .bind(Self::username_skeleton(&user.username))
+                .fetch_optional(self.dbcp.as_ref())
+                .await
+                {
+                    Ok(Some(id)) => Ok(SaveOutcome::Existing(id)),
+                    // It was removed meanwhile.
+                    Ok(None) => Err(err),
+                    Err(err) => {
+                        log::error!("Failed to get the existing user: {err}");
+                        Err(AppError::InternalErr)
+                    }
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Create the user, or update the existing one with the same (case-insensitive) email, such as
+    /// when provisioning the accounts from an external directory. <br/>
+    /// On update, the username is overwritten, while the password is kept, unless `overwrite_password`
+    /// is set. A (soft) deleted account is not updated, and it is `AppError::Conflict`.
+    /// It returns whether the user was `Created`, or it was an `Existing` one (that got updated).
+    #[tracing::instrument(skip(self, reg, pwd), fields(username = %reg.user().username))]
+    pub async fn upsert_by_email(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        overwrite_password: bool,
+    ) -> Result<SaveOutcome, AppError> {
+        //
+        let user = reg.user();
+        // The conflict target is the (unique) `accounts_lower_email_key` index.
+        // A row that was inserted (and not updated) by the statement has no `xmax`.
+        let query = sqlx::query(
+            "INSERT INTO accounts (email, username, password, salt, pwd_algo, username_skeleton)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT ((lower(email))) DO UPDATE SET
+                 username = EXCLUDED.username,
+                 username_skeleton = EXCLUDED.username_skeleton,
+                 password = CASE WHEN $7 THEN EXCLUDED.password ELSE accounts.password END,
+                 salt = CASE WHEN $7 THEN EXCLUDED.salt ELSE accounts.salt END,
+                 pwd_algo = CASE WHEN $7 THEN EXCLUDED.pwd_algo ELSE accounts.pwd_algo END,
+                 version = accounts.version + 1,
+                 updated_at = now()
+             WHERE accounts.deleted_at IS NULL
+             RETURNING id, (xmax = 0) AS inserted",
+        )
+        .bind(user.email.as_str())
+        .bind(&user.username)
+        .bind(&pwd.hash)
+        .bind(&pwd.salt)
+        .bind(pwd.algo.as_str())
+        .bind(Self::username_skeleton(&user.username))
+        .bind(overwrite_password)
+        .map(|row: PgRow| {
+            let id = UserId::from_trusted(row.get("id"));
+            match row.get::<bool, _>("inserted") {
+                true => SaveOutcome::Created(id),
+                false => SaveOutcome::Existing(id),
+            }
+        })
+        .fetch_optional(self.dbcp.as_ref());
+        match db::timed("users.upsert_by_email", query).await {
+            Ok(Some(outcome)) => Ok(outcome),
+            // No row is returned when the conflicting account is (soft) deleted.
+            Ok(None) => Err(AppError::Conflict(
+                "the account of this email is deleted".into(),
+            )),
             Err(err) => Err(AppError::from((err, AppUseCase::UserRegistration))),
         }
     }
 
+    /// Same as `save`, but as part of the provided transaction (and without an idempotency key).
+    pub async fn save_tx(
+        &self,
+        txn: &mut Transaction<'_, Postgres>,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+    ) -> Result<User, AppError> {
+        //
+        Self::save_query(reg.user(), pwd)
+            .fetch_one(&mut *txn)
+            .await
+            .map_err(|err| AppError::from((err, AppUseCase::UserRegistration)))
+    }
+
+    fn save_query<'q>(
+        user: &'q User,
+        pwd: &'q Password,
+    ) -> QueryAs<'q, Postgres, User, PgArguments> {
+        //
+        sqlx::query_as::<_, User>(
+            "INSERT INTO accounts (email, username, password, salt, pwd_algo, username_skeleton)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING id, email, username, bio, image, created_at, updated_at",
+        )
+        .bind(user.email.as_str())
+        .bind(&user.username)
+        .bind(&pwd.hash)
+        .bind(&pwd.salt)
+        .bind(pwd.algo.as_str())
+        .bind(Self::username_skeleton(&user.username))
+    }
+
+    /// The skeleton of an (already validated) username. See `Username::skeleton`. <br/>
+    /// The existing accounts with an invalid (legacy) username have no skeleton.
+    fn username_skeleton(username: &str) -> Option<String> {
+        Username::try_new(username).ok().map(|u| u.skeleton())
+    }
+
+    /// Set the skeleton of the accounts that don't have one yet (created before it was introduced).
+    /// The ones that would collide with an existing skeleton are left without one (and logged),
+    /// as they need a manual resolution. It returns the number of updated accounts.
+    pub async fn backfill_username_skeletons(&self) -> Result<u64, AppError> {
+        //
+        let rows = sqlx::query(
+            "SELECT id, username FROM accounts WHERE username_skeleton IS NULL ORDER BY id",
+        )
+        .fetch_all(self.dbcp.as_ref())
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get the accounts without a username skeleton: {err}");
+            AppError::InternalErr
+        })?;
+        let mut updated = 0;
+        for row in rows {
+            let id: i64 = row.get("id");
+            let username: String = row.get("username");
+            let skeleton = match Self::username_skeleton(&username) {
+                Some(skeleton) => skeleton,
+                None => continue,
+            };
+            match sqlx::query("UPDATE accounts SET username_skeleton = $1 WHERE id = $2")
+                .bind(skeleton)
+                .bind(id)
+                .execute(self.dbcp.as_ref())
+                .await
+            {
+                Ok(res) => updated += res.rows_affected(),
+                Err(err) => log::warn!("Failed to set the username skeleton of user {id}: {err}"),
+            }
+        }
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip(self, email))]
     pub async fn get_by_email(
         &self,
         email: &String,
         usecase: AppUseCase,
     ) -> Result<UserEntry, AppError> {
         //
-        sqlx::query_as::<_, UserEntry>(
-            "SELECT id, email, username, password, salt, bio, image FROM accounts 
-             WHERE email = $1",
+        let query = db::retry(db::RETRY_MAX_ATTEMPTS, || {
+            sqlx::query_as::<_, UserEntry>(USER_ENTRY_BY_EMAIL)
+                .bind(email)
+                .fetch_one(self.dbcp.as_ref())
+        });
+        db::timed("users.get_by_email", query)
+            .await
+            .map_err(|err| Self::lookup_err(err, usecase))
+    }
+
+    /// Get the entry of the user with the provided email, if any. <br/>
+    /// Unlike `get_by_email`, a missing user is `None` and not an error.
+    pub async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+        //
+        let query = db::retry(db::RETRY_MAX_ATTEMPTS, || {
+            sqlx::query_as::<_, UserEntry>(USER_ENTRY_BY_EMAIL)
+                .bind(email)
+                .fetch_one(self.dbcp.as_ref())
+        });
+        Self::optional(db::timed("users.get_by_email", query).await)
+    }
+
+    /// Get the full entry (including the password) of the user with the provided username. <br/>
+    /// Unlike `get_profile_by_username`, this is meant for authentication flows.
+    pub async fn get_by_username(
+        &self,
+        username: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError> {
+        //
+        let query = sqlx::query_as::<_, UserEntry>(
+            "SELECT id, email, username, password, salt, pwd_algo, bio, image, version, created_at, updated_at
+             FROM accounts WHERE username = $1 AND deleted_at IS NULL",
         )
-        .bind(email)
+        .bind(username)
+        .fetch_one(self.dbcp.as_ref());
+        db::timed("users.get_by_username", query)
+            .await
+            .map_err(|err| Self::lookup_err(err, usecase))
+    }
+
+    /// Check whether the email is already used by an account (case insensitively). <br/>
+    /// The accounts that are (soft) deleted keep their email taken.
+    pub async fn is_email_taken(&self, email: &str) -> Result<bool, AppError> {
+        //
+        match sqlx::query("SELECT EXISTS(SELECT 1 FROM accounts WHERE lower(email) = lower($1))")
+            .bind(email.trim())
+            .map(|row: PgRow| row.get::<bool, _>(0))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+        {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("Failed to check if the email is taken: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Check whether the username, or one that looks like it (see `Username::skeleton`),
+    /// is already used by an account. An invalid username is reported as taken. <br/>
+    /// The accounts that are (soft) deleted keep their username taken.
+    pub async fn is_username_taken(&self, username: &str) -> Result<bool, AppError> {
+        //
+        let username = match Username::try_new(username) {
+            Ok(username) => username,
+            Err(_) => return Ok(true),
+        };
+        // The exact match covers the (legacy) accounts without a skeleton.
+        match sqlx::query(
+            "SELECT EXISTS(SELECT 1 FROM accounts WHERE username_skeleton = $1 OR username = $2)",
+        )
+        .bind(username.skeleton())
+        .bind(username.as_str())
+        .map(|row: PgRow| row.get::<bool, _>(0))
         .fetch_one(self.dbcp.as_ref())
         .await
-        .map_err(|err| AppError::from((err, usecase)))
+        {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("Failed to check if the username is taken: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
     }
 
+    #[tracing::instrument(skip(self, id), fields(id = id.as_value()))]
     pub async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
         //
-        let entry = sqlx::query_as::<_, UserEntry>(
-            "SELECT email, username, password, salt, bio, image FROM accounts 
-             WHERE id = $1",
-        )
-        .bind(id.as_value())
-        .fetch_one(self.dbcp.as_ref())
-        .await;
+        // Fail fast on an id that can never match.
+        let id = UserId::try_new(id.as_value())?;
+        let query = db::retry(db::RETRY_MAX_ATTEMPTS, || {
+            sqlx::query_as::<_, UserEntry>(USER_ENTRY_BY_ID)
+                .bind(id.as_value())
+                .fetch_one(self.dbcp.as_ref())
+        });
+        let entry = db::timed("users.get_by_id", query).await;
         match entry {
             Ok(entry) => Ok(entry),
-            Err(err) => Err(AppError::from((err, usecase))),
+            Err(err) => Err(Self::lookup_err(err, usecase)),
+        }
+    }
+
+    /// Get the entry of the user with the provided id, if any. <br/>
+    /// Unlike `get_by_id`, a missing user is `None` and not an error.
+    pub async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+        //
+        if UserId::try_new(id.as_value()).is_err() {
+            return Ok(None);
+        }
+        let query = db::retry(db::RETRY_MAX_ATTEMPTS, || {
+            sqlx::query_as::<_, UserEntry>(USER_ENTRY_BY_ID)
+                .bind(id.as_value())
+                .fetch_one(self.dbcp.as_ref())
+        });
+        Self::optional(db::timed("users.get_by_id", query).await)
+    }
+
+    /// Check whether the (not deleted) user with the provided id exists, without fetching it.
+    pub async fn exists(&self, id: &UserId) -> Result<bool, AppError> {
+        //
+        let query = sqlx::query(
+            "SELECT EXISTS(SELECT 1 FROM accounts WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(id.as_value())
+        .map(|row: PgRow| row.get::<bool, _>(0))
+        .fetch_one(self.dbcp.as_ref());
+        match db::timed("users.exists", query).await {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("Failed to check if the user exists: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Get the users with the provided ids, using one query. <br/>
+    /// The unknown (or deleted) ids are simply absent from the result.
+    pub async fn get_users_by_ids(&self, ids: &[i64]) -> Result<HashMap<i64, User>, AppError> {
+        //
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let query = sqlx::query_as::<_, User>(
+            "SELECT id, email, username, bio, image, created_at, updated_at
+             FROM accounts WHERE id = ANY($1) AND deleted_at IS NULL",
+        )
+        .bind(ids)
+        .fetch_all(self.dbcp.as_ref());
+        match db::timed("users.get_users_by_ids", query).await {
+            Ok(users) => Ok(users.into_iter().map(|u| (u.id, u)).collect()),
+            Err(err) => {
+                log::error!("Failed to get the users by ids: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Convert the result of looking up a single user, so that no row is `None`.
+    fn optional(res: Result<UserEntry, sqlx::Error>) -> Result<Option<UserEntry>, AppError> {
+        //
+        match res {
+            Ok(entry) => Ok(Some(entry)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(err) => {
+                log::error!("Failed to look up the user: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Convert the error of looking up a user, so that a missing user is reported as such,
+    /// except for the login use case, where it must not be revealed.
+    fn lookup_err(err: sqlx::Error, usecase: AppUseCase) -> AppError {
+        //
+        if matches!(err, sqlx::Error::RowNotFound) && !matches!(usecase, AppUseCase::UserLogin) {
+            return AppError::NotFound("user".into());
         }
+        AppError::from((err, usecase))
     }
 
+    /// Make the current user follow the user with the given username. <br/>
+    /// Following an already followed user is not an error.
     pub async fn follow_user(
         &self,
         curr_user_id: &UserId,
         followed_username: &String,
     ) -> Result<UserProfile, AppError> {
         //
-        let followed_user_id: UserId;
-        match sqlx::query_as::<_, UserId>("SELECT id FROM accounts WHERE username = $1")
-            .bind(followed_username)
-            .fetch_one(self.dbcp.as_ref())
-            .await
+        let followed_user_id = self
+            .get_followable_id(curr_user_id, followed_username)
+            .await?;
+        let mut txn = self.begin().await?;
+        self.follow_tx(&mut txn, curr_user_id, &followed_user_id)
+            .await?;
+        txn.commit().await.map_err(Self::txn_err)?;
+        // The `following` flag is resolved from the database, thus reflecting the actual state.
+        self.get_profile_by_username(
+            Some(curr_user_id),
+            followed_username,
+            AppUseCase::FollowUser,
+        )
+        .await
+    }
+
+    /// Make a user follow another user, as part of the provided transaction. <br/>
+    /// Following an already followed user is not an error. The follow counts
+    /// of both users are updated within the same transaction.
+    pub async fn follow_tx(
+        &self,
+        txn: &mut Transaction<'_, Postgres>,
+        follower: &UserId,
+        followed: &UserId,
+    ) -> Result<(), AppError> {
+        //
+        if follower.as_value() == followed.as_value() {
+            return Err(AppError::InvalidRequest(SELF_FOLLOW_MSG.into()));
+        }
+        match sqlx::query(
+            "INSERT INTO followings (user_id, followed_user_id) VALUES ($1, $2)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(follower.as_value())
+        .bind(followed.as_value())
+        .execute(&mut *txn)
+        .await
         {
-            Ok(id) => followed_user_id = id,
-            Err(err) => match err {
-                sqlx::Error::RowNotFound => {
-                    return Err(AppError::NotFound("followed username was not found".into()))
-                }
-                _ => return Err(AppError::InternalErr),
+            // Only a newly created following changes the counts.
+            Ok(res) if res.rows_affected() > 0 => {
+                Self::adjust_follow_counts_tx(txn, follower, followed, 1).await
+            }
+            Ok(_) => Ok(()),
+            // A unique violation (not expected, given the `ON CONFLICT`) means it is already followed.
+            Err(err) => match AppError::from((err, AppUseCase::FollowUser)) {
+                AppError::Ignorable => Ok(()),
+                err => Err(err),
             },
-        };
-
-        if curr_user_id.as_value() == followed_user_id.as_value() {
-            return Err(AppError::InvalidRequest(
-                "a user cannot follow himself".into(),
-            ));
         }
+    }
 
-        match sqlx::query("INSERT INTO followings VALUES($1, $2)")
-            .bind(curr_user_id.as_value())
-            .bind(followed_user_id.as_value())
-            .execute(self.dbcp.as_ref())
+    /// Make a user unfollow another user, as part of the provided transaction. <br/>
+    /// Unfollowing a user that is not followed is not an error. The follow counts
+    /// of both users are updated within the same transaction.
+    pub async fn unfollow_tx(
+        &self,
+        txn: &mut Transaction<'_, Postgres>,
+        follower: &UserId,
+        followed: &UserId,
+    ) -> Result<(), AppError> {
+        //
+        match sqlx::query("DELETE FROM followings WHERE user_id = $1 AND followed_user_id = $2")
+            .bind(follower.as_value())
+            .bind(followed.as_value())
+            .execute(&mut *txn)
             .await
         {
-            Ok(_) => {
-                self.get_profile_by_username(
-                    curr_user_id,
-                    followed_username,
-                    AppUseCase::FollowUser,
-                )
-                .await
+            Ok(res) if res.rows_affected() > 0 => {
+                Self::adjust_follow_counts_tx(txn, follower, followed, -1).await
             }
+            Ok(_) => Ok(()),
             Err(err) => Err(AppError::from((err, AppUseCase::FollowUser))),
         }
     }
 
+    /// Add the `delta` to the following count of the `follower` and to the follower count of the `followed`.
+    async fn adjust_follow_counts_tx(
+        txn: &mut Transaction<'_, Postgres>,
+        follower: &UserId,
+        followed: &UserId,
+        delta: i64,
+    ) -> Result<(), AppError> {
+        //
+        match sqlx::query(
+            "UPDATE accounts SET
+                 following_count = following_count + CASE WHEN id = $1 THEN $3 ELSE 0 END,
+                 follower_count = follower_count + CASE WHEN id = $2 THEN $3 ELSE 0 END
+             WHERE id IN ($1, $2)",
+        )
+        .bind(follower.as_value())
+        .bind(followed.as_value())
+        .bind(delta)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("Failed to update the follow counts: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Convert the error of beginning or committing a transaction.
+    fn txn_err(err: sqlx::Error) -> AppError {
+        //
+        log::error!("Failed to complete the transaction: {err}");
+        AppError::InternalErr
+    }
+
+    /// Make a user follow all the provided users, using a single query. <br/>
+    /// Self-follows, already existing followings, and unknown (or deleted) users are skipped.
+    /// It returns the number of newly created followings.
+    pub async fn bulk_follow(
+        &self,
+        follower: &UserId,
+        followed: &[UserId],
+    ) -> Result<u64, AppError> {
+        //
+        let followed: Vec<i64> = followed
+            .iter()
+            .map(UserId::as_value)
+            .filter(|id| *id != follower.as_value())
+            .collect();
+        if followed.is_empty() {
+            return Ok(0);
+        }
+        let mut txn = self.begin().await?;
+        // The newly followed ids are returned, for updating their follower counts.
+        let created: Vec<i64> = match sqlx::query(
+            "INSERT INTO followings (user_id, followed_user_id)
+             SELECT DISTINCT $1::bigint, a.id FROM UNNEST($2::bigint[]) AS f(id)
+             JOIN accounts a ON a.id = f.id AND a.deleted_at IS NULL
+             ON CONFLICT DO NOTHING
+             RETURNING followed_user_id",
+        )
+        .bind(follower.as_value())
+        .bind(&followed)
+        .map(|row: PgRow| row.get::<i64, _>("followed_user_id"))
+        .fetch_all(&mut txn)
+        .await
+        {
+            Ok(ids) => ids,
+            Err(err) => return Err(AppError::from((err, AppUseCase::FollowUser))),
+        };
+        if !created.is_empty() {
+            if let Err(err) = sqlx::query(
+                "UPDATE accounts SET
+                     following_count = following_count + CASE WHEN id = $1 THEN $3 ELSE 0 END,
+                     follower_count = follower_count + CASE WHEN id = ANY($2) THEN 1 ELSE 0 END
+                 WHERE id = $1 OR id = ANY($2)",
+            )
+            .bind(follower.as_value())
+            .bind(&created)
+            .bind(created.len() as i64)
+            .execute(&mut txn)
+            .await
+            {
+                log::error!("Failed to update the follow counts: {err}");
+                return Err(AppError::InternalErr);
+            }
+        }
+        txn.commit().await.map_err(Self::txn_err)?;
+        Ok(created.len() as u64)
+    }
+
+    /// Make the current user unfollow the user with the given username. <br/>
+    /// Unfollowing a user that is not followed is not an error.
     pub async fn unfollow_user(
         &self,
         curr_user_id: &UserId,
         followed_username: &String,
     ) -> Result<UserProfile, AppError> {
         //
-        let followed_user_id =
-            sqlx::query_as::<_, UserId>("SELECT id FROM accounts WHERE username = $1")
-                .bind(followed_username)
-                .fetch_one(self.dbcp.as_ref())
-                .await?;
-        match sqlx::query("DELETE FROM followings WHERE user_id = $1 AND followed_user_id = $2")
-            .bind(curr_user_id.as_value())
-            .bind(followed_user_id.as_value())
-            .execute(self.dbcp.as_ref())
-            .await
+        let followed_user_id = self
+            .get_followable_id(curr_user_id, followed_username)
+            .await?;
+        let mut txn = self.begin().await?;
+        self.unfollow_tx(&mut txn, curr_user_id, &followed_user_id)
+            .await?;
+        txn.commit().await.map_err(Self::txn_err)?;
+        self.get_profile_by_username(
+            Some(curr_user_id),
+            followed_username,
+            AppUseCase::FollowUser,
+        )
+        .await
+    }
+
+    /// Get the id of the user (with the given username) that can be (un)followed by the current user.
+    async fn get_followable_id(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserId, AppError> {
+        //
+        let followed_user_id = match sqlx::query_as::<_, UserId>(
+            "SELECT id FROM accounts WHERE username = $1 AND deleted_at IS NULL",
+        )
+        .bind(followed_username)
+        .fetch_one(self.dbcp.as_ref())
+        .await
         {
-            Ok(_) => {
-                self.get_profile_by_username(
-                    curr_user_id,
-                    followed_username,
-                    AppUseCase::FollowUser,
-                )
-                .await
-            }
-            Err(err) => Err(AppError::from((err, AppUseCase::FollowUser))),
+            Ok(id) => id,
+            Err(err) => match err {
+                sqlx::Error::RowNotFound => {
+                    return Err(AppError::NotFound("followed username".into()))
+                }
+                _ => return Err(AppError::InternalErr),
+            },
+        };
+        if curr_user_id.as_value() == followed_user_id.as_value() {
+            return Err(AppError::InvalidRequest(SELF_FOLLOW_MSG.into()));
         }
+        Ok(followed_user_id)
     }
 
+    /// Get the profile of a user by username, including its follower and following counts. <br/>
+    /// The `following` flag means whether the `viewer` (if any) follows this user;
+    /// without a viewer it is `false`. All of them are resolved within the same query.
+    #[tracing::instrument(skip(self, viewer), fields(viewer = viewer.map(UserId::as_value)))]
     pub async fn get_profile_by_username(
         &self,
-        curr_user_id: &UserId,
+        viewer: Option<&UserId>,
         username: &String,
         usecase: AppUseCase,
     ) -> Result<UserProfile, AppError> {
         //
-        let mut user_id = 0_i64;
-        let res = sqlx::query(
-            "SELECT id, bio, image, COUNT(f.user_id) AS following FROM accounts a
-             LEFT OUTER JOIN followings f ON f.followed_user_id = a.id AND f.user_id = $2
-             WHERE a.username = $1
-             GROUP BY a.id",
+        let query = sqlx::query(
+            "SELECT a.id, a.bio, a.image, a.follower_count, a.following_count,
+                    EXISTS(SELECT 1 FROM followings f
+                           WHERE f.user_id = $2 AND f.followed_user_id = a.id) AS following
+             FROM accounts a WHERE a.username = $1 AND a.deleted_at IS NULL",
         )
         .bind(username)
-        .bind(curr_user_id.as_value())
+        .bind(viewer.map(UserId::as_value))
+        .map(|row: PgRow| UserProfile {
+            user_id: row.get("id"),
+            username: username.clone(),
+            bio: row.get("bio"),
+            image: User::normalize_image(row.get("image")),
+            following: row.get("following"),
+            followers_count: Some(row.get("follower_count")),
+            following_count: Some(row.get("following_count")),
+        })
+        .fetch_one(self.dbcp.as_ref());
+        db::timed("users.get_profile_by_username", query)
+            .await
+            .map_err(|err| AppError::from((err, usecase)))
+    }
+
+    /// Check whether the `viewer` user follows the `target` user.
+    pub async fn is_following(&self, viewer: &UserId, target: &UserId) -> Result<bool, AppError> {
+        //
+        let query = sqlx::query(
+            "SELECT EXISTS(SELECT 1 FROM followings WHERE user_id = $1 AND followed_user_id = $2)",
+        )
+        .bind(viewer.as_value())
+        .bind(target.as_value())
+        .map(|row: PgRow| row.get::<bool, _>(0))
+        .fetch_one(self.dbcp.as_ref());
+        let res = db::timed("users.is_following", query)
+            .await
+            .map_err(Self::followings_err)?;
+        Ok(res)
+    }
+
+    /// Get the subset of the `targets` (user ids) that are followed by the `viewer` user,
+    /// using one query. The empty `targets` do not reach the database.
+    pub async fn following_set(
+        &self,
+        viewer: &UserId,
+        targets: &[i64],
+    ) -> Result<HashSet<i64>, AppError> {
+        //
+        if targets.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let query = sqlx::query(
+            "SELECT followed_user_id FROM followings
+             WHERE user_id = $1 AND followed_user_id = ANY($2)",
+        )
+        .bind(viewer.as_value())
+        .bind(targets)
+        .map(|row: PgRow| row.get::<i64, _>("followed_user_id"))
+        .fetch_all(self.dbcp.as_ref());
+        let rows = db::timed("users.following_set", query)
+            .await
+            .map_err(Self::followings_err)?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Convert the error of querying the followings. <br/>
+    /// Unlike the plain `AppError::from`, any failure is reported (not ignored), thus
+    /// the `following` flag is never silently resolved to a wrong value.
+    fn followings_err(err: sqlx::Error) -> AppError {
+        //
+        log::error!("Failed to query the followings: {err}");
+        AppError::InternalErr
+    }
+
+    /// Get the profiles of the provided usernames, using one query for the accounts
+    /// and (if there is a viewer) one query for resolving the `following` flags. <br/>
+    /// The result preserves the order of the input, and the unknown usernames are skipped.
+    pub async fn get_profiles_by_usernames(
+        &self,
+        usernames: &[String],
+        viewer: Option<&UserId>,
+        usecase: AppUseCase,
+    ) -> Result<Vec<UserProfile>, AppError> {
+        //
+        if usernames.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut profiles: HashMap<String, UserProfile> = match sqlx::query(
+            "SELECT id, username, bio, image FROM accounts
+             WHERE username = ANY($1) AND deleted_at IS NULL",
+        )
+        .bind(usernames)
         .map(|row: PgRow| {
-            user_id = row.get("id");
-            UserProfile {
-                user_id,
-                username: username.clone(),
+            let profile = UserProfile {
+                user_id: row.get("id"),
+                username: row.get("username"),
                 bio: row.get("bio"),
-                image: row.get("image"),
-                following: row.get::<i64, _>("following") == 1,
-            }
+                image: User::normalize_image(row.get("image")),
+                following: false,
+                followers_count: None,
+                following_count: None,
+            };
+            (profile.username.clone(), profile)
         })
-        .fetch_one(self.dbcp.as_ref())
-        .await;
-        match res {
-            Ok(result) => Ok(result),
-            Err(err) => Err(AppError::from((err, usecase))),
+        .fetch_all(self.dbcp.as_ref())
+        .await
+        {
+            Ok(rows) => rows.into_iter().collect(),
+            Err(err) => return Err(AppError::from((err, usecase))),
+        };
+
+        if let Some(viewer) = viewer {
+            let ids: Vec<i64> = profiles.values().map(|p| p.user_id).collect();
+            let followed_ids = self.following_set(viewer, &ids).await?;
+            for p in profiles.values_mut() {
+                p.following = followed_ids.contains(&p.user_id);
+            }
         }
+
+        Ok(usernames
+            .iter()
+            .filter_map(|username| profiles.get(username).cloned())
+            .collect())
+    }
+
+    /// Get a page of the profiles whose usernames start with the provided prefix (case-insensitive),
+    /// ordered by username, together with the total number of the matching ones. <br/>
+    /// At most `SEARCH_MAX_LIMIT` profiles are returned per page.
+    pub async fn search_by_username_prefix(
+        &self,
+        prefix: &str,
+        page: &Pagination,
+    ) -> Result<Page<UserProfile>, AppError> {
+        //
+        // The LIKE wildcards in the prefix are escaped, so they are matched literally.
+        let prefix = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let prefix = &prefix;
+        Page::fetch_counted(page, |page| async move {
+            match sqlx::query(
+                "SELECT id, username, bio, image, count(*) OVER () AS total_count FROM accounts
+                 WHERE username ILIKE ($1 || '%') ESCAPE '\\' AND deleted_at IS NULL
+                 ORDER BY username OFFSET $2 LIMIT $3",
+            )
+            .bind(prefix)
+            .bind(page.offset())
+            .bind(page.limit().min(SEARCH_MAX_LIMIT))
+            .map(|row: PgRow| {
+                let profile = UserProfile {
+                    user_id: row.get("id"),
+                    username: row.get("username"),
+                    bio: row.get("bio"),
+                    image: User::normalize_image(row.get("image")),
+                    following: false,
+                    followers_count: None,
+                    following_count: None,
+                };
+                (profile, row.get::<i64, _>("total_count"))
+            })
+            .fetch_all(self.dbcp.as_ref())
+            .await
+            {
+                Ok(rows) => Ok(rows),
+                Err(err) => {
+                    log::error!("Failed to search the users by username prefix: {err}");
+                    Err(AppError::InternalErr)
+                }
+            }
+        })
+        .await
     }
 
-    pub async fn get_profile_by_id(&self, user_id: i64) -> Result<UserProfile, AppError> {
+    /// Get the profile of the user with the provided id, including its follower and following counts. <br/>
+    /// The `following` flag is set only if a `viewer` is provided. Like `get_profile_by_username`,
+    /// all of them are resolved within the same query.
+    pub async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError> {
         //
-        let res = sqlx::query(
-            "SELECT username, bio, image, COUNT(f.user_id) AS following FROM accounts a
-             LEFT OUTER JOIN followings f ON f.followed_user_id = a.id AND f.user_id = $1
-             WHERE a.id = $1
-             GROUP BY a.username, a.bio, a.image",
+        let query = sqlx::query(
+            "SELECT a.username, a.bio, a.image, a.follower_count, a.following_count,
+                    EXISTS(SELECT 1 FROM followings f
+                           WHERE f.user_id = $2 AND f.followed_user_id = a.id) AS following
+             FROM accounts a WHERE a.id = $1 AND a.deleted_at IS NULL",
         )
-        .bind(user_id)
+        .bind(id.as_value())
+        .bind(viewer.map(UserId::as_value))
         .map(|row: PgRow| UserProfile {
-            user_id,
+            user_id: id.as_value(),
             username: row.get("username"),
             bio: row.get("bio"),
-            image: row.get("image"),
-            following: row.get::<i64, _>("following") == 1,
+            image: User::normalize_image(row.get("image")),
+            following: row.get("following"),
+            followers_count: Some(row.get("follower_count")),
+            following_count: Some(row.get("following_count")),
         })
-        .fetch_one(self.dbcp.as_ref())
-        .await;
-        match res {
-            Ok(result) => Ok(result),
-            Err(err) => Err(AppError::from(err)),
-        }
+        .fetch_one(self.dbcp.as_ref());
+        db::timed("users.get_profile_by_id", query)
+            .await
+            .map_err(|err| Self::lookup_err(err, AppUseCase::GetUserProfile))
     }
 
-    async fn _get_followings(&self, user_id: i64) -> Result<Vec<UserId>, AppError> {
+    /// Get the number of users that follow the provided user.
+    pub async fn count_followers(&self, user_id: i64) -> Result<i64, AppError> {
         //
-        let result = sqlx::query("SELECT followed_user_id FROM followings WHERE user_id = $1")
+        sqlx::query("SELECT follower_count FROM accounts WHERE id = $1")
             .bind(user_id)
-            .map(|row: PgRow| UserId::from(row.get::<i64, _>("followed_user_id")))
-            .fetch_all(self.dbcp.as_ref())
-            .await?;
-        Ok(result)
+            .map(|row: PgRow| row.get::<i64, _>(0))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+            .map_err(|err| Self::lookup_err(err, AppUseCase::GetUserProfile))
+    }
+
+    /// Get the number of users that are followed by the provided user.
+    pub async fn count_following(&self, user_id: i64) -> Result<i64, AppError> {
+        //
+        sqlx::query("SELECT following_count FROM accounts WHERE id = $1")
+            .bind(user_id)
+            .map(|row: PgRow| row.get::<i64, _>(0))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+            .map_err(|err| Self::lookup_err(err, AppUseCase::GetUserProfile))
+    }
+
+    /// Populate the followers and following counts of the provided profile.
+    pub async fn set_follow_counts(&self, profile: &mut UserProfile) -> Result<(), AppError> {
+        //
+        // Both (denormalized) counts are read at once.
+        let (followers, following) =
+            sqlx::query("SELECT follower_count, following_count FROM accounts WHERE id = $1")
+                .bind(profile.user_id)
+                .map(|row: PgRow| (row.get::<i64, _>(0), row.get::<i64, _>(1)))
+                .fetch_one(self.dbcp.as_ref())
+                .await
+                .map_err(|err| Self::lookup_err(err, AppUseCase::GetUserProfile))?;
+        profile.followers_count = Some(followers);
+        profile.following_count = Some(following);
+        Ok(())
+    }
+
+    /// Recompute the (denormalized) follower and following counts of all accounts
+    /// from the followings, using a single statement. <br/>
+    /// This is a maintenance operation, for fixing any drift of the counts.
+    pub async fn recompute_follow_counts(&self) -> Result<(), AppError> {
+        //
+        let query = sqlx::query(
+            "UPDATE accounts a SET
+                 follower_count = (SELECT count(*) FROM followings f WHERE f.followed_user_id = a.id),
+                 following_count = (SELECT count(*) FROM followings f WHERE f.user_id = a.id)",
+        )
+        .execute(self.dbcp.as_ref());
+        match db::timed("users.recompute_follow_counts", query).await {
+            Ok(res) => {
+                log::info!(
+                    "Recomputed the follow counts of {} accounts.",
+                    res.rows_affected()
+                );
+                Ok(())
+            }
+            Err(err) => {
+                log::error!("Failed to recompute the follow counts: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Get a page of ids of the users followed by the provided user,
+    /// ordered by id and starting after the `after` id, if provided
+    /// (the page's offset is relative to it). <br/>
+    /// It also returns whether there are more entries after this page.
+    pub async fn get_followings_page(
+        &self,
+        user_id: i64,
+        after: Option<i64>,
+        page: &Pagination,
+    ) -> Result<(Vec<UserId>, bool), AppError> {
+        //
+        let limit = page.limit();
+        // One more entry is fetched, just to know if there are more.
+        let mut result = sqlx::query(
+            "SELECT followed_user_id FROM followings
+             WHERE user_id = $1 AND ($2::BIGINT IS NULL OR followed_user_id > $2)
+             ORDER BY followed_user_id OFFSET $3 LIMIT $4",
+        )
+        .bind(user_id)
+        .bind(after)
+        .bind(page.offset())
+        .bind(limit + 1)
+        .map(|row: PgRow| UserId::from_trusted(row.get::<i64, _>("followed_user_id")))
+        .fetch_all(self.dbcp.as_ref())
+        .await
+        .map_err(Self::followings_err)?;
+        let has_more = result.len() as i64 > limit;
+        result.truncate(limit as usize);
+        Ok((result, has_more))
+    }
+
+    /// Get a page of users, in the provided order. <br/>
+    /// Its SQL is built per order, thus each (of the few) orders has its own cached prepared statement.
+    pub async fn list(
+        &self,
+        page: &Pagination,
+        order: UserSortField,
+    ) -> Result<Page<User>, AppError> {
+        //
+        let sql = &format!(
+            "SELECT id, email, username, bio, image, created_at, updated_at,
+                    count(*) OVER () AS total_count
+             FROM accounts WHERE deleted_at IS NULL
+             ORDER BY {} OFFSET $1 LIMIT $2",
+            order.order_clause()
+        );
+        Page::fetch_counted(page, |page| async move {
+            match sqlx::query(sql)
+                .bind(page.offset())
+                .bind(page.limit())
+                .try_map(|row: PgRow| Ok((User::from_row(&row)?, row.try_get("total_count")?)))
+                .fetch_all(self.dbcp.as_ref())
+                .await
+            {
+                Ok(rows) => Ok(rows),
+                Err(err) => {
+                    log::error!("Failed to list the users: {err}");
+                    Err(AppError::InternalErr)
+                }
+            }
+        })
+        .await
+    }
+
+    /// Get the total number of users.
+    pub async fn count(&self) -> Result<i64, AppError> {
+        //
+        match sqlx::query("SELECT count(*) FROM accounts WHERE deleted_at IS NULL")
+            .map(|row: PgRow| row.get::<i64, _>(0))
+            .fetch_one(self.dbcp.as_ref())
+            .await
+        {
+            Ok(count) => Ok(count),
+            Err(err) => {
+                log::error!("Failed to count the users: {err}");
+                Err(AppError::InternalErr)
+            }
+        }
+    }
+
+    /// Deactivate a user. Its entry is kept, so its email and username remain taken.
+    pub async fn soft_delete(&self, id: &UserId) -> Result<(), AppError> {
+        //
+        match sqlx::query(
+            "UPDATE accounts SET deleted_at = now() WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id.as_value())
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(res) if res.rows_affected() == 0 => Err(AppError::NotFound("user".into())),
+            Ok(_) => Ok(()),
+            Err(err) => Err(AppError::from((err, AppUseCase::UpdateUser))),
+        }
+    }
+
+    /// Permanently delete a user, together with its followings (in both directions) and favorites. <br/>
+    /// The followings are deleted explicitly (and the follow counts of the other users adjusted)
+    /// within one transaction, as their foreign keys have no `ON DELETE CASCADE`. The idempotency keys
+    /// and refresh tokens are removed by their cascading foreign keys. <br/>
+    /// A user that still authors articles or comments is not deleted, and it is a `Conflict`.
+    pub async fn hard_delete(&self, id: &UserId) -> Result<(), AppError> {
+        //
+        let mut txn = self.begin().await?;
+        // The other side of each following loses a follower or a followed user.
+        sqlx::query(
+            "WITH deleted AS (
+                 DELETE FROM followings WHERE user_id = $1 OR followed_user_id = $1
+                 RETURNING user_id, followed_user_id
+             )
+             UPDATE accounts a SET
+                 follower_count = a.follower_count
+                     - (SELECT count(*) FROM deleted d WHERE d.followed_user_id = a.id),
+                 following_count = a.following_count
+                     - (SELECT count(*) FROM deleted d WHERE d.user_id = a.id)
+             WHERE a.id <> $1 AND a.id IN (SELECT user_id FROM deleted
+                                           UNION SELECT followed_user_id FROM deleted)",
+        )
+        .bind(id.as_value())
+        .execute(&mut txn)
+        .await
+        .map_err(Self::txn_err)?;
+        sqlx::query("DELETE FROM favorited_articles WHERE user_id = $1")
+            .bind(id.as_value())
+            .execute(&mut txn)
+            .await
+            .map_err(Self::txn_err)?;
+        match sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(id.as_value())
+            .execute(&mut txn)
+            .await
+        {
+            Ok(res) if res.rows_affected() == 0 => return Err(AppError::NotFound("user".into())),
+            Ok(_) => {}
+            Err(err) => {
+                // A foreign key violation (23503) means that the user still has authored content.
+                let is_referenced = err
+                    .as_database_error()
+                    .and_then(|e| e.code())
+                    .is_some_and(|code| code == "23503");
+                return match is_referenced {
+                    true => Err(AppError::Conflict(
+                        "user still has articles or comments".into(),
+                    )),
+                    false => Err(Self::txn_err(err)),
+                };
+            }
+        }
+        txn.commit().await.map_err(Self::txn_err)
     }
 
+    /// Reactivate a previously deactivated user.
+    pub async fn restore(&self, id: &UserId) -> Result<(), AppError> {
+        //
+        match sqlx::query(
+            "UPDATE accounts SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+        )
+        .bind(id.as_value())
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(res) if res.rows_affected() == 0 => Err(AppError::NotFound("deleted user".into())),
+            Ok(_) => Ok(()),
+            Err(err) => Err(AppError::from((err, AppUseCase::UpdateUser))),
+        }
+    }
+
+    /// Replace the password of a user. The provided password must be already hashed with the salt. <br/>
+    /// Like any other update, it bumps the version. A (soft) deleted user is `AppError::NotFound`.
+    pub async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+        //
+        match sqlx::query(
+            "UPDATE accounts SET password = $1, salt = $2, pwd_algo = $4,
+                 version = version + 1, updated_at = now()
+             WHERE id = $3 AND deleted_at IS NULL",
+        )
+        .bind(&pwd.hash)
+        .bind(&pwd.salt)
+        .bind(pwd.algo.as_str())
+        .bind(id.as_value())
+        .execute(self.dbcp.as_ref())
+        .await
+        {
+            Ok(res) if res.rows_affected() == 0 => Err(AppError::NotFound("user".into())),
+            Ok(_) => Ok(()),
+            Err(err) => Err(AppError::from((err, AppUseCase::UpdateUser))),
+        }
+    }
+
+    #[tracing::instrument(
+        skip(self, actor, id, changes),
+        fields(actor = actor.as_value(), id = id.as_value())
+    )]
     pub async fn update_by_id(
         &self,
+        actor: &UserId,
         id: UserId,
-        email: Option<String>,
-        bio: Option<String>,
-        image: Option<String>,
+        changes: UserUpdate,
     ) -> Result<UserEntry, AppError> {
         //
-        if email.is_none() && bio.is_none() && image.is_none() {
-            return Err(AppError::InvalidRequest(
-                "email, bio, and image is missing from request body".into(),
+        if actor != &id {
+            return Err(AppError::Forbidden(
+                "a user can update only its own account".into(),
             ));
         }
+        changes.validate()?;
+        let UserUpdate {
+            username,
+            bio,
+            image,
+        } = changes;
         match self.get_by_id(&id, AppUseCase::UpdateUser).await {
             Ok(mut entry) => {
-                entry.user.email = email.unwrap_or(entry.user.email);
+                if let Some(username) = username {
+                    entry.user.username = Username::try_new(&username)?.into();
+                }
                 entry.user.bio = bio.unwrap_or(entry.user.bio);
-                entry.user.image = if image.is_some() {
-                    image
-                } else {
-                    entry.user.image
-                };
-                match sqlx::query(
-                    "UPDATE accounts SET email = $1, bio = $2, image = $3 WHERE id = $4",
+                // An empty image clears it (stored as NULL).
+                entry.user.image = User::normalize_image(image.apply(entry.user.image));
+                // The update applies only if no one else has updated the entry in the meantime.
+                // The entry is built from the updated row, thus having the database-canonical values.
+                // The email is not updated here, as its change needs to be confirmed first.
+                match sqlx::query_as::<_, UserEntry>(
+                    "UPDATE accounts SET username = $1, bio = $2, image = $3,
+                     username_skeleton = $6,
+                     version = version + 1, updated_at = now() WHERE id = $4 AND version = $5
+                     RETURNING id, email, username, password, salt, pwd_algo, bio, image, version,
+                               created_at, updated_at",
                 )
-                .bind(&entry.user.email)
+                .bind(&entry.user.username)
                 .bind(&entry.user.bio)
                 .bind(&entry.user.image)
                 .bind(id.as_value())
-                .execute(self.dbcp.as_ref())
+                .bind(entry.version)
+                .bind(Self::username_skeleton(&entry.user.username))
+                .fetch_optional(self.dbcp.as_ref())
                 .await
                 {
-                    Ok(_) => Ok(entry),
+                    Ok(None) => Err(AppError::Conflict("user was concurrently updated".into())),
+                    Ok(Some(updated)) => Ok(updated),
                     Err(err) => Err(AppError::from((err, AppUseCase::UpdateUser))),
                 }
             }
@@ -257,10 +1261,12 @@ impl FromRow<'_, PgRow> for User {
     fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
         Ok(Self {
             id: row.get("id"),
-            email: row.get("email"),
+            email: Email::from_trusted(row.get("email")),
             username: row.get("username"),
             bio: row.get("bio"),
-            image: row.get("image"),
+            image: User::normalize_image(row.get("image")),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
         })
     }
 }
@@ -268,7 +1274,7 @@ impl FromRow<'_, PgRow> for User {
 impl FromRow<'_, PgRow> for UserId {
     //
     fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
-        Ok(UserId::from(row.get::<i64, _>("id")))
+        Ok(UserId::from_trusted(row.get::<i64, _>("id")))
     }
 }
 
@@ -277,14 +1283,26 @@ impl FromRow<'_, PgRow> for UserEntry {
     fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
         Ok(Self {
             user: User {
-                id: row.try_get("id").unwrap_or_default(),
-                email: row.get("email"),
+                id: row.try_get("id")?,
+                email: Email::from_trusted(row.get("email")),
                 username: row.get("username"),
                 bio: row.get("bio"),
-                image: row.try_get("image").unwrap_or_default(),
+                image: User::normalize_image(row.try_get("image")?),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            },
+            password: Password {
+                hash: row.get("password"),
+                salt: row.get("salt"),
+                algo: {
+                    let algo: String = row.try_get("pwd_algo")?;
+                    PasswordAlgo::from_stored(&algo).ok_or_else(|| sqlx::Error::ColumnDecode {
+                        index: "pwd_algo".into(),
+                        source: format!("unknown password algorithm '{algo}'").into(),
+                    })?
+                },
             },
-            password: row.get("password"),
-            salt: row.get("salt"),
+            version: row.try_get("version")?,
         })
     }
 }
@@ -0,0 +1,477 @@
+use crate::{
+    domain::model::{
+        Email, Password, User, UserEntry, UserId, UserProfile, UserUpdate, Username,
+        ValidatedRegister,
+    },
+    repos::UserRepository,
+    AppError, AppUseCase, SELF_FOLLOW_MSG,
+};
+use axum::async_trait;
+use chrono::Utc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+/// An in-memory implementation of `UserRepository`, useful where a database is not available. <br/>
+/// Like the database, it enforces unique emails, usernames, and username skeletons,
+/// it rejects self-follows, and it does not expose the (soft) deleted users.
+/// All its state is behind a single lock, thus the operations are atomic.
+#[derive(Default)]
+pub struct InMemoryUsersRepo {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<i64, UserEntry>,
+    /// The ids of the (soft) deleted users. Like in the database, they keep their email and username.
+    deleted: HashSet<i64>,
+    /// The (follower id, followed id) pairs.
+    followings: HashSet<(i64, i64)>,
+    /// The idempotency keys of `save` (they do not expire) and the ids of the saved users.
+    idempotency_keys: HashMap<String, i64>,
+}
+
+impl State {
+    //
+    /// Get the entry with the provided id, unless it is (soft) deleted.
+    fn active(&self, id: i64) -> Option<&UserEntry> {
+        match self.deleted.contains(&id) {
+            true => None,
+            false => self.entries.get(&id),
+        }
+    }
+
+    /// The entries that are not (soft) deleted.
+    fn actives(&self) -> impl Iterator<Item = &UserEntry> {
+        self.entries
+            .values()
+            .filter(move |e| !self.deleted.contains(&e.user.id))
+    }
+
+    /// Get the profile of the entry, with the `following` flag resolved against the `viewer`.
+    fn profile_of(&self, entry: &UserEntry, viewer: Option<&UserId>) -> UserProfile {
+        //
+        let following = match viewer {
+            Some(viewer) => self
+                .followings
+                .contains(&(viewer.as_value(), entry.user.id)),
+            None => false,
+        };
+        UserProfile::from(entry.clone()).with_following(following)
+    }
+
+    /// Same as `profile_of`, including the follower and following counts.
+    fn profile_with_counts_of(&self, entry: &UserEntry, viewer: Option<&UserId>) -> UserProfile {
+        //
+        let mut profile = self.profile_of(entry, viewer);
+        let count = |of: fn(&(i64, i64)) -> i64| {
+            self.followings
+                .iter()
+                .filter(|pair| of(pair) == entry.user.id)
+                .count() as i64
+        };
+        profile.followers_count = Some(count(|pair| pair.1));
+        profile.following_count = Some(count(|pair| pair.0));
+        profile
+    }
+}
+
+impl InMemoryUsersRepo {
+    //
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deactivate (soft delete) a user, like `UsersRepo::soft_delete`.
+    pub fn soft_delete(&self, id: &UserId) -> Result<(), AppError> {
+        //
+        let mut state = self.state.lock().unwrap();
+        let is_active = state.active(id.as_value()).is_some();
+        match is_active {
+            true => {
+                state.deleted.insert(id.as_value());
+                Ok(())
+            }
+            false => Err(AppError::NotFound("user".into())),
+        }
+    }
+
+    /// Reactivate a previously deactivated user, like `UsersRepo::restore`.
+    pub fn restore(&self, id: &UserId) -> Result<(), AppError> {
+        //
+        match self.state.lock().unwrap().deleted.remove(&id.as_value()) {
+            true => Ok(()),
+            false => Err(AppError::NotFound("deleted user".into())),
+        }
+    }
+
+    /// Check that the unique attributes of the provided values are not already used
+    /// by an entry other than the one with `except_id`. <br/>
+    /// Like the database, a taken username or email is `AlreadyExists`,
+    /// and a taken username skeleton (see `Username::skeleton`) is `Conflict`. <br/>
+    /// Without a `username` (an unchanged one), only the email is checked.
+    fn check_unique(
+        entries: &HashMap<i64, UserEntry>,
+        except_id: i64,
+        email: &Email,
+        username: Option<&Username>,
+    ) -> Result<(), AppError> {
+        //
+        let skeleton = username.map(|u| u.skeleton());
+        let others = entries.values().filter(|e| e.user.id != except_id);
+        for entry in others {
+            if username.is_some_and(|u| entry.user.username == u.as_str()) {
+                return Err(AppError::AlreadyExists("username".into()));
+            }
+            if &entry.user.email == email {
+                return Err(AppError::AlreadyExists("email".into()));
+            }
+            if skeleton.is_some()
+                && Username::try_new(&entry.user.username)
+                    .is_ok_and(|u| Some(u.skeleton()) == skeleton)
+            {
+                return Err(AppError::Conflict(
+                    "username is too similar to an existing one".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add (if `follow`) or remove the following of the user with the given username by the current user.
+    fn set_following(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+        follow: bool,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let mut state = self.state.lock().unwrap();
+        let entry = match state
+            .actives()
+            .find(|e| &e.user.username == followed_username)
+        {
+            Some(entry) => entry.clone(),
+            None => return Err(AppError::NotFound("followed username".into())),
+        };
+        if entry.user.id == curr_user_id.as_value() {
+            return Err(AppError::InvalidRequest(SELF_FOLLOW_MSG.into()));
+        }
+        let pair = (curr_user_id.as_value(), entry.user.id);
+        match follow {
+            true => state.followings.insert(pair),
+            false => state.followings.remove(&pair),
+        };
+        Ok(UserProfile::from(entry).with_following(follow))
+    }
+
+    fn not_found(usecase: AppUseCase) -> AppError {
+        match usecase {
+            AppUseCase::UserLogin => AppError::Unauthorized("wrong credentials".into()),
+            _ => AppError::NotFound("user".into()),
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUsersRepo {
+    //
+    async fn save(
+        &self,
+        reg: &ValidatedRegister,
+        pwd: &Password,
+        idempotency_key: Option<&str>,
+    ) -> Result<User, AppError> {
+        //
+        let user = reg.user();
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = idempotency_key.and_then(|key| state.idempotency_keys.get(key)) {
+            if let Some(entry) = state.active(*id) {
+                return Ok(entry.user.clone());
+            }
+        }
+        let username = Username::try_new(&user.username)?;
+        Self::check_unique(&state.entries, 0, &user.email, Some(&username))?;
+        let id = state.entries.keys().max().unwrap_or(&0) + 1;
+        let now = Utc::now();
+        let entry = UserEntry {
+            user: User {
+                id,
+                email: user.email.clone(),
+                username: user.username.clone(),
+                bio: user.bio.clone(),
+                image: User::normalize_image(user.image.clone()),
+                created_at: now,
+                updated_at: now,
+            },
+            password: pwd.clone(),
+            version: 0,
+        };
+        let saved = entry.user.clone();
+        state.entries.insert(id, entry);
+        if let Some(key) = idempotency_key {
+            state.idempotency_keys.insert(key.to_string(), id);
+        }
+        Ok(saved)
+    }
+
+    async fn get_by_email(
+        &self,
+        email: &String,
+        usecase: AppUseCase,
+    ) -> Result<UserEntry, AppError> {
+        //
+        let email = email.to_lowercase();
+        let state = self.state.lock().unwrap();
+        let found = state.actives().find(|e| e.user.email.as_str() == email);
+        match found {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(Self::not_found(usecase)),
+        }
+    }
+
+    async fn get_by_id(&self, id: &UserId, usecase: AppUseCase) -> Result<UserEntry, AppError> {
+        //
+        let id = UserId::try_new(id.as_value())?;
+        let state = self.state.lock().unwrap();
+        match state.active(id.as_value()) {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(Self::not_found(usecase)),
+        }
+    }
+
+    async fn get_by_email_opt(&self, email: &String) -> Result<Option<UserEntry>, AppError> {
+        //
+        let email = email.to_lowercase();
+        let state = self.state.lock().unwrap();
+        let found = state.actives().find(|e| e.user.email.as_str() == email);
+        Ok(found.cloned())
+    }
+
+    async fn get_by_id_opt(&self, id: &UserId) -> Result<Option<UserEntry>, AppError> {
+        //
+        let state = self.state.lock().unwrap();
+        Ok(state.active(id.as_value()).cloned())
+    }
+
+    async fn get_profile_by_username(
+        &self,
+        viewer: Option<&UserId>,
+        username: &String,
+        _usecase: AppUseCase,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let state = self.state.lock().unwrap();
+        let found = state.actives().find(|e| &e.user.username == username);
+        match found {
+            Some(entry) => Ok(state.profile_with_counts_of(entry, viewer)),
+            None => Err(AppError::NotFound("profile".into())),
+        }
+    }
+
+    async fn get_profile_by_id(
+        &self,
+        id: &UserId,
+        viewer: Option<&UserId>,
+    ) -> Result<UserProfile, AppError> {
+        //
+        let state = self.state.lock().unwrap();
+        match state.active(id.as_value()) {
+            Some(entry) => Ok(state.profile_with_counts_of(entry, viewer)),
+            None => Err(AppError::NotFound("user".into())),
+        }
+    }
+
+    async fn follow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        self.set_following(curr_user_id, followed_username, true)
+    }
+
+    async fn unfollow_user(
+        &self,
+        curr_user_id: &UserId,
+        followed_username: &String,
+    ) -> Result<UserProfile, AppError> {
+        self.set_following(curr_user_id, followed_username, false)
+    }
+
+    async fn update_password(&self, id: &UserId, pwd: &Password) -> Result<(), AppError> {
+        //
+        let mut state = self.state.lock().unwrap();
+        if state.deleted.contains(&id.as_value()) {
+            return Err(AppError::NotFound("user".into()));
+        }
+        match state.entries.get_mut(&id.as_value()) {
+            Some(entry) => {
+                entry.password = pwd.clone();
+                entry.version += 1;
+                entry.user.updated_at = Utc::now();
+                Ok(())
+            }
+            None => Err(AppError::NotFound("user".into())),
+        }
+    }
+
+    async fn update_by_id(
+        &self,
+        actor: &UserId,
+        id: UserId,
+        changes: UserUpdate,
+    ) -> Result<UserEntry, AppError> {
+        //
+        if actor != &id {
+            return Err(AppError::Forbidden(
+                "a user can update only its own account".into(),
+            ));
+        }
+        changes.validate()?;
+        let UserUpdate {
+            username,
+            bio,
+            image,
+        } = changes;
+        let username = match username {
+            Some(username) => Some(Username::try_new(&username)?),
+            None => None,
+        };
+        let mut state = self.state.lock().unwrap();
+        let mut entry = match state.active(id.as_value()) {
+            Some(entry) => entry.clone(),
+            None => return Err(AppError::NotFound("user".into())),
+        };
+        // Only a changed username needs the skeleton and uniqueness checks.
+        let username = username.filter(|u| u.as_str() != entry.user.username);
+        if let Some(username) = &username {
+            entry.user.username = username.to_string();
+        }
+        entry.user.bio = bio.unwrap_or(entry.user.bio);
+        // An empty image clears it.
+        entry.user.image = User::normalize_image(image.apply(entry.user.image));
+        Self::check_unique(
+            &state.entries,
+            entry.user.id,
+            &entry.user.email,
+            username.as_ref(),
+        )?;
+        entry.version += 1;
+        entry.user.updated_at = Utc::now();
+        state.entries.insert(id.as_value(), entry.clone());
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::RegisterInput;
+
+    fn reg(email: &str, username: &str) -> ValidatedRegister {
+        RegisterInput {
+            email: email.into(),
+            username: username.into(),
+            password: "test-password".into(),
+        }
+        .validate()
+        .unwrap()
+    }
+
+    async fn save(repo: &InMemoryUsersRepo, email: &str, username: &str) -> UserId {
+        let reg = reg(email, username);
+        let pwd = Password::generate(reg.password()).unwrap();
+        UserId::from_trusted(repo.save(&reg, &pwd, None).await.unwrap().id)
+    }
+
+    #[tokio::test]
+    async fn rejects_a_taken_email() {
+        let repo = InMemoryUsersRepo::new();
+        save(&repo, "ann@example.com", "ann").await;
+        let reg = reg("ANN@example.com", "other");
+        let pwd = Password::generate(reg.password()).unwrap();
+        assert!(matches!(
+            repo.save(&reg, &pwd, None).await,
+            Err(AppError::AlreadyExists(field)) if field == "email"
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_the_email_of_a_deleted_user() {
+        let repo = InMemoryUsersRepo::new();
+        let id = save(&repo, "ann@example.com", "ann").await;
+        repo.soft_delete(&id).unwrap();
+        let reg = reg("ann@example.com", "other");
+        let pwd = Password::generate(reg.password()).unwrap();
+        assert!(matches!(
+            repo.save(&reg, &pwd, None).await,
+            Err(AppError::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn does_not_expose_a_deleted_user() {
+        let repo = InMemoryUsersRepo::new();
+        let id = save(&repo, "ann@example.com", "ann").await;
+        let other = save(&repo, "bob@example.com", "bob").await;
+        repo.soft_delete(&id).unwrap();
+        assert!(repo
+            .get_by_email_opt(&"ann@example.com".into())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repo.get_profile_by_id(&id, None).await.is_err());
+        assert!(matches!(
+            repo.follow_user(&other, &"ann".into()).await,
+            Err(AppError::NotFound(_))
+        ));
+
+        repo.restore(&id).unwrap();
+        assert!(repo.get_by_id_opt(&id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn gets_the_profile_by_id_with_the_counts() {
+        let repo = InMemoryUsersRepo::new();
+        let ann = save(&repo, "ann@example.com", "ann").await;
+        let bob = save(&repo, "bob@example.com", "bob").await;
+        repo.follow_user(&bob, &"ann".into()).await.unwrap();
+        let profile = repo.get_profile_by_id(&ann, Some(&bob)).await.unwrap();
+        assert_eq!(profile.followers_count, Some(1));
+        assert_eq!(profile.following_count, Some(0));
+        assert!(profile.following);
+    }
+
+    #[tokio::test]
+    async fn an_update_normalizes_the_provided_username() {
+        let repo = InMemoryUsersRepo::new();
+        let id = save(&repo, "ann@example.com", "ann").await;
+        let changes = UserUpdate {
+            username: Some(" anne ".into()),
+            ..Default::default()
+        };
+        let entry = repo.update_by_id(&id, id.clone(), changes).await.unwrap();
+        assert_eq!(entry.user.username, "anne");
+    }
+
+    #[tokio::test]
+    async fn an_update_keeping_the_username_skips_its_checks() {
+        let repo = InMemoryUsersRepo::new();
+        save(&repo, "ann@example.com", "ann").await;
+        let id = save(&repo, "bob@example.com", "bob").await;
+        // A (legacy) username that looks like ann's one, having a Cyrillic 'а' (U+0430).
+        let lookalike = "\u{0430}nn";
+        if let Some(entry) = repo.state.lock().unwrap().entries.get_mut(&id.as_value()) {
+            entry.user.username = lookalike.into();
+        }
+        let changes = UserUpdate {
+            username: Some(lookalike.into()),
+            bio: Some("hi".into()),
+            ..Default::default()
+        };
+        let entry = repo.update_by_id(&id, id.clone(), changes).await.unwrap();
+        assert_eq!(entry.user.bio, "hi");
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
     domain::model::UserId,
-    web_api::{extractors::InputJson, respond_bad_request, respond_internal_server_error},
+    web_api::{extractors::InputJson, respond_internal_server_error, respond_invalid_request},
     AppError, AppState,
 };
 use axum::{extract::State, http::StatusCode, Json};
+use common_model::ArticleEnvelope;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
@@ -40,12 +41,12 @@ pub async fn create_article(
     {
         Ok(article) => (
             StatusCode::OK,
-            Json(json!({
-                "article": article,
+            Json(json!(ArticleEnvelope {
+                article: article.into()
             })),
         ),
         Err(err) => match err {
-            AppError::AlreadyExists(_) => respond_bad_request(err),
+            AppError::AlreadyExists(_) => respond_invalid_request(err),
             _ => respond_internal_server_error(err),
         },
     }
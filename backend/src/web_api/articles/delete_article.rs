@@ -1,6 +1,6 @@
 use crate::{
     domain::model::UserId,
-    web_api::{respond_bad_request, respond_internal_server_error, respond_unauthorized},
+    web_api::{respond_internal_server_error, respond_invalid_request, respond_unauthorized},
     AppError, AppState,
 };
 use axum::{
@@ -20,7 +20,7 @@ pub async fn delete_article(
         Ok(_) => (StatusCode::NO_CONTENT, Json(Value::default())),
         Err(err) => match err {
             AppError::Unauthorized(_) => respond_unauthorized(err),
-            AppError::InvalidRequest(_) => respond_bad_request(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
             _ => respond_internal_server_error(err),
         },
     }
@@ -0,0 +1,67 @@
+use crate::{
+    domain::model::UserId,
+    web_api::{
+        respond_internal_server_error, respond_invalid_request, respond_not_found,
+        respond_unauthorized,
+    },
+    AppError, AppState,
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use common_model::ArticleEnvelope;
+use serde_json::{json, Value};
+
+pub async fn favorite_article(
+    State(state): State<AppState>,
+    curr_user_id: UserId,
+    Path(slug): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    //
+    match state
+        .articles_mgr
+        .favorite_article(&curr_user_id, slug)
+        .await
+    {
+        Ok(article) => (
+            StatusCode::OK,
+            Json(json!(ArticleEnvelope {
+                article: article.into()
+            })),
+        ),
+        Err(err) => match err {
+            AppError::NotFound(_) => respond_not_found(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
+            AppError::Unauthorized(_) => respond_unauthorized(err),
+            _ => respond_internal_server_error(err),
+        },
+    }
+}
+
+pub async fn unfavorite_article(
+    State(state): State<AppState>,
+    curr_user_id: UserId,
+    Path(slug): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    //
+    match state
+        .articles_mgr
+        .unfavorite_article(&curr_user_id, slug)
+        .await
+    {
+        Ok(article) => (
+            StatusCode::OK,
+            Json(json!(ArticleEnvelope {
+                article: article.into()
+            })),
+        ),
+        Err(err) => match err {
+            AppError::NotFound(_) => respond_not_found(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
+            AppError::Unauthorized(_) => respond_unauthorized(err),
+            _ => respond_internal_server_error(err),
+        },
+    }
+}
@@ -1,17 +1,38 @@
-use crate::{web_api::respond_internal_server_error, AppState};
-use axum::{extract::State, http::StatusCode, Json};
+use crate::{
+    domain::model::{ArticleFilter, UserId},
+    web_api::{respond_internal_server_error, respond_invalid_request},
+    AppError, AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use common_model::ArticlesEnvelope;
 use serde_json::{json, Value};
 
-pub async fn get_articles(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+pub async fn get_articles(
+    State(state): State<AppState>,
+    curr_user_id: Option<UserId>,
+    Query(filter): Query<ArticleFilter>,
+) -> (StatusCode, Json<Value>) {
     //
-    match state.articles_mgr.get_articles().await {
-        Ok(articles) => (
+    match state
+        .articles_mgr
+        .list_global(curr_user_id.as_ref(), &filter)
+        .await
+    {
+        Ok(page) => (
             StatusCode::OK,
-            Json(json!({
-                "articles": articles,
-                "articles_count": articles.len()
+            Json(json!(ArticlesEnvelope {
+                articles: page.items.into_iter().map(Into::into).collect(),
+                articles_count: page.total,
+                has_more: page.has_more,
             })),
         ),
-        Err(err) => respond_internal_server_error(err),
+        Err(err) => match err {
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
+            _ => respond_internal_server_error(err),
+        },
     }
 }
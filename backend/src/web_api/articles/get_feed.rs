@@ -0,0 +1,42 @@
+use crate::{
+    domain::{model::UserId, Pagination},
+    web_api::{respond_internal_server_error, respond_invalid_request},
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use common_model::ArticlesEnvelope;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Deserialize)]
+pub struct FeedParams {
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+pub async fn get_feed(
+    State(state): State<AppState>,
+    curr_user_id: UserId,
+    Query(params): Query<FeedParams>,
+) -> (StatusCode, Json<Value>) {
+    //
+    let page = match Pagination::new(params.offset, params.limit) {
+        Ok(page) => page,
+        Err(err) => return respond_invalid_request(err),
+    };
+    match state.articles_mgr.feed(&curr_user_id, &page).await {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(json!(ArticlesEnvelope {
+                articles: page.items.into_iter().map(Into::into).collect(),
+                articles_count: page.total,
+                has_more: page.has_more,
+            })),
+        ),
+        Err(err) => respond_internal_server_error(err),
+    }
+}
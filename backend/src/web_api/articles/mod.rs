@@ -1,6 +1,12 @@
 mod create_article;
 pub use create_article::*;
 
+mod favorite_article;
+pub use favorite_article::*;
+
+mod get_feed;
+pub use get_feed::*;
+
 mod get_articles;
 pub use get_articles::*;
 
@@ -1,7 +1,8 @@
 use crate::{
     domain::{logic::UpdateArticleInput, model::UserId},
     web_api::{
-        respond_bad_request, respond_internal_server_error, respond_not_found, respond_unauthorized,
+        respond_internal_server_error, respond_invalid_request, respond_not_found,
+        respond_unauthorized,
     },
     AppError, AppState,
 };
@@ -10,6 +11,7 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use common_model::ArticleEnvelope;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
@@ -30,12 +32,17 @@ pub async fn update_article(
         .update_article(curr_user_id, slug, input.article)
         .await
     {
-        Ok(article) => (StatusCode::OK, Json(json!({ "article": article }))),
+        Ok(article) => (
+            StatusCode::OK,
+            Json(json!(ArticleEnvelope {
+                article: article.into()
+            })),
+        ),
         Err(err) => {
             log::error!("Failed to update article: {}", err);
             match err {
                 AppError::Unauthorized(_) => respond_unauthorized(err),
-                AppError::InvalidRequest(_) => respond_bad_request(err),
+                AppError::InvalidRequest(_) => respond_invalid_request(err),
                 AppError::NotFound(_) => respond_not_found(err),
                 _ => respond_internal_server_error(err),
             }
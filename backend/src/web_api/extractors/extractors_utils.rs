@@ -1,14 +1,17 @@
-use crate::{
-    web_api::token::{verify_jwt, Claims, Token},
-    AppError,
+use crate::{auth::verify_token, domain::model::UserId, AppError};
+use axum::{
+    extract::FromRequestParts,
+    headers::{authorization::Credentials, Authorization},
+    http::{request::Parts, HeaderValue},
+    TypedHeader,
 };
-use axum::{extract::FromRequestParts, headers::Authorization, http::request::Parts, TypedHeader};
 
-/// It extracts the token - if it exists - from the Authorization (HTTP request) header having the value of "Token <token>".
-pub async fn validate_token_extract_claims<S: Send + Sync>(
+/// It extracts the token - if it exists - from the Authorization (HTTP request) header having the value of "Token <token>",
+/// and verifies it, returning the id of its user.
+pub async fn validate_token_extract_user_id<S: Send + Sync>(
     parts: &mut Parts,
     state: &S,
-) -> Result<Claims, AppError> {
+) -> Result<UserId, AppError> {
     //
     let token = TypedHeader::<Authorization<Token>>::from_request_parts(parts, state)
         .await
@@ -17,11 +20,42 @@ pub async fn validate_token_extract_claims<S: Send + Sync>(
             AppError::Unauthorized(err.to_string())
         })?;
 
-    match verify_jwt(token.0 .0.token()) {
-        Ok(claims) => Ok(claims),
+    match verify_token(token.0 .0.token()) {
+        Ok(user_id) => Ok(user_id),
         Err(err) => {
-            log::debug!("Failed to verify jwt: {err}");
+            log::debug!("Failed to verify the token: {err}");
             Err(err)
         }
     }
 }
+
+// Support for `Authorization: Token <jwt>` as per "Authentication Header" spec:
+// https://realworld-docs.netlify.app/docs/specs/backend-specs/endpoints
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token(String);
+
+impl Token {
+    /// Get the inner token value.
+    pub fn token(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Credentials for Token {
+    const SCHEME: &'static str = "Token";
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        debug_assert!(
+            value.as_bytes().starts_with(b"Token "),
+            "HeaderValue to decode should start with \"Token ..\", received = {:?}",
+            value,
+        );
+        let token = &value.to_str().unwrap()["Token ".len()..];
+        Some(Self(token.to_string()))
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_str(self.0.as_str()).unwrap()
+    }
+}
@@ -1,5 +1,3 @@
-pub mod claims_extractor;
-
 mod extractors_utils;
 pub use extractors_utils::*;
 
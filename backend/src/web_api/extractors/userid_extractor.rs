@@ -1,4 +1,4 @@
-use super::validate_token_extract_claims;
+use super::validate_token_extract_user_id;
 use crate::{domain::model::UserId, AppError};
 use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
 
@@ -10,7 +10,6 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let claims = validate_token_extract_claims(parts, state).await?;
-        Ok(claims.sub.into())
+        validate_token_extract_user_id(parts, state).await
     }
 }
@@ -13,5 +13,3 @@ pub use responses::*;
 
 mod users;
 pub use users::*;
-
-mod token;
@@ -1,77 +1,217 @@
-use crate::AppError;
+use crate::{AppError, AppUseCase};
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::{json, Value};
+use std::collections::HashMap;
 
 /// Utility function for responding with `500 Internal Server Error` code and an error description.
 pub fn respond_internal_server_error<E>(err: E) -> (StatusCode, Json<Value>)
 where
-    E: std::error::Error,
+    E: std::error::Error + 'static,
 {
     (
         StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({
-            "error": err.to_string()
-        })),
+        error_body(err, "internal"),
     )
 }
 
-/// Utility function for responding with `400 Bad Request` code and an error description.
-pub fn respond_bad_request<E>(err: E) -> (StatusCode, Json<Value>)
+/// Utility function for responding to an invalid request with `422 Unprocessable Entity` code
+/// and an error description.
+pub fn respond_invalid_request<E>(err: E) -> (StatusCode, Json<Value>)
 where
-    E: std::error::Error,
+    E: std::error::Error + 'static,
 {
     (
-        StatusCode::BAD_REQUEST,
-        Json(json!({
-            "error": err.to_string()
-        })),
+        StatusCode::UNPROCESSABLE_ENTITY,
+        error_body(err, "invalid_input"),
     )
 }
 
 /// Utility function for responding with `401 Unauthorized` code and an error description.
 pub fn respond_unauthorized<E>(err: E) -> (StatusCode, Json<Value>)
 where
-    E: std::error::Error,
+    E: std::error::Error + 'static,
 {
-    (
-        StatusCode::UNAUTHORIZED,
-        Json(json!({
-            "error": err.to_string()
-        })),
-    )
+    (StatusCode::UNAUTHORIZED, error_body(err, "unauthorized"))
 }
 
 /// Utility function for responding with `404 Not Found` code and an error description.
 pub fn respond_not_found<E>(err: E) -> (StatusCode, Json<Value>)
 where
-    E: std::error::Error,
+    E: std::error::Error + 'static,
+{
+    (StatusCode::NOT_FOUND, error_body(err, "not_found"))
+}
+
+/// Utility function for responding with `403 Forbidden` code and an error description.
+pub fn respond_forbidden<E>(err: E) -> (StatusCode, Json<Value>)
+where
+    E: std::error::Error + 'static,
 {
+    (StatusCode::FORBIDDEN, error_body(err, "forbidden"))
+}
+
+/// Utility function for responding with `409 Conflict` code and an error description.
+pub fn respond_conflict<E>(err: E) -> (StatusCode, Json<Value>)
+where
+    E: std::error::Error + 'static,
+{
+    (StatusCode::CONFLICT, error_body(err, "conflict"))
+}
+
+/// Utility function for responding with `422 Unprocessable Entity` code and
+/// an errors description, as per the RealWorld spec.
+pub fn respond_unprocessable_entity(field: &str, reason: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({
+            "errors": { field: [reason] }
+        })),
+    )
+}
+
+/// Utility function for responding with `422 Unprocessable Entity` code and
+/// the errors of all the invalid fields, as per the RealWorld spec.
+pub fn respond_validation_failed(
+    errors: HashMap<String, Vec<String>>,
+) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "errors": errors })),
+    )
+}
+
+/// Utility function for responding with `500 Internal Server Error` code and a description
+/// of the failed `usecase` (and not any internal details, that are only logged).
+pub fn respond_failed_usecase(err: AppError, usecase: AppUseCase) -> (StatusCode, Json<Value>) {
+    //
+    log::error!("Failed use case {usecase:?}: {err}");
     (
-        StatusCode::NOT_FOUND,
+        StatusCode::INTERNAL_SERVER_ERROR,
         Json(json!({
-            "error": err.to_string()
+            "error": err.user_message_for(&usecase),
+            "code": err.code(),
         })),
     )
 }
 
+/// The body of an error response. Besides the error description, it includes
+/// the error code (that clients can rely on), or the provided default one if not an `AppError`. <br/>
+/// The description of an `AppError` is its (end-user facing) `user_message`.
+fn error_body<E>(err: E, default_code: &'static str) -> Json<Value>
+where
+    E: std::error::Error + 'static,
+{
+    let (code, msg) = match (&err as &dyn std::error::Error).downcast_ref::<AppError>() {
+        Some(app_err) => (app_err.code(), app_err.user_message()),
+        None => (default_code, err.to_string()),
+    };
+    Json(json!({
+        "error": msg,
+        "code": code,
+    }))
+}
+
+impl AppError {
+    //
+    /// The HTTP status code that corresponds to the error. <br/>
+    /// It matches the `respond_*` functions used by the handlers for the same error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::AlreadyExists(_) | AppError::InvalidRequest(_) | AppError::Validation(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Ignorable | AppError::InternalErr => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 // Implementation of Axum's `IntoResponse` trait, so that
 // an `AppError` can be converted into an HTTP response.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         //
-        let body = Json(json!({
-            "error": self.to_string()
-        }));
-        let response_tuple = match self {
-            AppError::Unauthorized(msg) => {
-                (StatusCode::UNAUTHORIZED, Json(json!({ "error": msg })))
+        let status = self.status_code();
+        let msg = match self {
+            AppError::Validation(errors) => {
+                return respond_validation_failed(errors).into_response()
             }
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, body),
+            // The reason (such as an expired token) is enough.
+            AppError::Unauthorized(ref msg) => msg.clone(),
+            _ => self.user_message(),
+        };
+        (status, Json(json!({ "error": msg, "code": self.code() }))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ValidationErrors;
+
+    #[test]
+    fn maps_each_error_to_its_status_code() {
+        let cases = [
+            (AppError::AlreadyExists("email".into()), 422),
+            (AppError::Validation(HashMap::new()), 422),
+            (AppError::InvalidRequest("bad".into()), 422),
+            (AppError::Conflict("stale".into()), 409),
+            (AppError::Forbidden("not yours".into()), 403),
+            (AppError::NotFound("user".into()), 404),
+            (AppError::Unauthorized("no token".into()), 401),
+            (AppError::Unavailable("database".into()), 503),
+            (AppError::Ignorable, 500),
+            (AppError::InternalErr, 500),
+        ];
+        for (err, status) in cases {
+            assert_eq!(err.status_code().as_u16(), status, "for {err:?}");
+        }
+    }
+
+    #[test]
+    fn responds_with_the_same_status_as_the_handlers() {
+        let status = AppError::InvalidRequest("bad".into()).status_code();
+        assert_eq!(
+            respond_invalid_request(AppError::InvalidRequest("bad".into())).0,
+            status
+        );
+        assert_eq!(
+            AppError::InvalidRequest("bad".into())
+                .into_response()
+                .status(),
+            status
+        );
+    }
+
+    #[test]
+    fn responds_with_the_errors_of_all_the_invalid_fields() {
+        let mut errors = ValidationErrors::new();
+        errors
+            .add("email", "is invalid")
+            .add("password", "can't be blank")
+            .add("password", "is too short");
+        let errors = match errors.into_result() {
+            Err(AppError::Validation(errors)) => errors,
+            _ => panic!("expected a validation error"),
         };
-        response_tuple.into_response()
+        let (status, Json(body)) = respond_validation_failed(errors);
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body,
+            json!({
+                "errors": {
+                    "email": ["is invalid"],
+                    "password": ["can't be blank", "is too short"]
+                }
+            })
+        );
     }
 }
@@ -0,0 +1,40 @@
+use crate::{
+    web_api::{
+        extractors::InputJson, get_current_user, respond_internal_server_error,
+        respond_invalid_request, respond_not_found, respond_unprocessable_entity,
+    },
+    AppError, AppState,
+};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmEmailChangeInputDTO {
+    pub token: String,
+}
+
+/// Confirm the email change requested by `update_current_user`, responding with the updated user. <br/>
+/// As the token is sent to the new email, it is enough for the confirmation (no auth is required).
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    InputJson(input): InputJson<ConfirmEmailChangeInputDTO>,
+) -> (StatusCode, Json<Value>) {
+    //
+    match state
+        .repos
+        .email_changes
+        .confirm_email_change(&input.token)
+        .await
+    {
+        Ok(user_id) => get_current_user(State(state), user_id).await,
+        Err(err) => match err {
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
+            AppError::NotFound(_) => respond_not_found(err),
+            AppError::AlreadyExists(field) => {
+                respond_unprocessable_entity(&field, "has already been taken")
+            }
+            _ => respond_internal_server_error(err),
+        },
+    }
+}
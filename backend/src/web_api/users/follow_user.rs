@@ -1,8 +1,9 @@
 use crate::{
     domain::model::UserId,
+    repos::UserRepository,
     web_api::{
-        get_user_profile, respond_bad_request, respond_internal_server_error, respond_not_found,
-        respond_unauthorized, token::Claims,
+        get_user_profile, respond_internal_server_error, respond_invalid_request,
+        respond_not_found, respond_unauthorized,
     },
     AppError, AppState,
 };
@@ -11,6 +12,7 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use common_model::ProfileEnvelope;
 use serde_json::{json, Value};
 
 pub async fn follow_user(
@@ -19,14 +21,23 @@ pub async fn follow_user(
     curr_user_id: UserId,
 ) -> (StatusCode, Json<Value>) {
     //
-    match state.user_repo.follow_user(&curr_user_id, &username).await {
-        Ok(profile) => (StatusCode::OK, Json(json!({ "profile": profile }))),
+    match state
+        .user_repository
+        .follow_user(&curr_user_id, &username)
+        .await
+    {
+        Ok(profile) => (
+            StatusCode::OK,
+            Json(json!(ProfileEnvelope {
+                profile: profile.into()
+            })),
+        ),
         Err(err) => match err {
             AppError::Ignorable => {
-                get_user_profile(State(state), curr_user_id, Path(username)).await
+                get_user_profile(State(state), Some(curr_user_id), Path(username)).await
             }
             AppError::NotFound(_) => respond_not_found(err),
-            AppError::InvalidRequest(_) => respond_bad_request(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
             AppError::Unauthorized(_) => respond_unauthorized(err),
             _ => respond_internal_server_error(err),
         },
@@ -35,24 +46,28 @@ pub async fn follow_user(
 
 pub async fn unfollow_user(
     Path(username): Path<String>,
-    user_claims: Claims,
+    curr_user_id: UserId,
     State(state): State<AppState>,
 ) -> (StatusCode, Json<Value>) {
     //
-    let curr_user_id = UserId::from(user_claims.sub);
     let profile = state
-        .user_repo
+        .user_repository
         .unfollow_user(&curr_user_id, &username)
         .await;
 
     match profile {
-        Ok(profile) => (StatusCode::OK, Json(json!({ "profile": profile }))),
+        Ok(profile) => (
+            StatusCode::OK,
+            Json(json!(ProfileEnvelope {
+                profile: profile.into()
+            })),
+        ),
         Err(err) => match err {
             AppError::Ignorable => {
-                get_user_profile(State(state), curr_user_id, Path(username)).await
+                get_user_profile(State(state), Some(curr_user_id), Path(username)).await
             }
             AppError::NotFound(_) => respond_not_found(err),
-            AppError::InvalidRequest(_) => respond_bad_request(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
             AppError::Unauthorized(_) => respond_unauthorized(err),
             _ => respond_internal_server_error(err),
         },
@@ -1,7 +1,8 @@
 use super::responses::respond_with_user_dto;
 use crate::{
     domain::model::UserId,
-    web_api::{respond_internal_server_error, respond_unauthorized},
+    repos::UserRepository,
+    web_api::{respond_internal_server_error, respond_not_found, respond_unauthorized},
     AppError, AppState, AppUseCase,
 };
 use axum::{extract::State, http::StatusCode, Json};
@@ -13,19 +14,21 @@ pub async fn get_current_user(
 ) -> (StatusCode, Json<Value>) {
     //
     match state
-        .user_repo
+        .user_repository
         .get_by_id(&curr_user_id, AppUseCase::AnyTokenProtectedOperation)
         .await
     {
         Ok(entry) => respond_with_user_dto(
-            entry.user.email,
+            entry.user.email.into(),
             None,
             entry.user.username,
             entry.user.bio,
             entry.user.image,
+            Some((entry.user.created_at, entry.user.updated_at)),
         ),
         Err(err) => match err {
             AppError::Unauthorized(_) => respond_unauthorized(err),
+            AppError::NotFound(_) => respond_not_found(err),
             _ => respond_internal_server_error(err),
         },
     }
@@ -1,7 +1,9 @@
 use crate::{
     domain::model::UserId,
+    repos::UserRepository,
     web_api::{
-        respond_bad_request, respond_internal_server_error, respond_not_found, respond_unauthorized,
+        respond_internal_server_error, respond_invalid_request, respond_not_found,
+        respond_unauthorized,
     },
     AppError, AppState, AppUseCase,
 };
@@ -10,23 +12,30 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use common_model::ProfileEnvelope;
 use serde_json::{json, Value};
 
 pub async fn get_user_profile(
     State(state): State<AppState>,
-    curr_user_id: UserId,
+    curr_user_id: Option<UserId>,
     Path(username): Path<String>,
 ) -> (StatusCode, Json<Value>) {
     //
+    // The profile includes the follow counts.
     let profile = state
-        .user_repo
-        .get_profile_by_username(&curr_user_id, &username, AppUseCase::GetUserProfile)
+        .user_repository
+        .get_profile_by_username(curr_user_id.as_ref(), &username, AppUseCase::GetUserProfile)
         .await;
     match profile {
-        Ok(profile) => (StatusCode::OK, Json(json!({ "profile": profile }))),
+        Ok(profile) => (
+            StatusCode::OK,
+            Json(json!(ProfileEnvelope {
+                profile: profile.into()
+            })),
+        ),
         Err(err) => match err {
             AppError::NotFound(_) => respond_not_found(err),
-            AppError::InvalidRequest(_) => respond_bad_request(err),
+            AppError::InvalidRequest(_) => respond_invalid_request(err),
             AppError::Unauthorized(_) => respond_unauthorized(err),
             _ => respond_internal_server_error(err),
         },
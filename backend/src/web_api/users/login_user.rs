@@ -1,12 +1,12 @@
 use super::responses::respond_with_user_dto;
 use crate::{
-    web_api::{
-        extractors::InputJson, respond_internal_server_error, respond_unauthorized,
-        token::create_jwt,
-    },
+    auth::{issue_token, ACCESS_TOKEN_TTL_MINUTES},
+    domain::model::UserId,
+    web_api::{extractors::InputJson, respond_internal_server_error, respond_unauthorized},
     AppError, AppState,
 };
 use axum::{extract::State, response::IntoResponse};
+use chrono::Duration;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -31,12 +31,20 @@ pub async fn login_user(
         .login_user(input.user.email, input.user.password)
         .await
     {
-        Ok(user) => match create_jwt(user.id, user.email.clone(), user.username.clone()) {
-            Ok(token) => {
-                respond_with_user_dto(user.email, Some(token), user.username, user.bio, user.image)
-            }
+        Ok(user) => match issue_token(
+            &UserId::from_trusted(user.id),
+            Duration::minutes(ACCESS_TOKEN_TTL_MINUTES),
+        ) {
+            Ok(token) => respond_with_user_dto(
+                user.email.into(),
+                Some(token),
+                user.username,
+                user.bio,
+                user.image,
+                Some((user.created_at, user.updated_at)),
+            ),
             Err(err) => {
-                log::error!("Failed to create jwt: {err}");
+                log::error!("Failed to issue the token: {err}");
                 respond_internal_server_error(err)
             }
         },
@@ -3,6 +3,9 @@
 // re-exporting their entries with a shorter path.
 //
 
+mod confirm_email_change;
+pub use confirm_email_change::*;
+
 mod follow_user;
 pub use follow_user::*;
 
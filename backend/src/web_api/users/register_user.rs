@@ -1,14 +1,21 @@
 use super::responses::respond_with_user_dto;
 use crate::{
-    domain::model::User,
+    auth::{issue_token, ACCESS_TOKEN_TTL_MINUTES},
+    domain::model::{RegisterInput, UserId, ValidatedRegister},
     web_api::{
-        extractors::InputJson, respond_bad_request, respond_internal_server_error,
-        token::create_jwt,
+        extractors::InputJson, respond_conflict, respond_failed_usecase,
+        respond_internal_server_error, respond_invalid_request, respond_unprocessable_entity,
+        respond_validation_failed,
     },
-    AppError::AlreadyExists,
-    AppState,
+    AppError::{self, AlreadyExists},
+    AppState, AppUseCase,
 };
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Duration;
 use serde::Deserialize;
 use serde_json::Value;
 
@@ -17,15 +24,12 @@ pub struct RegisterUserInput {
     pub user: RegisterUserInputUserKey,
 }
 
-impl From<RegisterUserInput> for User {
-    //
+impl From<RegisterUserInput> for RegisterInput {
     fn from(val: RegisterUserInput) -> Self {
         Self {
-            id: 0, // not relevant
             email: val.user.email,
             username: val.user.username,
-            bio: String::new(),
-            image: None,
+            password: val.user.password,
         }
     }
 }
@@ -37,26 +41,57 @@ pub struct RegisterUserInputUserKey {
     pub password: String,
 }
 
+/// The (optional) header of a client provided key, that makes the registration idempotent.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The maximum length of an idempotency key.
+const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+
 pub async fn register_user(
     State(state): State<AppState>,
+    headers: HeaderMap,
     InputJson(input): InputJson<RegisterUserInput>,
 ) -> (StatusCode, Json<Value>) {
     //
-    let pwd = input.user.password.clone();
-    let user: User = input.into();
-    match state.auth_mgr.register_user(&user, pwd).await {
-        Ok(id) => match create_jwt(id, user.email.clone(), user.username.clone()) {
-            Ok(token) => {
-                respond_with_user_dto(user.email, Some(token), user.username, "".to_string(), None)
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(val) => match val.to_str() {
+            Ok(key) if !key.is_empty() && key.len() <= IDEMPOTENCY_KEY_MAX_LEN => Some(key),
+            _ => {
+                return respond_invalid_request(AppError::InvalidRequest(format!(
+                    "{IDEMPOTENCY_KEY_HEADER} header must have up to {IDEMPOTENCY_KEY_MAX_LEN} visible chars"
+                )))
             }
+        },
+        None => None,
+    };
+    // All the invalid fields are reported at once.
+    let reg: ValidatedRegister = match RegisterInput::from(input).validate() {
+        Ok(reg) => reg,
+        Err(AppError::Validation(errors)) => return respond_validation_failed(errors),
+        Err(err) => return respond_invalid_request(err),
+    };
+    match state.auth_mgr.register_user(&reg, idempotency_key).await {
+        Ok(saved) => match issue_token(
+            &UserId::from_trusted(saved.id),
+            Duration::minutes(ACCESS_TOKEN_TTL_MINUTES),
+        ) {
+            Ok(token) => respond_with_user_dto(
+                saved.email.into(),
+                Some(token),
+                saved.username,
+                saved.bio,
+                saved.image,
+                Some((saved.created_at, saved.updated_at)),
+            ),
             Err(err) => {
-                log::error!("Failed to create JWT: {err}");
+                log::error!("Failed to issue the token: {err}");
                 respond_internal_server_error(err)
             }
         },
         Err(err) => match err {
-            AlreadyExists(_) => respond_bad_request(err),
-            _ => respond_internal_server_error(err),
+            AlreadyExists(field) => respond_unprocessable_entity(&field, "has already been taken"),
+            AppError::Conflict(_) => respond_conflict(err),
+            _ => respond_failed_usecase(err, AppUseCase::UserRegistration),
         },
     }
 }
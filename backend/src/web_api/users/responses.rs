@@ -1,14 +1,18 @@
+use crate::domain::model::{to_rfc3339, ImageUrlResolver};
 use axum::{http::StatusCode, Json};
+use chrono::{DateTime, Utc};
 use common_model::UserDTO;
 use serde_json::{json, Value};
 
-/// Utility function for responding with `User` payload in multiple use cases.
+/// Utility function for responding with `User` payload in multiple use cases. <br/>
+/// The `timestamps` (creation and last update time) are included only if provided.
 pub fn respond_with_user_dto(
     email: String,
     token: Option<String>,
     username: String,
     bio: String,
     image: Option<String>,
+    timestamps: Option<(DateTime<Utc>, DateTime<Utc>)>,
 ) -> (StatusCode, Json<Value>) {
     //
     let dto = UserDTO {
@@ -16,7 +20,9 @@ pub fn respond_with_user_dto(
         token,
         username,
         bio,
-        image,
+        image: ImageUrlResolver::global().resolve(image),
+        created_at: timestamps.map(|(created_at, _)| to_rfc3339(&created_at)),
+        updated_at: timestamps.map(|(_, updated_at)| to_rfc3339(&updated_at)),
     };
     (StatusCode::OK, Json(json!({ "user": dto })))
 }
@@ -1,14 +1,16 @@
 use super::responses::respond_with_user_dto;
 use crate::{
-    domain::model::UserId,
+    domain::model::{Email, FieldUpdate, ImageChecker, UserId, UserUpdate},
+    repos::UserRepository,
     web_api::{
-        extractors::InputJson, respond_bad_request, respond_internal_server_error,
-        respond_unauthorized,
+        extractors::InputJson, respond_conflict, respond_forbidden, respond_internal_server_error,
+        respond_invalid_request, respond_not_found, respond_unauthorized,
+        respond_unprocessable_entity,
     },
-    AppError, AppState,
+    AppError, AppState, AppUseCase,
 };
 use axum::{extract::State, http::StatusCode, Json};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 
 #[derive(Debug, Deserialize)]
@@ -19,32 +21,145 @@ pub struct UpdateUserInputDTO {
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserInputDTOUserAttrs {
     pub email: Option<String>,
+    pub username: Option<String>,
     pub bio: Option<String>,
-    pub image: Option<String>,
+    /// A missing image is left unchanged, while a `null` (or empty) one clears it.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub image: Option<Option<String>>,
 }
 
+/// Deserialize a present (possibly `null`) value as `Some`, while `serde(default)` makes a missing one `None`.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl From<UpdateUserInputDTOUserAttrs> for UserUpdate {
+    fn from(val: UpdateUserInputDTOUserAttrs) -> Self {
+        Self {
+            username: val.username,
+            bio: val.bio,
+            image: match val.image {
+                None => FieldUpdate::Keep,
+                Some(Some(image)) if !image.trim().is_empty() => FieldUpdate::Set(image),
+                Some(_) => FieldUpdate::Clear,
+            },
+        }
+    }
+}
+
+/// Update the current user. <br/>
+/// A provided email is not changed right away, but only requested to be changed,
+/// taking effect once it is confirmed (see `confirm_email_change`).
 pub async fn update_current_user(
     State(state): State<AppState>,
     user_id: UserId,
     InputJson(input): InputJson<UpdateUserInputDTO>,
 ) -> (StatusCode, Json<Value>) {
     //
-    match state
-        .user_repo
-        .update_by_id(user_id, input.user.email, input.user.bio, input.user.image)
-        .await
-    {
+    let mut attrs = input.user;
+    let email = attrs.email.take();
+    let changes = UserUpdate::from(attrs);
+    if changes.is_empty() {
+        if email.is_none() {
+            return respond_invalid_request(AppError::InvalidRequest(
+                "email, username, bio, and image is missing from request body".into(),
+            ));
+        }
+    } else if let Err(err) = changes.validate() {
+        return respond_err(err);
+    }
+    if let FieldUpdate::Set(image) = &changes.image {
+        if let Err(err) = ImageChecker::global().check(image).await {
+            return respond_invalid_request(err);
+        }
+    }
+    if let Some(email) = email {
+        let email = match Email::try_new(&email) {
+            Ok(email) => email,
+            Err(err) => return respond_err(err),
+        };
+        let curr = match state
+            .user_repository
+            .get_by_id(&user_id, AppUseCase::UpdateUser)
+            .await
+        {
+            Ok(entry) => entry,
+            Err(err) => return respond_err(err),
+        };
+        // An unchanged email (as the clients usually send all the attributes) is no change.
+        // The token is meant to be sent to the new email (thus it is not logged),
+        // but there is no mailer yet.
+        if curr.user.email != email {
+            if let Err(err) = state
+                .repos
+                .email_changes
+                .request_email_change(&user_id, email.as_str())
+                .await
+            {
+                return respond_err(err);
+            }
+        }
+    }
+    let res = if changes.is_empty() {
+        state
+            .user_repository
+            .get_by_id(&user_id, AppUseCase::UpdateUser)
+            .await
+    } else {
+        state
+            .user_repository
+            .update_by_id(&user_id, user_id.clone(), changes)
+            .await
+    };
+    match res {
         Ok(entry) => respond_with_user_dto(
-            entry.user.email,
+            entry.user.email.into(),
             None,
             entry.user.username,
             entry.user.bio,
             entry.user.image,
+            Some((entry.user.created_at, entry.user.updated_at)),
         ),
-        Err(err) => match err {
-            AppError::InvalidRequest(_) => respond_bad_request(err),
-            AppError::Unauthorized(_) => respond_unauthorized(err),
-            _ => respond_internal_server_error(err),
-        },
+        Err(err) => respond_err(err),
+    }
+}
+
+fn respond_err(err: AppError) -> (StatusCode, Json<Value>) {
+    //
+    match err {
+        AppError::InvalidRequest(_) => respond_invalid_request(err),
+        AppError::Unauthorized(_) => respond_unauthorized(err),
+        AppError::Forbidden(_) => respond_forbidden(err),
+        AppError::NotFound(_) => respond_not_found(err),
+        AppError::Conflict(_) => respond_conflict(err),
+        AppError::AlreadyExists(field) => {
+            respond_unprocessable_entity(&field, "has already been taken")
+        }
+        _ => respond_internal_server_error(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_update(json: &str) -> FieldUpdate<String> {
+        let attrs: UpdateUserInputDTOUserAttrs = serde_json::from_str(json).unwrap();
+        UserUpdate::from(attrs).image
+    }
+
+    #[test]
+    fn distinguishes_a_missing_image_from_a_cleared_one() {
+        assert_eq!(image_update(r#"{"bio": "bio"}"#), FieldUpdate::Keep);
+        assert_eq!(image_update(r#"{"image": null}"#), FieldUpdate::Clear);
+        assert_eq!(image_update(r#"{"image": " "}"#), FieldUpdate::Clear);
+        assert_eq!(
+            image_update(r#"{"image": "https://example.com/ann.png"}"#),
+            FieldUpdate::Set("https://example.com/ann.png".into())
+        );
     }
 }
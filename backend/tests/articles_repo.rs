@@ -0,0 +1,259 @@
+//! The articles repo tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing --test articles_repo -- --ignored`.
+
+mod common;
+
+use backend::{
+    db::testing::TestDb,
+    domain::{
+        logic::ArticlesMgr,
+        model::{Article, ArticleFilter, UserId},
+        Pagination,
+    },
+    repos::{ArticlesRepo, CommentsRepo, TagsRepo},
+    AppError,
+};
+use common::*;
+use std::sync::Arc;
+
+fn articles_mgr(db: &TestDb) -> ArticlesMgr {
+    ArticlesMgr::new(
+        ArticlesRepo::new(Arc::new(db.dbcp.clone())),
+        Arc::new(users_repo(db)),
+    )
+}
+
+/// Create an article of the `author`, having the `title` and `tags`.
+async fn create_article(mgr: &ArticlesMgr, author: &UserId, title: &str, tags: &[&str]) -> Article {
+    mgr.create_article(
+        title.into(),
+        "desc".into(),
+        "body".into(),
+        tags.iter().map(|tag| tag.to_string()).collect(),
+        author.clone(),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_same_title_gets_a_suffixed_slug() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann"]).await;
+    let mgr = articles_mgr(&db);
+    let mut slugs = Vec::new();
+    for _ in 0..2 {
+        let a = create_article(&mgr, &ids[0], "My Post", &[]).await;
+        slugs.push(a.slug);
+    }
+    assert_eq!(slugs, ["my-post", "my-post-2"]);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn counts_the_favorites_of_distinct_users() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob", "cid"]).await;
+    let mgr = articles_mgr(&db);
+    let slug = create_article(&mgr, &ids[0], "Liked", &[]).await.slug;
+    mgr.favorite_article(&ids[1], slug.clone()).await.unwrap();
+    // Favoriting it again is not counted twice.
+    mgr.favorite_article(&ids[1], slug.clone()).await.unwrap();
+    let a = mgr.favorite_article(&ids[2], slug.clone()).await.unwrap();
+    assert_eq!(a.favorites_count, 2);
+    assert!(a.favorited);
+
+    let anonymous = mgr.get_article(&slug, None).await.unwrap().unwrap();
+    assert_eq!(anonymous.favorites_count, 2);
+    assert!(!anonymous.favorited);
+    let author = mgr
+        .get_article(&slug, Some(&ids[0]))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!author.favorited);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn unfavoriting_a_not_favorited_article_is_a_no_op() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob"]).await;
+    let mgr = articles_mgr(&db);
+    let slug = create_article(&mgr, &ids[0], "Unliked", &[]).await.slug;
+    let a = mgr.unfavorite_article(&ids[1], slug).await.unwrap();
+    assert_eq!(a.favorites_count, 0);
+    assert!(!a.favorited);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn ensuring_the_tags_is_idempotent() {
+    let db = TestDb::new().await;
+    let repo = TagsRepo::new(Arc::new(db.dbcp.clone()));
+    let names: Vec<String> = vec!["Rust".into(), " web ".into(), "rust".into(), "".into()];
+    let ids = repo.ensure(&names).await.unwrap();
+    assert_eq!(ids.len(), 2);
+    assert_eq!(repo.ensure(&names).await.unwrap(), ids);
+    let reordered: Vec<String> = vec!["web".into(), "RUST".into()];
+    assert_eq!(repo.ensure(&reordered).await.unwrap(), [ids[1], ids[0]]);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn lists_the_tags_by_popularity() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann"]).await;
+    let mgr = articles_mgr(&db);
+    create_article(&mgr, &ids[0], "One", &["rust", "web"]).await;
+    create_article(&mgr, &ids[0], "Two", &["rust", "db"]).await;
+    create_article(&mgr, &ids[0], "Three", &["rust", "web"]).await;
+
+    let repo = TagsRepo::new(Arc::new(db.dbcp.clone()));
+    assert_eq!(repo.list_popular(10).await.unwrap(), ["rust", "web", "db"]);
+    assert_eq!(repo.list_popular(1).await.unwrap(), ["rust"]);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_feed_excludes_the_not_followed_authors() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    let mgr = articles_mgr(&db);
+    create_article(&mgr, &ids[1], "Followed", &[]).await;
+    create_article(&mgr, &ids[2], "Not Followed", &[]).await;
+    create_article(&mgr, &ids[0], "Own", &[]).await;
+    repo.follow_user(&ids[0], &"bob".into()).await.unwrap();
+
+    let page = Pagination::new(None, None).unwrap();
+    let feed = mgr.feed(&ids[0], &page).await.unwrap();
+    let slugs: Vec<_> = feed.items.iter().map(|a| a.slug.as_str()).collect();
+    assert_eq!(slugs, ["followed"]);
+    assert_eq!(feed.total, 1);
+    assert!(feed.items[0].author.following);
+
+    let global = mgr
+        .list_global(Some(&ids[0]), &ArticleFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(global.total, 3);
+    // Nobody followed, no feed.
+    assert!(mgr.feed(&ids[2], &page).await.unwrap().items.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn lists_the_articles_by_each_and_all_the_filters() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob", "cid"]).await;
+    let mgr = articles_mgr(&db);
+    create_article(&mgr, &ids[0], "A1", &["rust"]).await;
+    let b1 = create_article(&mgr, &ids[1], "B1", &["web"]).await;
+    let a2 = create_article(&mgr, &ids[0], "A2", &["Web"]).await;
+    mgr.favorite_article(&ids[2], b1.slug).await.unwrap();
+    mgr.favorite_article(&ids[2], a2.slug).await.unwrap();
+
+    let slugs = |author: Option<&str>, tag: Option<&str>, favorited_by: Option<&str>| {
+        let filter = ArticleFilter {
+            author: author.map(String::from),
+            tag: tag.map(String::from),
+            favorited_by: favorited_by.map(String::from),
+            ..ArticleFilter::default()
+        };
+        let mgr = &mgr;
+        async move {
+            let page = mgr.list_global(None, &filter).await.unwrap();
+            assert_eq!(page.total, page.items.len() as i64);
+            page.items.into_iter().map(|a| a.slug).collect::<Vec<_>>()
+        }
+    };
+    // Newest first.
+    assert_eq!(slugs(Some("ann"), None, None).await, ["a2", "a1"]);
+    assert_eq!(slugs(None, Some("WEB"), None).await, ["a2", "b1"]);
+    assert_eq!(slugs(None, None, Some("cid")).await, ["a2", "b1"]);
+    assert_eq!(slugs(Some("ann"), Some("web"), Some("cid")).await, ["a2"]);
+    assert!(slugs(Some("bob"), Some("rust"), None).await.is_empty());
+    assert!(slugs(Some("nobody"), None, None).await.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn lists_each_article_with_its_own_tags() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann"]).await;
+    let mgr = articles_mgr(&db);
+    create_article(&mgr, &ids[0], "One", &["rust", "web"]).await;
+    create_article(&mgr, &ids[0], "Two", &[]).await;
+    create_article(&mgr, &ids[0], "Three", &["db"]).await;
+
+    let page = mgr
+        .list_global(None, &ArticleFilter::default())
+        .await
+        .unwrap();
+    let tags: Vec<_> = page
+        .items
+        .into_iter()
+        .map(|a| {
+            let mut tags = a.tag_list;
+            tags.sort();
+            (a.slug, tags)
+        })
+        .collect();
+    assert_eq!(
+        tags,
+        [
+            ("three".to_string(), vec!["db".to_string()]),
+            ("two".into(), vec![]),
+            ("one".into(), vec!["rust".into(), "web".into()]),
+        ]
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn only_the_author_can_delete_a_comment() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob"]).await;
+    let slug = create_article(&articles_mgr(&db), &ids[0], "Commented", &[])
+        .await
+        .slug;
+    let repo = CommentsRepo::new(Arc::new(db.dbcp.clone()));
+    let comment = repo.create(&slug, &ids[1], "bob's").await.unwrap();
+
+    assert!(matches!(
+        repo.delete(comment.id, &ids[0]).await,
+        Err(AppError::Forbidden(_))
+    ));
+    repo.delete(comment.id, &ids[1]).await.unwrap();
+    assert!(matches!(
+        repo.delete(comment.id, &ids[1]).await,
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn lists_the_comments_oldest_first() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob"]).await;
+    let slug = create_article(&articles_mgr(&db), &ids[0], "Commented", &[])
+        .await
+        .slug;
+    let repo = CommentsRepo::new(Arc::new(db.dbcp.clone()));
+    for (author, body) in [(&ids[1], "first"), (&ids[0], "second"), (&ids[1], "third")] {
+        repo.create(&slug, author, body).await.unwrap();
+    }
+
+    let page = Pagination::new(None, None).unwrap();
+    let comments = repo.list_for_article(&slug, None, &page).await.unwrap();
+    let bodies: Vec<_> = comments.items.iter().map(|c| c.body.as_str()).collect();
+    assert_eq!(bodies, ["first", "second", "third"]);
+    assert_eq!(comments.items[0].author.username, "bob");
+    assert!(matches!(
+        repo.list_for_article("missing", None, &page).await,
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,43 @@
+//! The helpers that are shared by the tests (that need a database).
+
+#![allow(dead_code)]
+
+use backend::{
+    db::testing::TestDb,
+    domain::model::{Password, RegisterInput, UserId, ValidatedRegister},
+    repos::UsersRepo,
+};
+use std::sync::Arc;
+
+pub fn reg(email: &str, username: &str) -> ValidatedRegister {
+    reg_with_password(email, username, "test-password")
+}
+
+pub fn reg_with_password(email: &str, username: &str, password: &str) -> ValidatedRegister {
+    RegisterInput {
+        email: email.into(),
+        username: username.into(),
+        password: password.into(),
+    }
+    .validate()
+    .unwrap()
+}
+
+pub fn users_repo(db: &TestDb) -> UsersRepo {
+    UsersRepo::new(Arc::new(db.dbcp.clone()))
+}
+
+pub fn pwd(reg: &ValidatedRegister) -> Password {
+    Password::generate(reg.password()).unwrap()
+}
+
+/// Save a user for each of the usernames (having `<username>@example.com` as email).
+pub async fn save_users(repo: &UsersRepo, usernames: &[&str]) -> Vec<UserId> {
+    let mut ids = Vec::new();
+    for username in usernames {
+        let reg = reg(&format!("{username}@example.com"), username);
+        let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+        ids.push(UserId::from_trusted(saved.id));
+    }
+    ids
+}
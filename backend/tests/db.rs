@@ -0,0 +1,69 @@
+//! The database tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing --test db -- --ignored`.
+
+mod common;
+
+use backend::{
+    db::{run_migrations, testing::TestDb, Tx},
+    domain::model::UserId,
+    AppError,
+};
+use common::*;
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn runs_the_migrations_on_a_fresh_schema_idempotently() {
+    // It runs the migrations on its (fresh) schema.
+    let db = TestDb::new().await;
+    run_migrations(&db.dbcp).await.unwrap();
+
+    let migrations = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .unwrap()
+        .count() as i64;
+    let (applied, failed): (i64, i64) = sqlx::query_as(
+        "SELECT count(*), count(*) FILTER (WHERE NOT success) FROM _sqlx_migrations",
+    )
+    .fetch_one(&db.dbcp)
+    .await
+    .unwrap();
+    assert_eq!((applied, failed), (migrations, 0));
+
+    for table in ["accounts", "followings", "articles", "comments", "tags"] {
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(table)
+            .fetch_one(&db.dbcp)
+            .await
+            .unwrap();
+        assert!(exists, "for {table}");
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_error_after_a_partial_write_leaves_the_db_unchanged() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("ann@example.com", "ann");
+
+    let mut tx = Tx::begin(&db.dbcp).await.unwrap();
+    let saved = repo.save_tx(&mut tx, &reg, &pwd(&reg)).await.unwrap();
+    let id = UserId::from_trusted(saved.id);
+    // The second write fails (as a self follow), thus the transaction is rolled back.
+    let res = repo.follow_tx(&mut tx, &id, &id).await;
+    assert!(matches!(res, Err(AppError::InvalidRequest(_))));
+    tx.rollback().await.unwrap();
+    assert!(!repo.is_email_taken("ann@example.com").await.unwrap());
+
+    // Same when the transaction is just dropped (such as on an early return of the error).
+    {
+        let mut tx = Tx::begin(&db.dbcp).await.unwrap();
+        repo.save_tx(&mut tx, &reg, &pwd(&reg)).await.unwrap();
+    }
+    assert!(!repo.is_email_taken("ann@example.com").await.unwrap());
+
+    let mut tx = Tx::begin(&db.dbcp).await.unwrap();
+    repo.save_tx(&mut tx, &reg, &pwd(&reg)).await.unwrap();
+    tx.commit().await.unwrap();
+    assert!(repo.is_email_taken("ann@example.com").await.unwrap());
+}
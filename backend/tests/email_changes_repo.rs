@@ -0,0 +1,166 @@
+//! The email changes repo tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing --test email_changes_repo -- --ignored`.
+
+mod common;
+
+use axum::{extract::State, http::StatusCode, Json};
+use backend::{
+    db::testing::TestDb,
+    domain::model::UserId,
+    repos::{EmailChangesRepo, UsersRepo},
+    web_api::{
+        confirm_email_change, extractors::InputJson, update_current_user,
+        ConfirmEmailChangeInputDTO, UpdateUserInputDTO, UpdateUserInputDTOUserAttrs,
+    },
+    AppError, AppState, AppUseCase,
+};
+use common::*;
+use sqlx::{postgres::PgRow, Row};
+use std::sync::Arc;
+
+fn email_changes_repo(db: &TestDb) -> EmailChangesRepo {
+    EmailChangesRepo::new(Arc::new(db.dbcp.clone()))
+}
+
+async fn email_of(repo: &UsersRepo, id: &UserId) -> String {
+    let entry = repo.get_by_id(id, AppUseCase::UpdateUser).await.unwrap();
+    entry.user.email.as_str().to_string()
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_confirmed_change_updates_the_email() {
+    let db = TestDb::new().await;
+    let users = users_repo(&db);
+    let ids = save_users(&users, &["ann"]).await;
+    let repo = email_changes_repo(&db);
+
+    let token = repo
+        .request_email_change(&ids[0], "Ann.New@Example.com")
+        .await
+        .unwrap();
+    // The live email is unchanged until the confirmation.
+    assert_eq!(email_of(&users, &ids[0]).await, "ann@example.com");
+
+    assert_eq!(repo.confirm_email_change(&token).await.unwrap(), ids[0]);
+    assert_eq!(email_of(&users, &ids[0]).await, "ann.new@example.com");
+    // The token is single use.
+    assert!(matches!(
+        repo.confirm_email_change(&token).await,
+        Err(AppError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_expired_token_is_rejected() {
+    let db = TestDb::new().await;
+    let users = users_repo(&db);
+    let ids = save_users(&users, &["ann"]).await;
+    let repo = email_changes_repo(&db);
+
+    let token = repo
+        .request_email_change(&ids[0], "ann.new@example.com")
+        .await
+        .unwrap();
+    sqlx::query("UPDATE email_changes SET expires_at = now() - interval '1 hour'")
+        .execute(&db.dbcp)
+        .await
+        .unwrap();
+    assert!(matches!(
+        repo.confirm_email_change(&token).await,
+        Err(AppError::InvalidRequest(msg)) if msg.contains("expired")
+    ));
+    assert_eq!(email_of(&users, &ids[0]).await, "ann@example.com");
+    // And the expired change is removed.
+    assert!(matches!(
+        repo.confirm_email_change(&token).await,
+        Err(AppError::InvalidRequest(msg)) if msg.contains("unknown")
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_email_taken_meanwhile_keeps_the_change_pending() {
+    let db = TestDb::new().await;
+    let users = users_repo(&db);
+    let ids = save_users(&users, &["ann"]).await;
+    let repo = email_changes_repo(&db);
+
+    // An already taken email cannot be requested.
+    assert!(matches!(
+        repo.request_email_change(&ids[0], "ann@example.com").await,
+        Err(AppError::AlreadyExists(field)) if field == "email"
+    ));
+
+    let token = repo
+        .request_email_change(&ids[0], "bob@example.com")
+        .await
+        .unwrap();
+    // bob registers (with that email) before the change is confirmed.
+    save_users(&users, &["bob"]).await;
+    for _ in 0..2 {
+        assert!(matches!(
+            repo.confirm_email_change(&token).await,
+            Err(AppError::AlreadyExists(field)) if field == "email"
+        ));
+    }
+    assert_eq!(email_of(&users, &ids[0]).await, "ann@example.com");
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_update_keeps_the_old_email_until_the_change_is_confirmed() {
+    let db = TestDb::new().await;
+    let users = users_repo(&db);
+    let ids = save_users(&users, &["ann"]).await;
+    let state = AppState::new(db.dbcp.clone());
+
+    let input = UpdateUserInputDTO {
+        user: UpdateUserInputDTOUserAttrs {
+            email: Some("ann.new@example.com".into()),
+            username: None,
+            bio: Some("updated".into()),
+            image: None,
+        },
+    };
+    let (status, Json(body)) =
+        update_current_user(State(state.clone()), ids[0].clone(), InputJson(input)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"]["email"], "ann@example.com");
+    assert_eq!(body["user"]["bio"], "updated");
+    assert_eq!(email_of(&users, &ids[0]).await, "ann@example.com");
+    let pending = sqlx::query("SELECT new_email FROM email_changes WHERE user_id = $1")
+        .bind(ids[0].as_value())
+        .map(|row: PgRow| row.get::<String, _>(0))
+        .fetch_one(&db.dbcp)
+        .await
+        .unwrap();
+    assert_eq!(pending, "ann.new@example.com");
+
+    // The token is not exposed (being meant for the new email only), thus requesting it again.
+    let token = state
+        .repos
+        .email_changes
+        .request_email_change(&ids[0], "ann.new@example.com")
+        .await
+        .unwrap();
+    let input = ConfirmEmailChangeInputDTO { token };
+    let (status, Json(body)) = confirm_email_change(State(state.clone()), InputJson(input)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"]["email"], "ann.new@example.com");
+    assert_eq!(email_of(&users, &ids[0]).await, "ann.new@example.com");
+
+    // An unchanged email is no change (thus not a taken one).
+    let input = UpdateUserInputDTO {
+        user: UpdateUserInputDTOUserAttrs {
+            email: Some("Ann.New@Example.com".into()),
+            username: None,
+            bio: None,
+            image: None,
+        },
+    };
+    let (status, _) = update_current_user(State(state), ids[0].clone(), InputJson(input)).await;
+    assert_eq!(status, StatusCode::OK);
+}
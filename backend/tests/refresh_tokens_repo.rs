@@ -0,0 +1,79 @@
+//! The refresh tokens repo tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing --test refresh_tokens_repo -- --ignored`.
+
+mod common;
+
+use backend::{db::testing::TestDb, repos::RefreshTokensRepo, AppError};
+use common::*;
+use std::sync::Arc;
+
+fn tokens_repo(db: &TestDb) -> RefreshTokensRepo {
+    RefreshTokensRepo::new(Arc::new(db.dbcp.clone()))
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_rotated_token_is_single_use() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann"]).await;
+    let repo = tokens_repo(&db);
+
+    let first = repo.issue_refresh(&ids[0]).await.unwrap();
+    let (user_id, second) = repo.rotate(&first).await.unwrap();
+    assert_eq!(user_id, ids[0]);
+    assert_ne!(second, first);
+    // The new one can be rotated as well, the chain continuing.
+    let (user_id, third) = repo.rotate(&second).await.unwrap();
+    assert_eq!(user_id, ids[0]);
+    assert_ne!(third, second);
+
+    assert!(matches!(
+        repo.rotate("unknown-token").await,
+        Err(AppError::Unauthorized(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_reused_token_revokes_the_whole_chain() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann"]).await;
+    let repo = tokens_repo(&db);
+
+    let first = repo.issue_refresh(&ids[0]).await.unwrap();
+    let (_, second) = repo.rotate(&first).await.unwrap();
+    // Presenting the already rotated token is a reuse.
+    assert!(matches!(
+        repo.rotate(&first).await,
+        Err(AppError::Unauthorized(_))
+    ));
+    // And the (not yet used) latest token of the chain is revoked as well.
+    assert!(matches!(
+        repo.rotate(&second).await,
+        Err(AppError::Unauthorized(_))
+    ));
+    // A chain of the same user that was issued separately is not affected.
+    let other = repo.issue_refresh(&ids[0]).await.unwrap();
+    assert!(repo.rotate(&other).await.is_ok());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn revoke_invalidates_all_the_tokens_of_the_user() {
+    let db = TestDb::new().await;
+    let ids = save_users(&users_repo(&db), &["ann", "bob"]).await;
+    let repo = tokens_repo(&db);
+
+    let ann_1 = repo.issue_refresh(&ids[0]).await.unwrap();
+    let ann_2 = repo.issue_refresh(&ids[0]).await.unwrap();
+    let bob = repo.issue_refresh(&ids[1]).await.unwrap();
+    assert_eq!(repo.revoke(&ids[0]).await.unwrap(), 2);
+    for token in [&ann_1, &ann_2] {
+        assert!(matches!(
+            repo.rotate(token).await,
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+    assert!(repo.rotate(&bob).await.is_ok());
+}
@@ -0,0 +1,41 @@
+//! The seeding tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing,seed --test seed -- --ignored`.
+
+use backend::db::{
+    seed::{seed_follows, seed_users},
+    testing::TestDb,
+};
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn seeds_the_users_and_their_followings() {
+    let db = TestDb::new().await;
+    let ids = seed_users(&db.dbcp, 100).await.unwrap();
+    assert_eq!(ids.len(), 100);
+    let accounts: i64 = sqlx::query_scalar("SELECT count(*) FROM accounts")
+        .fetch_one(&db.dbcp)
+        .await
+        .unwrap();
+    assert_eq!(accounts, 100);
+
+    // About 500 of the 9900 (non-self) pairs.
+    let created = seed_follows(&db.dbcp, &ids, 500.0 / 9900.0).await.unwrap();
+    assert!((300..=700).contains(&created), "created {created}");
+    let (followings, followers, followed): (i64, i64, i64) = sqlx::query_as(
+        "SELECT (SELECT count(*) FROM followings),
+                (SELECT sum(follower_count)::bigint FROM accounts),
+                (SELECT sum(following_count)::bigint FROM accounts)",
+    )
+    .fetch_one(&db.dbcp)
+    .await
+    .unwrap();
+    assert_eq!(followings as u64, created);
+    assert_eq!((followers, followed), (followings, followings));
+
+    // The already existing followings are skipped.
+    let rest = seed_follows(&db.dbcp, &ids, 1.0).await.unwrap();
+    assert_eq!(created + rest, 9900);
+    // And the users can be seeded again.
+    assert_eq!(seed_users(&db.dbcp, 10).await.unwrap().len(), 10);
+}
@@ -0,0 +1,1426 @@
+//! The users repo tests, each one against its own isolated schema (see `db::testing::TestDb`)
+//! of the configured database. <br/>
+//! Run them using `cargo test --features testing --test users_repo -- --ignored`.
+
+mod common;
+
+use backend::{
+    crypto::PasswordAlgo,
+    db::testing::TestDb,
+    domain::{
+        model::{FieldUpdate, Password, UserId, UserUpdate},
+        Pagination,
+    },
+    repos::{SaveOutcome, UserSortField},
+    AppError, AppUseCase, SELF_FOLLOW_MSG,
+};
+use common::*;
+use common_model::UserProfileDTO;
+use std::sync::{Arc, Mutex};
+use tracing::{
+    field::Field,
+    span::{Attributes, Id, Record},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry, Layer,
+};
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn saves_the_same_email_in_one_schema() {
+    let db = TestDb::new().await;
+    let reg = reg("same@example.com", "same");
+    let saved = users_repo(&db).save(&reg, &pwd(&reg), None).await.unwrap();
+    assert_eq!(saved.email.as_str(), "same@example.com");
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn saves_the_same_email_in_another_schema() {
+    let db = TestDb::new().await;
+    let reg = reg("same@example.com", "same");
+    let saved = users_repo(&db).save(&reg, &pwd(&reg), None).await.unwrap();
+    assert_eq!(saved.email.as_str(), "same@example.com");
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_profiles_by_usernames_issues_two_queries() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    repo.follow_user(&ids[0], &"bob".into()).await.unwrap();
+    repo.follow_user(&ids[0], &"cid".into()).await.unwrap();
+
+    let usernames: Vec<String> = vec!["ann".into(), "bob".into(), "cid".into()];
+    let (profiles, queries) = count_queries(repo.get_profiles_by_usernames(
+        &usernames,
+        Some(&ids[0]),
+        AppUseCase::GetUserProfile,
+    ))
+    .await;
+    let following: Vec<(String, bool)> = profiles
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.username, p.following))
+        .collect();
+    assert_eq!(
+        following,
+        vec![
+            ("ann".into(), false),
+            ("bob".into(), true),
+            ("cid".into(), true)
+        ]
+    );
+    assert_eq!(queries, 2);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn profile_without_a_viewer_is_not_followed() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+
+    let anonymous = repo
+        .get_profile_by_username(None, &"ann".into(), AppUseCase::GetUserProfile)
+        .await
+        .unwrap();
+    assert!(!anonymous.following);
+    assert!(repo.is_following(&ids[1], &ids[0]).await.unwrap());
+    assert!(!repo.is_following(&ids[0], &ids[1]).await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn following_twice_is_not_an_error() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    assert!(
+        repo.follow_user(&ids[1], &"ann".into())
+            .await
+            .unwrap()
+            .following
+    );
+    assert!(
+        repo.follow_user(&ids[1], &"ann".into())
+            .await
+            .unwrap()
+            .following
+    );
+    assert_eq!(repo.count_followers(ids[0].as_value()).await.unwrap(), 1);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn unfollowing_a_not_followed_user_is_not_an_error() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    let profile = repo.unfollow_user(&ids[1], &"ann".into()).await.unwrap();
+    assert!(!profile.following);
+    assert_eq!(repo.count_followers(ids[0].as_value()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn following_oneself_is_an_invalid_request() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    assert!(matches!(
+        repo.follow_user(&ids[0], &"ann".into()).await,
+        Err(AppError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_absent_email_is_not_found() {
+    let db = TestDb::new().await;
+    let res = users_repo(&db)
+        .get_by_email(
+            &"absent@example.com".into(),
+            AppUseCase::AnyTokenProtectedOperation,
+        )
+        .await;
+    assert!(matches!(res, Err(AppError::NotFound(_))));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_deleted_user_is_not_found_by_id() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    repo.soft_delete(&ids[0]).await.unwrap();
+    assert!(matches!(
+        repo.get_by_id(&ids[0], AppUseCase::AnyTokenProtectedOperation)
+            .await,
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_stale_update_is_a_conflict() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    // Another (not yet committed) update of the same user, that bumps its version.
+    let mut other = db.dbcp.begin().await.unwrap();
+    sqlx::query("UPDATE accounts SET bio = 'other', version = version + 1 WHERE id = $1")
+        .bind(ids[0].as_value())
+        .execute(&mut other)
+        .await
+        .unwrap();
+
+    // It reads the (committed) current version, and waits for the other update's row lock.
+    let update = tokio::spawn({
+        let (repo, id) = (repo.clone(), ids[0].clone());
+        async move {
+            let changes = UserUpdate {
+                bio: Some("mine".into()),
+                ..UserUpdate::default()
+            };
+            repo.update_by_id(&id, id.clone(), changes).await
+        }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    other.commit().await.unwrap();
+    assert!(matches!(update.await.unwrap(), Err(AppError::Conflict(_))));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn taking_a_taken_username_is_rejected() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    let take = |id: &UserId| {
+        let (repo, id) = (&repo, id.clone());
+        async move {
+            let changes = UserUpdate {
+                username: Some("carol".into()),
+                ..UserUpdate::default()
+            };
+            repo.update_by_id(&id, id.clone(), changes).await
+        }
+    };
+    assert_eq!(take(&ids[0]).await.unwrap().user.username, "carol");
+    // Both the username and its skeleton are taken, either unique key may be the reported one.
+    match take(&ids[1]).await {
+        Err(AppError::AlreadyExists(field)) => assert_eq!(field, "username"),
+        Err(AppError::Conflict(_)) => {}
+        other => panic!(
+            "expected the username to be taken, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_soft_deleted_user_cannot_log_in_until_restored() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let email = "ann@example.com".to_string();
+
+    repo.soft_delete(&ids[0]).await.unwrap();
+    assert!(matches!(
+        repo.get_by_email(&email, AppUseCase::UserLogin).await,
+        Err(AppError::Unauthorized(_))
+    ));
+    // Its username remains taken.
+    assert!(repo.is_username_taken("ann").await.unwrap());
+
+    repo.restore(&ids[0]).await.unwrap();
+    let entry = repo
+        .get_by_email(&email, AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert!(entry.password().verify("test-password"));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_by_id_returns_the_requested_id() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    for id in &ids {
+        let entry = repo
+            .get_by_id(id, AppUseCase::AnyTokenProtectedOperation)
+            .await
+            .unwrap();
+        assert_eq!(entry.user.id, id.as_value());
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn logs_in_with_a_mixed_case_email() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let entry = repo
+        .get_by_email(&"Ann@Example.COM".into(), AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert_eq!(entry.user.id, ids[0].as_value());
+    assert!(entry.password().verify("test-password"));
+
+    let reg = reg("ANN@example.com", "ann2");
+    assert!(matches!(
+        repo.save(&reg, &pwd(&reg), None).await,
+        Err(AppError::AlreadyExists(field)) if field == "email"
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_username_prefix_wildcards_are_matched_literally() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    save_users(&repo, &["ann_b", "annxb", "ann-c"]).await;
+    let page = Pagination::new(None, None).unwrap();
+
+    let found = repo.search_by_username_prefix("ANN", &page).await.unwrap();
+    let usernames: Vec<_> = found.items.iter().map(|p| p.username.as_str()).collect();
+    assert_eq!(usernames, ["ann-c", "ann_b", "annxb"]);
+
+    let found = repo.search_by_username_prefix("ann_", &page).await.unwrap();
+    let usernames: Vec<_> = found.items.iter().map(|p| p.username.as_str()).collect();
+    assert_eq!(usernames, ["ann_b"]);
+
+    for prefix in ["%", "ann%b", "\\"] {
+        let found = repo.search_by_username_prefix(prefix, &page).await.unwrap();
+        assert!(found.items.is_empty(), "for {prefix}");
+        assert_eq!(found.total, 0);
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_failed_follow_rolls_back_the_registration() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("txn@example.com", "txn");
+    let mut txn = repo.begin().await.unwrap();
+    let saved = repo.save_tx(&mut txn, &reg, &pwd(&reg)).await.unwrap();
+    // There is no such user to follow (a foreign key violation).
+    let res = repo
+        .follow_tx(
+            &mut txn,
+            &UserId::from_trusted(saved.id),
+            &UserId::from_trusted(i64::MAX),
+        )
+        .await;
+    assert!(res.is_err());
+    drop(txn);
+
+    assert!(repo
+        .get_by_email_opt(&"txn@example.com".into())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_saved_id_round_trips_through_get_by_id() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("round@example.com", "round");
+    let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+    let entry = repo
+        .get_by_id(
+            &UserId::from_trusted(saved.id),
+            AppUseCase::AnyTokenProtectedOperation,
+        )
+        .await
+        .unwrap();
+    assert_eq!(entry.user.id, saved.id);
+    assert_eq!(entry.user.email, saved.email);
+    assert_eq!(entry.user.username, "round");
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_duplicate_registration_reports_the_taken_field() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    save_users(&repo, &["ann"]).await;
+    for (email, username, taken) in [
+        ("ann@example.com", "other", "email"),
+        ("other@example.com", "ann", "username"),
+    ] {
+        let reg = reg(email, username);
+        match repo.save(&reg, &pwd(&reg), None).await {
+            Err(AppError::AlreadyExists(field)) => assert_eq!(field, taken),
+            other => panic!("expected {taken} to be taken, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn pages_through_the_followings() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid", "dan"]).await;
+    for username in ["bob", "cid", "dan"] {
+        repo.follow_user(&ids[0], &username.into()).await.unwrap();
+    }
+    let page = Pagination::new(None, Some(2)).unwrap();
+
+    let (first, has_more) = repo
+        .get_followings_page(ids[0].as_value(), None, &page)
+        .await
+        .unwrap();
+    assert_eq!(first, ids[1..3]);
+    assert!(has_more);
+
+    let after = first.last().map(UserId::as_value);
+    let (second, has_more) = repo
+        .get_followings_page(ids[0].as_value(), after, &page)
+        .await
+        .unwrap();
+    assert_eq!(second, ids[3..]);
+    assert!(!has_more);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_following_flag_is_resolved_for_a_viewer() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+
+    for (viewer, following) in [(&ids[1], true), (&ids[2], false)] {
+        let profile = repo
+            .get_profile_by_username(Some(viewer), &"ann".into(), AppUseCase::GetUserProfile)
+            .await
+            .unwrap();
+        assert_eq!(profile.following, following);
+        assert_eq!(UserProfileDTO::from(profile).following, Some(following));
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn lists_the_users_in_pages() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["eve", "bob", "dan", "ann", "cid"]).await;
+
+    let mut usernames = Vec::new();
+    for offset in [0, 2, 4] {
+        let page = Pagination::new(Some(offset), Some(2)).unwrap();
+        let users = repo.list(&page, UserSortField::UsernameAsc).await.unwrap();
+        assert_eq!(users.total, 5);
+        usernames.extend(users.items.into_iter().map(|u| u.username));
+    }
+    assert_eq!(usernames, ["ann", "bob", "cid", "dan", "eve"]);
+    // A page past the last one still has the total.
+    let page = Pagination::new(Some(6), Some(2)).unwrap();
+    let past_the_last = repo.list(&page, UserSortField::UsernameAsc).await.unwrap();
+    assert!(past_the_last.items.is_empty());
+    assert_eq!(past_the_last.total, 5);
+
+    let page = Pagination::new(None, Some(2)).unwrap();
+    let newest = repo.list(&page, UserSortField::NewestFirst).await.unwrap();
+    let newest: Vec<_> = newest.items.iter().map(|u| u.id).collect();
+    assert_eq!(newest, [ids[4].as_value(), ids[3].as_value()]);
+    assert_eq!(repo.count().await.unwrap(), 5);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_profile_by_id_is_the_same_as_by_username() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    let changes = UserUpdate {
+        bio: Some("ann's bio".into()),
+        image: FieldUpdate::Set("https://example.com/ann.png".into()),
+        ..UserUpdate::default()
+    };
+    repo.update_by_id(&ids[0], ids[0].clone(), changes)
+        .await
+        .unwrap();
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+
+    for viewer in [None, Some(&ids[1]), Some(&ids[0])] {
+        let by_id = repo.get_profile_by_id(&ids[0], viewer).await.unwrap();
+        let by_username = repo
+            .get_profile_by_username(viewer, &"ann".into(), AppUseCase::GetUserProfile)
+            .await
+            .unwrap();
+        assert_eq!(by_id.user_id, by_username.user_id);
+        assert_eq!(by_id.username, by_username.username);
+        assert_eq!(by_id.bio, by_username.bio);
+        assert_eq!(by_id.image, by_username.image);
+        assert_eq!(by_id.following, by_username.following);
+        assert_eq!(by_id.followers_count, by_username.followers_count);
+        assert_eq!(by_id.following_count, by_username.following_count);
+    }
+    assert!(matches!(
+        repo.get_profile_by_id(&UserId::from_trusted(i64::MAX), None)
+            .await,
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_update_advances_updated_at() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("ann@example.com", "ann");
+    let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+    assert_eq!(saved.updated_at, saved.created_at);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+    let id = UserId::from_trusted(saved.id);
+    let changes = UserUpdate {
+        bio: Some("updated".into()),
+        ..UserUpdate::default()
+    };
+    let updated = repo.update_by_id(&id, id.clone(), changes).await.unwrap();
+    assert!(updated.user.updated_at > saved.updated_at);
+    assert_eq!(updated.user.created_at, saved.created_at);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_by_username_has_the_password_and_salt() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let entry = repo
+        .get_by_username(&"ann".into(), AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert_eq!(entry.user.id, ids[0].as_value());
+    assert!(!entry.password().hash.is_empty());
+    assert!(!entry.password().salt.is_empty());
+    assert!(entry.password().verify("test-password"));
+    assert!(matches!(
+        repo.get_by_username(&"nobody".into(), AppUseCase::UserLogin)
+            .await,
+        Err(AppError::Unauthorized(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_user_cannot_edit_another_one() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    let changes = UserUpdate {
+        bio: Some("edited by ann".into()),
+        ..UserUpdate::default()
+    };
+    assert!(matches!(
+        repo.update_by_id(&ids[0], ids[1].clone(), changes).await,
+        Err(AppError::Forbidden(_))
+    ));
+    let bob = repo
+        .get_by_id(&ids[1], AppUseCase::AnyTokenProtectedOperation)
+        .await
+        .unwrap();
+    assert_eq!(bob.user.bio, "");
+    assert_eq!(bob.version, 0);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn bulk_follow_counts_only_the_new_followings() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid", "dan"]).await;
+    repo.follow_user(&ids[0], &"bob".into()).await.unwrap();
+
+    // A duplicate, a self, an unknown, and twice the same new one.
+    let followed = [
+        ids[1].clone(),
+        ids[0].clone(),
+        UserId::from_trusted(i64::MAX),
+        ids[2].clone(),
+        ids[2].clone(),
+        ids[3].clone(),
+    ];
+    assert_eq!(repo.bulk_follow(&ids[0], &followed).await.unwrap(), 2);
+    assert_eq!(repo.count_following(ids[0].as_value()).await.unwrap(), 3);
+    let ann = repo.get_profile_by_id(&ids[0], None).await.unwrap();
+    assert_eq!(ann.following_count, Some(3));
+    let cid = repo.get_profile_by_id(&ids[2], None).await.unwrap();
+    assert_eq!(cid.followers_count, Some(1));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_empty_image_is_the_same_as_no_image() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let stored_image = || {
+        sqlx::query_scalar::<_, Option<String>>("SELECT image FROM accounts WHERE id = $1")
+            .bind(ids[0].as_value())
+            .fetch_one(&db.dbcp)
+    };
+    let set_image = |image: &str| {
+        let changes = UserUpdate {
+            image: FieldUpdate::Set(image.into()),
+            ..UserUpdate::default()
+        };
+        repo.update_by_id(&ids[0], ids[0].clone(), changes)
+    };
+
+    set_image("https://example.com/ann.png").await.unwrap();
+    let updated = set_image("").await.unwrap();
+    assert_eq!(updated.user.image, None);
+    assert_eq!(stored_image().await.unwrap(), None);
+
+    // An empty image that is stored (such as a legacy one) is read as no image.
+    sqlx::query("UPDATE accounts SET image = '' WHERE id = $1")
+        .bind(ids[0].as_value())
+        .execute(&db.dbcp)
+        .await
+        .unwrap();
+    assert_eq!(stored_image().await.unwrap(), Some(String::new()));
+    let entry = repo
+        .get_by_id(&ids[0], AppUseCase::AnyTokenProtectedOperation)
+        .await
+        .unwrap();
+    assert_eq!(entry.user.image, None);
+    let profile = repo.get_profile_by_id(&ids[0], None).await.unwrap();
+    assert_eq!(profile.image, None);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_by_id_reads_all_the_columns() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("Ann@Example.com", "ann");
+    let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+    let entry = repo
+        .get_by_id(
+            &UserId::from_trusted(saved.id),
+            AppUseCase::AnyTokenProtectedOperation,
+        )
+        .await
+        .unwrap();
+    assert_eq!(entry.user.id, saved.id);
+    assert_eq!(entry.user.email.as_str(), "ann@example.com");
+    assert_eq!(entry.user.username, "ann");
+    assert_eq!(entry.user.bio, "");
+    assert_eq!(entry.user.image, None);
+    assert_eq!(entry.user.created_at, saved.created_at);
+    assert_eq!(entry.version, 0);
+    assert!(entry.password().verify("test-password"));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn checks_the_availability_of_the_email_and_username() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    save_users(&repo, &["ann"]).await;
+    assert!(repo.is_email_taken("ann@example.com").await.unwrap());
+    assert!(repo.is_email_taken(" ANN@example.com ").await.unwrap());
+    assert!(!repo.is_email_taken("bob@example.com").await.unwrap());
+    assert!(repo.is_username_taken("ann").await.unwrap());
+    assert!(!repo.is_username_taken("bob").await.unwrap());
+    // The usernames that look like a taken one are taken as well.
+    assert!(repo.is_username_taken("Ann").await.unwrap());
+    // Having a Cyrillic 'а' (U+0430).
+    assert!(repo.is_username_taken("\u{0430}nn").await.unwrap());
+    // An invalid username is not available.
+    assert!(repo.is_username_taken("-bob").await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_partial_update_keeps_the_other_attributes() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let changes = UserUpdate {
+        bio: Some("ann's bio".into()),
+        image: FieldUpdate::Set("https://example.com/ann.png".into()),
+        ..UserUpdate::default()
+    };
+    repo.update_by_id(&ids[0], ids[0].clone(), changes)
+        .await
+        .unwrap();
+
+    let changes = UserUpdate {
+        username: Some("anna".into()),
+        ..UserUpdate::default()
+    };
+    let updated = repo
+        .update_by_id(&ids[0], ids[0].clone(), changes)
+        .await
+        .unwrap();
+    assert_eq!(updated.user.username, "anna");
+    assert_eq!(updated.user.email.as_str(), "ann@example.com");
+    assert_eq!(updated.user.bio, "ann's bio");
+    assert_eq!(
+        updated.user.image.as_deref(),
+        Some("https://example.com/ann.png")
+    );
+    assert_eq!(updated.version, 2);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_null_bio_cannot_be_stored() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let res = sqlx::query("UPDATE accounts SET bio = NULL WHERE id = $1")
+        .bind(ids[0].as_value())
+        .execute(&db.dbcp)
+        .await;
+    let code = res
+        .unwrap_err()
+        .as_database_error()
+        .and_then(|e| e.code())
+        .map(|code| code.to_string());
+    assert_eq!(code.as_deref(), Some("23502")); // not_null_violation
+
+    // Thus, the bio is always read (an empty one, by default).
+    let profile = repo
+        .get_profile_by_username(None, &"ann".into(), AppUseCase::GetUserProfile)
+        .await
+        .unwrap();
+    assert_eq!(profile.bio, "");
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn following_set_has_the_followed_targets_only() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid", "dan", "eve", "fay"]).await;
+    repo.follow_user(&ids[0], &"cid".into()).await.unwrap();
+    repo.follow_user(&ids[0], &"eve".into()).await.unwrap();
+    // A following of another viewer is not included.
+    repo.follow_user(&ids[1], &"dan".into()).await.unwrap();
+
+    let targets: Vec<i64> = ids[1..].iter().map(UserId::as_value).collect();
+    let followed = repo.following_set(&ids[0], &targets).await.unwrap();
+    assert_eq!(
+        followed,
+        [ids[2].as_value(), ids[4].as_value()].into_iter().collect()
+    );
+    assert!(repo.following_set(&ids[0], &[]).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_opt_lookups_are_none_for_the_absent_rows() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+
+    let by_email = repo.get_by_email_opt(&"ann@example.com".into()).await;
+    assert_eq!(by_email.unwrap().unwrap().user.id, ids[0].as_value());
+    let by_id = repo.get_by_id_opt(&ids[0]).await.unwrap();
+    assert_eq!(by_id.unwrap().user.email.as_str(), "ann@example.com");
+
+    let absent = repo.get_by_email_opt(&"bob@example.com".into()).await;
+    assert!(absent.unwrap().is_none());
+    for id in [i64::MAX, 0] {
+        let absent = repo.get_by_id_opt(&UserId::from_trusted(id)).await;
+        assert!(absent.unwrap().is_none(), "for {id}");
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn upsert_by_email_creates_then_updates_keeping_the_password() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let created = reg("upsert@example.com", "upsert");
+    let id = match repo
+        .upsert_by_email(&created, &pwd(&created), false)
+        .await
+        .unwrap()
+    {
+        SaveOutcome::Created(id) => id,
+        other => panic!("expected created, got {other:?}"),
+    };
+
+    let updated = reg_with_password("UPSERT@example.com", "upserted", "other-password");
+    let outcome = repo
+        .upsert_by_email(&updated, &pwd(&updated), false)
+        .await
+        .unwrap();
+    assert_eq!(outcome, SaveOutcome::Existing(id));
+    let entry = repo
+        .get_by_email(&"upsert@example.com".into(), AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert_eq!(entry.user.username, "upserted");
+    assert!(entry.verify_password("test-password"));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn upsert_by_email_overwrites_the_password_if_requested() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let created = reg("upsert@example.com", "upsert");
+    repo.upsert_by_email(&created, &pwd(&created), false)
+        .await
+        .unwrap();
+
+    let updated = reg_with_password("upsert@example.com", "upsert", "other-password");
+    repo.upsert_by_email(&updated, &pwd(&updated), true)
+        .await
+        .unwrap();
+    let entry = repo
+        .get_by_email(&"upsert@example.com".into(), AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert!(entry.verify_password("other-password"));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn upsert_by_email_does_not_update_a_deleted_account() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let created = reg("upsert@example.com", "upsert");
+    let saved = repo.save(&created, &pwd(&created), None).await.unwrap();
+    repo.soft_delete(&UserId::from_trusted(saved.id))
+        .await
+        .unwrap();
+
+    let updated = reg("upsert@example.com", "upserted");
+    assert!(matches!(
+        repo.upsert_by_email(&updated, &pwd(&updated), true).await,
+        Err(AppError::Conflict(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn update_password_replaces_the_salt_and_bumps_the_version() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("pwd@example.com", "pwd");
+    let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+    let id = UserId::from_trusted(saved.id);
+    let before = repo
+        .get_by_id(&id, AppUseCase::AnyTokenProtectedOperation)
+        .await
+        .unwrap();
+
+    let new_pwd = Password::generate("other-password").unwrap();
+    repo.update_password(&id, &new_pwd).await.unwrap();
+    let after = repo
+        .get_by_id(&id, AppUseCase::AnyTokenProtectedOperation)
+        .await
+        .unwrap();
+    assert!(after.verify_password("other-password"));
+    assert!(!after.verify_password("test-password"));
+    assert_eq!(after.version, before.version + 1);
+    let salts: Vec<String> = sqlx::query_scalar("SELECT salt FROM accounts WHERE id = $1")
+        .bind(saved.id)
+        .fetch_all(&db.dbcp)
+        .await
+        .unwrap();
+    assert_eq!(salts, vec![new_pwd.salt]);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn update_password_of_a_deleted_user_is_not_found() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("pwd@example.com", "pwd");
+    let saved = repo.save(&reg, &pwd(&reg), None).await.unwrap();
+    let id = UserId::from_trusted(saved.id);
+    repo.soft_delete(&id).await.unwrap();
+    assert!(matches!(
+        repo.update_password(&id, &pwd(&reg)).await,
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn counts_the_followers_and_followings() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    // Both bob and cid follow ann, and bob follows cid.
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[2], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[1], &"cid".into()).await.unwrap();
+
+    let counts = |id: &UserId| {
+        let (repo, id) = (&repo, id.as_value());
+        async move {
+            (
+                repo.count_followers(id).await.unwrap(),
+                repo.count_following(id).await.unwrap(),
+            )
+        }
+    };
+    assert_eq!(counts(&ids[0]).await, (2, 0));
+    assert_eq!(counts(&ids[1]).await, (0, 2));
+    assert_eq!(counts(&ids[2]).await, (1, 1));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn counts_of_an_unknown_user_are_not_found() {
+    let db = TestDb::new().await;
+    assert!(matches!(
+        users_repo(&db).count_followers(i64::MAX).await,
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn save_or_get_reports_the_same_email_imported_twice_as_existing() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let first = reg("import@example.com", "import");
+    let id = match repo.save_or_get(&first, &pwd(&first)).await.unwrap() {
+        SaveOutcome::Created(id) => id,
+        other => panic!("expected created, got {other:?}"),
+    };
+    let second = reg("Import@example.com", "imported");
+    assert_eq!(
+        repo.save_or_get(&second, &pwd(&second)).await.unwrap(),
+        SaveOutcome::Existing(id)
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn save_or_get_reports_a_too_similar_username_as_existing() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let first = reg("latin@example.com", "paypal");
+    let id = match repo.save_or_get(&first, &pwd(&first)).await.unwrap() {
+        SaveOutcome::Created(id) => id,
+        other => panic!("expected created, got {other:?}"),
+    };
+    // Having a Cyrillic 'а' (U+0430).
+    let second = reg("cyrillic@example.com", "p\u{0430}ypal");
+    assert_eq!(
+        repo.save_or_get(&second, &pwd(&second)).await.unwrap(),
+        SaveOutcome::Existing(id)
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_profile_by_id_has_the_counts_of_the_followings() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[2], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[0], &"bob".into()).await.unwrap();
+
+    let profile = repo
+        .get_profile_by_id(&ids[0], Some(&ids[1]))
+        .await
+        .unwrap();
+    // The counts are separately computed from the followings.
+    let (followers, following): (i64, i64) = sqlx::query_as(
+        "SELECT (SELECT count(*) FROM followings WHERE followed_user_id = $1),
+                (SELECT count(*) FROM followings WHERE user_id = $1)",
+    )
+    .bind(ids[0].as_value())
+    .fetch_one(&db.dbcp)
+    .await
+    .unwrap();
+    assert_eq!(profile.followers_count, Some(followers));
+    assert_eq!(profile.following_count, Some(following));
+    assert!(profile.following);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn recompute_follow_counts_fixes_the_zeroed_counts() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[2], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[0], &"cid".into()).await.unwrap();
+    sqlx::query("UPDATE accounts SET follower_count = 0, following_count = 0")
+        .execute(&db.dbcp)
+        .await
+        .unwrap();
+
+    repo.recompute_follow_counts().await.unwrap();
+    let counts = [(2, 1), (0, 1), (1, 1)];
+    for (id, (followers, following)) in ids.iter().zip(counts) {
+        assert_eq!(
+            repo.count_followers(id.as_value()).await.unwrap(),
+            followers
+        );
+        assert_eq!(
+            repo.count_following(id.as_value()).await.unwrap(),
+            following
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_counts_return_to_zero_after_an_unfollow() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+
+    let followed = repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+    assert_eq!(followed.followers_count, Some(1));
+    let bob = repo.get_profile_by_id(&ids[1], None).await.unwrap();
+    assert_eq!(bob.following_count, Some(1));
+
+    let unfollowed = repo.unfollow_user(&ids[1], &"ann".into()).await.unwrap();
+    assert_eq!(unfollowed.followers_count, Some(0));
+    let bob = repo.get_profile_by_id(&ids[1], None).await.unwrap();
+    assert_eq!(bob.following_count, Some(0));
+    // Unfollowing again does not make them negative.
+    let unfollowed = repo.unfollow_user(&ids[1], &"ann".into()).await.unwrap();
+    assert_eq!(unfollowed.followers_count, Some(0));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn get_users_by_ids_skips_the_missing_ones() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    let requested = [ids[0].as_value(), i64::MAX, ids[1].as_value()];
+    let users = repo.get_users_by_ids(&requested).await.unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[&ids[0].as_value()].username, "ann");
+    assert_eq!(users[&ids[1].as_value()].username, "bob");
+    assert!(repo.get_users_by_ids(&[]).await.unwrap().is_empty());
+}
+
+/// A tracing layer that captures the (`name=value`) fields of all the spans and events.
+#[derive(Clone, Default)]
+struct RecordedFields(Arc<Mutex<Vec<String>>>);
+
+impl RecordedFields {
+    fn push(&self, field: &Field, val: &dyn std::fmt::Debug) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}={val:?}", field.name()));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RecordedFields {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        attrs.record(&mut |field: &Field, val: &dyn std::fmt::Debug| self.push(field, val));
+    }
+
+    fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        values.record(&mut |field: &Field, val: &dyn std::fmt::Debug| self.push(field, val));
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        event.record(&mut |field: &Field, val: &dyn std::fmt::Debug| self.push(field, val));
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_spans_do_not_record_the_password() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let recorded = RecordedFields::default();
+    let _guard = tracing::subscriber::set_default(registry().with(recorded.clone()));
+
+    let reg = reg_with_password("ann@example.com", "ann", "secret-password");
+    let password = pwd(&reg);
+    let saved = repo.save(&reg, &password, None).await.unwrap();
+    let id = UserId::from_trusted(saved.id);
+    repo.get_by_email(&"ann@example.com".into(), AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    repo.get_by_id(&id, AppUseCase::AnyTokenProtectedOperation)
+        .await
+        .unwrap();
+    repo.update_password(&id, &Password::generate("other-password").unwrap())
+        .await
+        .unwrap();
+
+    let recorded = recorded.0.lock().unwrap();
+    assert!(
+        recorded.contains(&"username=ann".to_string()),
+        "{recorded:?}"
+    );
+    for secret in [
+        "secret-password",
+        "other-password",
+        password.hash.as_str(),
+        password.salt.as_str(),
+    ] {
+        assert!(
+            !recorded.iter().any(|field| field.contains(secret)),
+            "{secret} in {recorded:?}"
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_repeated_idempotency_key_saves_a_single_account() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("ann@example.com", "ann");
+    let first = repo.save(&reg, &pwd(&reg), Some("key-1")).await.unwrap();
+    let second = repo.save(&reg, &pwd(&reg), Some("key-1")).await.unwrap();
+    assert_eq!(second.id, first.id);
+    assert_eq!(repo.count().await.unwrap(), 1);
+
+    // The same registration with another key is a duplicate.
+    assert!(matches!(
+        repo.save(&reg, &pwd(&reg), Some("key-2")).await,
+        Err(AppError::AlreadyExists(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_self_follow_bypassing_the_app_is_rejected_by_the_database() {
+    let db = TestDb::new().await;
+    let reg = reg("self@example.com", "self");
+    let saved = users_repo(&db).save(&reg, &pwd(&reg), None).await.unwrap();
+    let err = sqlx::query("INSERT INTO followings (user_id, followed_user_id) VALUES ($1, $1)")
+        .bind(saved.id)
+        .execute(&db.dbcp)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        AppError::from((err, AppUseCase::FollowUser)),
+        AppError::InvalidRequest(msg) if msg == SELF_FOLLOW_MSG
+    ));
+}
+
+/// It counts the queries (logged by `sqlx`) that are issued by the current thread, thus by
+/// the (current thread runtime of the) running test.
+struct QueryCounter;
+
+thread_local! {
+    static QUERIES: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+impl log::Log for QueryCounter {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "sqlx::query"
+    }
+
+    fn log(&self, record: &log::Record) {
+        // The `search_path` of each new connection is not a query of the test.
+        if self.enabled(record.metadata()) && !record.args().to_string().starts_with("SET") {
+            QUERIES.with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The number of queries that are issued by the provided future.
+async fn count_queries<F: std::future::Future>(fut: F) -> (F::Output, usize) {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&QueryCounter).expect("Failed to set the query counter.");
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+    let before = QUERIES.with(|count| count.get());
+    let output = fut.await;
+    (output, QUERIES.with(|count| count.get()) - before)
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn save_returns_the_inserted_row_with_one_query() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let reg = reg("Ann@Example.com", "ann");
+    let pwd = pwd(&reg);
+    let (saved, queries) = count_queries(repo.save(&reg, &pwd, None)).await;
+    let saved = saved.unwrap();
+    assert_eq!(queries, 1);
+
+    let (id, email, username, created_at): (i64, String, String, chrono::DateTime<chrono::Utc>) =
+        sqlx::query_as("SELECT id, email, username, created_at FROM accounts")
+            .fetch_one(&db.dbcp)
+            .await
+            .unwrap();
+    assert_eq!(saved.id, id);
+    assert_eq!(saved.email.as_str(), email);
+    assert_eq!(saved.username, username);
+    assert_eq!(saved.bio, "");
+    assert_eq!(saved.image, None);
+    assert_eq!(saved.created_at, created_at);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn exists_only_for_the_present_active_users() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    assert!(repo.exists(&ids[0]).await.unwrap());
+    assert!(!repo.exists(&UserId::from_trusted(i64::MAX)).await.unwrap());
+    repo.soft_delete(&ids[1]).await.unwrap();
+    assert!(!repo.exists(&ids[1]).await.unwrap());
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_double_follow_keeps_a_single_following() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    for _ in 0..2 {
+        let profile = repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+        assert!(profile.following);
+        assert_eq!(profile.followers_count, Some(1));
+    }
+    let followings: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM followings WHERE user_id = $1 AND followed_user_id = $2",
+    )
+    .bind(ids[1].as_value())
+    .bind(ids[0].as_value())
+    .fetch_one(&db.dbcp)
+    .await
+    .unwrap();
+    assert_eq!(followings, 1);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn update_returns_the_stored_row() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let before = repo
+        .get_by_id(&ids[0], AppUseCase::UpdateUser)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let changes = UserUpdate {
+        bio: Some("updated".into()),
+        ..UserUpdate::default()
+    };
+    let updated = repo
+        .update_by_id(&ids[0], ids[0].clone(), changes)
+        .await
+        .unwrap();
+    // The `updated_at` (set by the database) is populated, same as the stored one.
+    let stored = repo
+        .get_by_id(&ids[0], AppUseCase::UpdateUser)
+        .await
+        .unwrap();
+    assert!(updated.user.updated_at > before.user.updated_at);
+    assert_eq!(updated.user.updated_at, stored.user.updated_at);
+    assert_eq!(updated.user.bio, "updated");
+    assert_eq!(updated.version, before.version + 1);
+    assert_eq!(updated.version, stored.version);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn save_rejects_a_mixed_script_near_duplicate_username() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let first = reg("latin@example.com", "paypal");
+    repo.save(&first, &pwd(&first), None).await.unwrap();
+    // Having a Cyrillic 'а' (U+0430).
+    let second = reg("cyrillic@example.com", "p\u{0430}ypal");
+    assert!(matches!(
+        repo.save(&second, &pwd(&second), None).await,
+        Err(AppError::Conflict(_))
+    ));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_following_flag_is_not_resolved_from_the_targets_followings() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    // bob follows ann, while ann (the target) follows cid.
+    repo.follow_user(&ids[1], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[0], &"cid".into()).await.unwrap();
+
+    for (viewer, following) in [
+        (Some(&ids[1]), true),
+        (Some(&ids[0]), false),
+        (Some(&ids[2]), false),
+        (None, false),
+    ] {
+        let profile = repo
+            .get_profile_by_username(viewer, &"ann".into(), AppUseCase::GetUserProfile)
+            .await
+            .unwrap();
+        assert_eq!(profile.following, following, "for viewer {viewer:?}");
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_hard_delete_leaves_no_followings_of_the_user() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob", "cid"]).await;
+    // ann follows bob, cid follows ann, and bob follows cid.
+    repo.follow_user(&ids[0], &"bob".into()).await.unwrap();
+    repo.follow_user(&ids[2], &"ann".into()).await.unwrap();
+    repo.follow_user(&ids[1], &"cid".into()).await.unwrap();
+
+    repo.hard_delete(&ids[0]).await.unwrap();
+    let remaining: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM followings WHERE user_id = $1 OR followed_user_id = $1",
+    )
+    .bind(ids[0].as_value())
+    .fetch_one(&db.dbcp)
+    .await
+    .unwrap();
+    assert_eq!(remaining, 0);
+    assert!(repo.get_by_id_opt(&ids[0]).await.unwrap().is_none());
+
+    // The followings among the others remain, and their counts are adjusted.
+    let bob = repo.get_profile_by_id(&ids[1], None).await.unwrap();
+    assert_eq!(bob.followers_count, Some(0));
+    assert_eq!(bob.following_count, Some(1));
+    let cid = repo.get_profile_by_id(&ids[2], None).await.unwrap();
+    assert_eq!(cid.followers_count, Some(1));
+    assert_eq!(cid.following_count, Some(0));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn a_row_of_the_legacy_algo_still_verifies() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann", "bob"]).await;
+    // ann's row is tagged (and hashed) as before switching to Argon2.
+    // The salt has all the 12 chars of its column, thus it is read back unpadded.
+    let legacy_hash = format!("{:x}", md5::compute("@legacy-salt1$old-password"));
+    sqlx::query("UPDATE accounts SET password = $1, salt = $2, pwd_algo = $3 WHERE id = $4")
+        .bind(&legacy_hash)
+        .bind("legacy-salt1")
+        .bind(PasswordAlgo::Md5.as_str())
+        .bind(ids[0].as_value())
+        .execute(&db.dbcp)
+        .await
+        .unwrap();
+
+    let ann = repo
+        .get_by_id(&ids[0], AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert_eq!(ann.password().algo, PasswordAlgo::Md5);
+    assert!(ann.verify_password("old-password"));
+    assert!(!ann.verify_password("test-password"));
+
+    // While the new rows use the current algo.
+    let bob = repo
+        .get_by_id(&ids[1], AppUseCase::UserLogin)
+        .await
+        .unwrap();
+    assert_eq!(bob.password().algo, PasswordAlgo::CURRENT);
+    assert!(bob.verify_password("test-password"));
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_search_total_counts_all_the_matches() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    save_users(&repo, &["ann1", "ann2", "ann3", "ann4", "ann5", "bob"]).await;
+
+    let page = Pagination::new(Some(0), Some(2)).unwrap();
+    let found = repo.search_by_username_prefix("ann", &page).await.unwrap();
+    let usernames: Vec<_> = found.items.iter().map(|p| p.username.as_str()).collect();
+    assert_eq!(usernames, ["ann1", "ann2"]);
+    assert_eq!(found.total, 5);
+    assert!(found.has_more);
+
+    let page = Pagination::new(Some(4), Some(2)).unwrap();
+    let found = repo.search_by_username_prefix("ann", &page).await.unwrap();
+    let usernames: Vec<_> = found.items.iter().map(|p| p.username.as_str()).collect();
+    assert_eq!(usernames, ["ann5"]);
+    assert_eq!(found.total, 5);
+    assert!(!found.has_more);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn an_image_update_keeps_sets_or_clears_the_image() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+    let ids = save_users(&repo, &["ann"]).await;
+    let update_image = |image: FieldUpdate<String>| {
+        let changes = UserUpdate {
+            bio: Some("bio".into()),
+            image,
+            ..UserUpdate::default()
+        };
+        repo.update_by_id(&ids[0], ids[0].clone(), changes)
+    };
+    let image = "https://example.com/ann.png";
+
+    let updated = update_image(FieldUpdate::Set(image.into())).await.unwrap();
+    assert_eq!(updated.user.image.as_deref(), Some(image));
+    let updated = update_image(FieldUpdate::Keep).await.unwrap();
+    assert_eq!(updated.user.image.as_deref(), Some(image));
+    let updated = update_image(FieldUpdate::Clear).await.unwrap();
+    assert_eq!(updated.user.image, None);
+    let stored: Option<String> = sqlx::query_scalar("SELECT image FROM accounts WHERE id = $1")
+        .bind(ids[0].as_value())
+        .fetch_one(&db.dbcp)
+        .await
+        .unwrap();
+    assert_eq!(stored, None);
+}
+
+#[tokio::test]
+#[ignore = "needs a database"]
+async fn the_clones_of_the_repo_share_the_pool() {
+    let db = TestDb::new().await;
+    let repo = users_repo(&db);
+
+    // Each clone is moved into its own task.
+    let saving = tokio::spawn({
+        let repo = repo.clone();
+        async move { save_users(&repo, &["ann"]).await }
+    });
+    let ids = saving.await.unwrap();
+    let reading = tokio::spawn({
+        let (repo, id) = (repo.clone(), ids[0].clone());
+        async move { repo.get_profile_by_id(&id, None).await }
+    });
+    assert_eq!(reading.await.unwrap().unwrap().username, "ann");
+    // And the original one sees the same data.
+    assert!(repo.exists(&ids[0]).await.unwrap());
+}
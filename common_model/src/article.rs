@@ -0,0 +1,37 @@
+use crate::UserProfileDTO;
+use serde::{Deserialize, Serialize};
+
+/// An article, as exposed by the API.<br/>
+/// In the payload, this must be the value of the "article" attribute, see `ArticleEnvelope`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticleDTO {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub tag_list: Vec<String>,
+    /// The creation time (in RFC 3339 format).
+    pub created_at: String,
+    /// The last update time (in RFC 3339 format).
+    pub updated_at: String,
+    pub favorited: bool,
+    pub favorites_count: i64,
+    pub author: UserProfileDTO,
+}
+
+/// The payload of a single article.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArticleEnvelope {
+    pub article: ArticleDTO,
+}
+
+/// The payload of a page of articles.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArticlesEnvelope {
+    pub articles: Vec<ArticleDTO>,
+    /// The number of all articles (in all pages).
+    pub articles_count: i64,
+    pub has_more: bool,
+}
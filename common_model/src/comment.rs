@@ -0,0 +1,15 @@
+use crate::UserProfileDTO;
+use serde::{Deserialize, Serialize};
+
+/// A comment on an article, as exposed by the API.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentDTO {
+    pub id: i64,
+    /// The creation time (in RFC 3339 format).
+    pub created_at: String,
+    /// The last update time (in RFC 3339 format).
+    pub updated_at: String,
+    pub body: String,
+    pub author: UserProfileDTO,
+}
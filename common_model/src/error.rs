@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// This is returned in case of a failed operation.<br/>
+/// The `code` is stable, so it can be used for (localized) messages, instead of the `error` description.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorDTO {
+    pub error: String,
+    pub code: Option<String>,
+}
@@ -1,3 +1,11 @@
+mod article;
+mod comment;
+mod error;
 mod login;
+mod profile;
 
+pub use article::*;
+pub use comment::*;
+pub use error::*;
 pub use login::*;
+pub use profile::*;
@@ -9,10 +9,17 @@ pub struct SuccessfulLoginDTO {
 /// In the payload, this must be the value of the "user" attribute<br/>
 /// aka using such return `(StatusCode::OK, Json(json!({ "user": dto })))`
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UserDTO {
     pub email: String,
     pub token: Option<String>,
     pub username: String,
     pub bio: String,
     pub image: Option<String>,
+    /// The creation time (in RFC 3339 format), if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// The last update time (in RFC 3339 format), if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
 }
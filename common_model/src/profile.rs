@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// The public profile of a user.<br/>
+/// In the payload, this must be the value of the "profile" attribute, see `ProfileEnvelope`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserProfileDTO {
+    pub username: String,
+    pub bio: String,
+    pub image: Option<String>,
+    pub following: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub followers_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub following_count: Option<i64>,
+}
+
+/// The payload of a profile.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileEnvelope {
+    pub profile: UserProfileDTO,
+}